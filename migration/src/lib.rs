@@ -8,6 +8,21 @@ mod m20241121_000005_create_table_message;
 mod m20241121_000006_create_table_feed;
 mod m20241121_000007_create_table_group;
 mod m20241202_000008_create_table_member;
+mod m20241215_000009_alter_table_upload;
+mod m20241216_000010_alter_table_group;
+mod m20241218_000011_create_table_invite;
+mod m20241220_000012_alter_table_member;
+mod m20241222_000013_alter_table_member;
+mod m20241224_000014_create_table_pinned_message;
+mod m20241226_000015_create_table_announcement;
+mod m20241228_000016_create_table_block;
+mod m20241230_000017_create_table_category;
+mod m20250102_000018_alter_table_user;
+mod m20250104_000019_alter_table_user;
+mod m20250106_000020_create_table_user_token;
+mod m20250108_000021_create_table_recovery_code;
+mod m20250110_000022_create_table_login_audit;
+mod m20250112_000023_alter_table_user;
 
 /// 数据库定义
 pub struct Migrator;
@@ -24,6 +39,21 @@ impl MigratorTrait for Migrator {
             Box::new(m20241121_000006_create_table_feed::Migration),
             Box::new(m20241121_000007_create_table_group::Migration),
             Box::new(m20241202_000008_create_table_member::Migration),
+            Box::new(m20241215_000009_alter_table_upload::Migration),
+            Box::new(m20241216_000010_alter_table_group::Migration),
+            Box::new(m20241218_000011_create_table_invite::Migration),
+            Box::new(m20241220_000012_alter_table_member::Migration),
+            Box::new(m20241222_000013_alter_table_member::Migration),
+            Box::new(m20241224_000014_create_table_pinned_message::Migration),
+            Box::new(m20241226_000015_create_table_announcement::Migration),
+            Box::new(m20241228_000016_create_table_block::Migration),
+            Box::new(m20241230_000017_create_table_category::Migration),
+            Box::new(m20250102_000018_alter_table_user::Migration),
+            Box::new(m20250104_000019_alter_table_user::Migration),
+            Box::new(m20250106_000020_create_table_user_token::Migration),
+            Box::new(m20250108_000021_create_table_recovery_code::Migration),
+            Box::new(m20250110_000022_create_table_login_audit::Migration),
+            Box::new(m20250112_000023_alter_table_user::Migration),
         ]
     }
 }
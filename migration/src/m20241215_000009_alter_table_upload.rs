@@ -0,0 +1,76 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20241012_000001_create_table_user::User;
+use super::m20241014_000002_create_table_upload::Upload;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20241215_000009_alter_table_upload"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Upload::Table)
+                    .add_column(ColumnDef::new(UploadOwner::Uploader).uuid())
+                    .add_column(
+                        ColumnDef::new(UploadOwner::Size)
+                            .big_integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .add_column(
+                        ColumnDef::new(UploadOwner::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .extra("DEFAULT now()::TIMESTAMP"),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_foreign_key(
+                ForeignKey::create()
+                    .from(Upload::Table, UploadOwner::Uploader)
+                    .to(User::Table, User::Id)
+                    .on_delete(ForeignKeyAction::SetNull)
+                    .name("FK_UPLOAD_UPLOADER_USER_ID")
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_foreign_key(
+                ForeignKey::drop()
+                    .name("FK_UPLOAD_UPLOADER_USER_ID")
+                    .table(Upload::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Upload::Table)
+                    .drop_column(UploadOwner::Uploader)
+                    .drop_column(UploadOwner::Size)
+                    .drop_column(UploadOwner::CreatedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum UploadOwner {
+    Uploader,
+    Size,
+    CreatedAt,
+}
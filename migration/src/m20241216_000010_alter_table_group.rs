@@ -0,0 +1,71 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20241014_000002_create_table_upload::Upload;
+use super::m20241121_000007_create_table_group::Group;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20241216_000010_alter_table_group"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Group::Table)
+                    .add_column(ColumnDef::new(GroupProfile::Avatar).uuid())
+                    .add_column(ColumnDef::new(GroupProfile::Description).string())
+                    .add_column(
+                        ColumnDef::new(GroupProfile::Settings)
+                            .json_binary()
+                            .not_null()
+                            .default(Expr::cust("'{}'::jsonb")),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_foreign_key(
+                ForeignKey::create()
+                    .from(Group::Table, GroupProfile::Avatar)
+                    .to(Upload::Table, Upload::Uuid)
+                    .on_delete(ForeignKeyAction::SetNull)
+                    .name("FK_GROUP_AVATAR_UPLOAD_UUID")
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_foreign_key(
+                ForeignKey::drop()
+                    .name("FK_GROUP_AVATAR_UPLOAD_UUID")
+                    .table(Group::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Group::Table)
+                    .drop_column(GroupProfile::Avatar)
+                    .drop_column(GroupProfile::Description)
+                    .drop_column(GroupProfile::Settings)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum GroupProfile {
+    Avatar,
+    Description,
+    Settings,
+}
@@ -0,0 +1,111 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20241012_000001_create_table_user::User;
+use super::m20241121_000007_create_table_group::Group;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20241218_000011_create_table_invite"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Invite::Table)
+                    .col(
+                        ColumnDef::new(Invite::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .extra("DEFAULT uuid_generate_v4()"),
+                    )
+                    .col(
+                        ColumnDef::new(Invite::Code)
+                            .string()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(ColumnDef::new(Invite::Group).uuid().not_null())
+                    .col(ColumnDef::new(Invite::Creator).uuid())
+                    .col(
+                        ColumnDef::new(Invite::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .extra("DEFAULT now()::TIMESTAMP"),
+                    )
+                    .col(ColumnDef::new(Invite::ExpiresAt).timestamp())
+                    .col(ColumnDef::new(Invite::MaxUses).integer())
+                    .col(ColumnDef::new(Invite::Uses).integer().not_null().default(0))
+                    .col(
+                        ColumnDef::new(Invite::Revoked)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_foreign_key(
+                ForeignKey::create()
+                    .from(Invite::Table, Invite::Group)
+                    .to(Group::Table, Group::Id)
+                    .on_delete(ForeignKeyAction::Cascade)
+                    .name("FK_INVITE_GROUP_GROUP_ID")
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_foreign_key(
+                ForeignKey::create()
+                    .from(Invite::Table, Invite::Creator)
+                    .to(User::Table, User::Id)
+                    .on_delete(ForeignKeyAction::SetNull)
+                    .name("FK_INVITE_CREATOR_USER_ID")
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_foreign_key(
+                ForeignKey::drop()
+                    .name("FK_INVITE_CREATOR_USER_ID")
+                    .table(Invite::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_foreign_key(
+                ForeignKey::drop()
+                    .name("FK_INVITE_GROUP_GROUP_ID")
+                    .table(Invite::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Invite::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum Invite {
+    Table,
+    Id,
+    Code,
+    Group,
+    Creator,
+    CreatedAt,
+    ExpiresAt,
+    MaxUses,
+    Uses,
+    Revoked,
+}
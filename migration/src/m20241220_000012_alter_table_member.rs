@@ -0,0 +1,43 @@
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20241220_000012_alter_table_member"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    /// 群主的成员记录改为角色 `2`, 群聊设置 `allow_member_invite` 迁移至 `permissions.invite`
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            r#"UPDATE member SET permission = 2 FROM "group"
+            WHERE member."group" = "group".id AND member."user" = "group".owner"#,
+        )
+        .await?;
+        db.execute_unprepared(
+            r#"UPDATE "group" SET settings = (settings - 'allow_member_invite')
+            || jsonb_build_object('permissions', jsonb_build_object('invite',
+                CASE WHEN (settings->>'allow_member_invite')::BOOLEAN IS FALSE
+                THEN 'admin' ELSE 'member' END))"#,
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            r#"UPDATE "group" SET settings = (settings - 'permissions')
+            || jsonb_build_object('allow_member_invite',
+                COALESCE(settings#>>'{permissions,invite}', 'member') = 'member')"#,
+        )
+        .await?;
+        db.execute_unprepared("UPDATE member SET permission = 0 WHERE permission = 2")
+            .await?;
+        Ok(())
+    }
+}
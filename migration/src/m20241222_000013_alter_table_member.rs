@@ -0,0 +1,41 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20241202_000008_create_table_member::Member;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20241222_000013_alter_table_member"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Member::Table)
+                    .add_column(ColumnDef::new(MemberBan::BannedUntil).timestamp())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Member::Table)
+                    .drop_column(MemberBan::BannedUntil)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum MemberBan {
+    BannedUntil,
+}
@@ -0,0 +1,107 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20241012_000001_create_table_user::User;
+use super::m20241110_000004_create_table_session::Session;
+use super::m20241121_000005_create_table_message::Message;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20241224_000014_create_table_pinned_message"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(PinnedMessage::Table)
+                    .col(
+                        ColumnDef::new(PinnedMessage::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .extra("DEFAULT uuid_generate_v4()"),
+                    )
+                    .col(ColumnDef::new(PinnedMessage::Session).uuid().not_null())
+                    .col(
+                        ColumnDef::new(PinnedMessage::Message)
+                            .uuid()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(ColumnDef::new(PinnedMessage::PinnedBy).uuid())
+                    .col(
+                        ColumnDef::new(PinnedMessage::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .extra("DEFAULT now()::TIMESTAMP"),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_foreign_key(
+                ForeignKey::create()
+                    .from(PinnedMessage::Table, PinnedMessage::Session)
+                    .to(Session::Table, Session::Id)
+                    .on_delete(ForeignKeyAction::Cascade)
+                    .name("FK_PINNED_MESSAGE_SESSION_SESSION_ID")
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_foreign_key(
+                ForeignKey::create()
+                    .from(PinnedMessage::Table, PinnedMessage::Message)
+                    .to(Message::Table, Message::Id)
+                    .on_delete(ForeignKeyAction::Cascade)
+                    .name("FK_PINNED_MESSAGE_MESSAGE_MESSAGE_ID")
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_foreign_key(
+                ForeignKey::create()
+                    .from(PinnedMessage::Table, PinnedMessage::PinnedBy)
+                    .to(User::Table, User::Id)
+                    .on_delete(ForeignKeyAction::SetNull)
+                    .name("FK_PINNED_MESSAGE_PINNED_BY_USER_ID")
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for name in [
+            "FK_PINNED_MESSAGE_PINNED_BY_USER_ID",
+            "FK_PINNED_MESSAGE_MESSAGE_MESSAGE_ID",
+            "FK_PINNED_MESSAGE_SESSION_SESSION_ID",
+        ] {
+            manager
+                .drop_foreign_key(
+                    ForeignKey::drop()
+                        .name(name)
+                        .table(PinnedMessage::Table)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        manager
+            .drop_table(Table::drop().table(PinnedMessage::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum PinnedMessage {
+    Table,
+    Id,
+    Session,
+    Message,
+    PinnedBy,
+    CreatedAt,
+}
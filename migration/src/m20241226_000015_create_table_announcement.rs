@@ -0,0 +1,84 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20241121_000005_create_table_message::Message;
+use super::m20241121_000006_create_table_feed::Feed;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20241226_000015_create_table_announcement"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Announcement::Table)
+                    .col(
+                        ColumnDef::new(Announcement::Message)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Announcement::Title).string().not_null())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_foreign_key(
+                ForeignKey::create()
+                    .from(Announcement::Table, Announcement::Message)
+                    .to(Message::Table, Message::Id)
+                    .on_delete(ForeignKeyAction::Cascade)
+                    .name("FK_ANNOUNCEMENT_MESSAGE_MESSAGE_ID")
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Feed::Table)
+                    .add_column(ColumnDef::new(FeedConfirm::ConfirmedAt).timestamp())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Feed::Table)
+                    .drop_column(FeedConfirm::ConfirmedAt)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_foreign_key(
+                ForeignKey::drop()
+                    .name("FK_ANNOUNCEMENT_MESSAGE_MESSAGE_ID")
+                    .table(Announcement::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Announcement::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum Announcement {
+    Table,
+    Message,
+    Title,
+}
+
+#[derive(Iden)]
+enum FeedConfirm {
+    ConfirmedAt,
+}
@@ -0,0 +1,110 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20241012_000001_create_table_user::User;
+use super::m20241028_000003_create_table_contact::Contact;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20241228_000016_create_table_block"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Contact::Table)
+                    .add_column(ColumnDef::new(ContactRequest::Message).string())
+                    .add_column(ColumnDef::new(ContactRequest::Source).string())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(Block::Table)
+                    .col(
+                        ColumnDef::new(Block::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .extra("DEFAULT uuid_generate_v4()"),
+                    )
+                    .col(ColumnDef::new(Block::User).uuid().not_null())
+                    .col(ColumnDef::new(Block::Blocked).uuid().not_null())
+                    .col(
+                        ColumnDef::new(Block::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .extra("DEFAULT now()::TIMESTAMP"),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("IDX_BLOCK_USER_BLOCKED")
+                    .table(Block::Table)
+                    .col(Block::User)
+                    .col(Block::Blocked)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_foreign_key(
+                ForeignKey::create()
+                    .from(Block::Table, Block::User)
+                    .to(User::Table, User::Id)
+                    .on_delete(ForeignKeyAction::Cascade)
+                    .name("FK_BLOCK_USER_USER_ID")
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_foreign_key(
+                ForeignKey::create()
+                    .from(Block::Table, Block::Blocked)
+                    .to(User::Table, User::Id)
+                    .on_delete(ForeignKeyAction::Cascade)
+                    .name("FK_BLOCK_BLOCKED_USER_ID")
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Block::Table).to_owned())
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Contact::Table)
+                    .drop_column(ContactRequest::Message)
+                    .drop_column(ContactRequest::Source)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum ContactRequest {
+    Message,
+    Source,
+}
+
+#[derive(Iden)]
+pub enum Block {
+    Table,
+    Id,
+    User,
+    Blocked,
+    CreatedAt,
+}
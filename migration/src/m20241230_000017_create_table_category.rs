@@ -0,0 +1,149 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20241012_000001_create_table_user::User;
+use super::m20241028_000003_create_table_contact::Contact;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20241230_000017_create_table_category"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    /// 好友分组由 `contact.category` 字符串迁移至 `category` 表
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Category::Table)
+                    .col(
+                        ColumnDef::new(Category::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .extra("DEFAULT uuid_generate_v4()"),
+                    )
+                    .col(ColumnDef::new(Category::User).uuid().not_null())
+                    .col(ColumnDef::new(Category::Name).string().not_null())
+                    .col(
+                        ColumnDef::new(Category::Position)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(Category::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .extra("DEFAULT now()::TIMESTAMP"),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("IDX_CATEGORY_USER_NAME")
+                    .table(Category::Table)
+                    .col(Category::User)
+                    .col(Category::Name)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_foreign_key(
+                ForeignKey::create()
+                    .from(Category::Table, Category::User)
+                    .to(User::Table, User::Id)
+                    .on_delete(ForeignKeyAction::Cascade)
+                    .name("FK_CATEGORY_USER_USER_ID")
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Contact::Table)
+                    .add_column(ColumnDef::new(ContactCategory::CategoryId).uuid())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_foreign_key(
+                ForeignKey::create()
+                    .from(Contact::Table, ContactCategory::CategoryId)
+                    .to(Category::Table, Category::Id)
+                    .on_delete(ForeignKeyAction::SetNull)
+                    .name("FK_CONTACT_CATEGORY_ID_CATEGORY_ID")
+                    .to_owned(),
+            )
+            .await?;
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            r#"INSERT INTO category ("user", name, position)
+            SELECT "user", category, (ROW_NUMBER() OVER (PARTITION BY "user" ORDER BY category) - 1)::INTEGER
+            FROM (SELECT DISTINCT "user", category FROM contact WHERE category IS NOT NULL) AS c"#,
+        )
+        .await?;
+        db.execute_unprepared(
+            r#"UPDATE contact SET category_id = category.id FROM category
+            WHERE contact."user" = category."user" AND contact.category = category.name"#,
+        )
+        .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Contact::Table)
+                    .drop_column(Contact::Category)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Contact::Table)
+                    .add_column(ColumnDef::new(Contact::Category).string())
+                    .to_owned(),
+            )
+            .await?;
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            r#"UPDATE contact SET category = category.name FROM category
+            WHERE contact.category_id = category.id"#,
+        )
+        .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Contact::Table)
+                    .drop_column(ContactCategory::CategoryId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Category::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum ContactCategory {
+    CategoryId,
+}
+
+#[derive(Iden)]
+pub enum Category {
+    Table,
+    Id,
+    User,
+    Name,
+    Position,
+    CreatedAt,
+}
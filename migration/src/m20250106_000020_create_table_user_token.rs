@@ -0,0 +1,97 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20241012_000001_create_table_user::User;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20250106_000020_create_table_user_token"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    /// 邮箱验证与重置密码的一次性令牌, 仅保存令牌的摘要
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(UserToken::Table)
+                    .col(
+                        ColumnDef::new(UserToken::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .extra("DEFAULT uuid_generate_v4()"),
+                    )
+                    .col(ColumnDef::new(UserToken::User).uuid().not_null())
+                    .col(ColumnDef::new(UserToken::Purpose).string().not_null())
+                    .col(
+                        ColumnDef::new(UserToken::TokenHash)
+                            .string()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(ColumnDef::new(UserToken::Email).string().not_null())
+                    .col(ColumnDef::new(UserToken::ExpiresAt).timestamp().not_null())
+                    .col(
+                        ColumnDef::new(UserToken::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .extra("DEFAULT now()::TIMESTAMP"),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_foreign_key(
+                ForeignKey::create()
+                    .from(UserToken::Table, UserToken::User)
+                    .to(User::Table, User::Id)
+                    .on_delete(ForeignKeyAction::Cascade)
+                    .name("FK_USER_TOKEN_USER_USER_ID")
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .add_column(ColumnDef::new(UserVerification::EmailVerifiedAt).timestamp())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .drop_column(UserVerification::EmailVerifiedAt)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(UserToken::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum UserVerification {
+    EmailVerifiedAt,
+}
+
+#[derive(Iden)]
+pub enum UserToken {
+    Table,
+    Id,
+    User,
+    Purpose,
+    TokenHash,
+    Email,
+    ExpiresAt,
+    CreatedAt,
+}
@@ -0,0 +1,93 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20241012_000001_create_table_user::User;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20250108_000021_create_table_recovery_code"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    /// 两步验证的密钥, 最近一次通过验证的时间步与恢复码, 恢复码仅保存摘要
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .add_column(ColumnDef::new(UserTotp::TotpSecret).string())
+                    .add_column(ColumnDef::new(UserTotp::TotpEnabledAt).timestamp())
+                    .add_column(ColumnDef::new(UserTotp::TotpLastStep).big_integer())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(RecoveryCode::Table)
+                    .col(
+                        ColumnDef::new(RecoveryCode::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .extra("DEFAULT uuid_generate_v4()"),
+                    )
+                    .col(ColumnDef::new(RecoveryCode::User).uuid().not_null())
+                    .col(ColumnDef::new(RecoveryCode::CodeHash).string().not_null())
+                    .col(
+                        ColumnDef::new(RecoveryCode::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .extra("DEFAULT now()::TIMESTAMP"),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_foreign_key(
+                ForeignKey::create()
+                    .from(RecoveryCode::Table, RecoveryCode::User)
+                    .to(User::Table, User::Id)
+                    .on_delete(ForeignKeyAction::Cascade)
+                    .name("FK_RECOVERY_CODE_USER_USER_ID")
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(RecoveryCode::Table).to_owned())
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .drop_column(UserTotp::TotpSecret)
+                    .drop_column(UserTotp::TotpEnabledAt)
+                    .drop_column(UserTotp::TotpLastStep)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+#[allow(clippy::enum_variant_names)]
+enum UserTotp {
+    TotpSecret,
+    TotpEnabledAt,
+    TotpLastStep,
+}
+
+#[derive(Iden)]
+pub enum RecoveryCode {
+    Table,
+    Id,
+    User,
+    CodeHash,
+    CreatedAt,
+}
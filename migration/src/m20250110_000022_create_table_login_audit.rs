@@ -0,0 +1,83 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20241012_000001_create_table_user::User;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20250110_000022_create_table_login_audit"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    /// 登录审计日志, 用户不存在时 `user` 为空
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(LoginAudit::Table)
+                    .col(
+                        ColumnDef::new(LoginAudit::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .extra("DEFAULT uuid_generate_v4()"),
+                    )
+                    .col(ColumnDef::new(LoginAudit::User).uuid())
+                    .col(ColumnDef::new(LoginAudit::Name).string().not_null())
+                    .col(ColumnDef::new(LoginAudit::Ip).string().not_null())
+                    .col(ColumnDef::new(LoginAudit::UserAgent).string())
+                    .col(ColumnDef::new(LoginAudit::Success).boolean().not_null())
+                    .col(ColumnDef::new(LoginAudit::Reason).string())
+                    .col(
+                        ColumnDef::new(LoginAudit::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .extra("DEFAULT now()::TIMESTAMP"),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_foreign_key(
+                ForeignKey::create()
+                    .from(LoginAudit::Table, LoginAudit::User)
+                    .to(User::Table, User::Id)
+                    .on_delete(ForeignKeyAction::Cascade)
+                    .name("FK_LOGIN_AUDIT_USER_USER_ID")
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("IDX_LOGIN_AUDIT_USER_CREATED_AT")
+                    .table(LoginAudit::Table)
+                    .col(LoginAudit::User)
+                    .col(LoginAudit::CreatedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(LoginAudit::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum LoginAudit {
+    Table,
+    Id,
+    User,
+    Name,
+    Ip,
+    UserAgent,
+    Success,
+    Reason,
+    CreatedAt,
+}
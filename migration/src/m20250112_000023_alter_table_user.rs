@@ -0,0 +1,86 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::Statement;
+
+use super::m20241012_000001_create_table_user::User;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20250112_000023_alter_table_user"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    /// 新增规范化邮箱列, 用于不区分大小写的邮箱登录与查重
+    ///
+    /// 仅大小写或首尾空白不同的重复邮箱只为最早注册的账号填充规范化邮箱,
+    /// 其余账号保持为空并记录警告, 需要用户修改邮箱后才能通过邮箱登录
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .add_column(ColumnDef::new(UserEmail::EmailNormalized).string())
+                    .to_owned(),
+            )
+            .await?;
+        let conn = manager.get_connection();
+        conn.execute_unprepared(
+            r#"UPDATE "user" SET email_normalized = r.normalized
+FROM (
+    SELECT id, LOWER(TRIM(email)) AS normalized,
+        ROW_NUMBER() OVER (PARTITION BY LOWER(TRIM(email)) ORDER BY created_at, id) AS rank
+    FROM "user" WHERE TRIM(email) <> ''
+) r
+WHERE "user".id = r.id AND r.rank = 1"#,
+        )
+        .await?;
+        let duplicates = conn
+            .query_all(Statement::from_string(
+                manager.get_database_backend(),
+                r#"SELECT id::text AS id, email FROM "user" WHERE TRIM(email) <> '' AND email_normalized IS NULL"#,
+            ))
+            .await?;
+        for row in duplicates {
+            let id: String = row.try_get("", "id")?;
+            let email: String = row.try_get("", "email")?;
+            tracing::warn!("user [{id}] has duplicate email [{email}], leave it unnormalized");
+        }
+        manager
+            .create_index(
+                Index::create()
+                    .name("IDX_USER_EMAIL_NORMALIZED")
+                    .table(User::Table)
+                    .col(UserEmail::EmailNormalized)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("IDX_USER_EMAIL_NORMALIZED")
+                    .table(User::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .drop_column(UserEmail::EmailNormalized)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum UserEmail {
+    EmailNormalized,
+}
@@ -9,16 +9,26 @@ pub struct Model {
     pub id: Uuid,
     pub user: Uuid,
     pub ref_user: Option<Uuid>,
-    pub category: Option<String>,
     pub session: Uuid,
     pub alias: Option<String>,
     pub created_at: DateTime,
     pub pin: bool,
     pub mute: bool,
+    pub message: Option<String>,
+    pub source: Option<String>,
+    pub category_id: Option<Uuid>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::category::Entity",
+        from = "Column::CategoryId",
+        to = "super::category::Column::Id",
+        on_update = "NoAction",
+        on_delete = "SetNull"
+    )]
+    Category,
     #[sea_orm(
         belongs_to = "super::session::Entity",
         from = "Column::Session",
@@ -45,6 +55,12 @@ pub enum Relation {
     User1,
 }
 
+impl Related<super::category::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Category.def()
+    }
+}
+
 impl Related<super::session::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Session.def()
@@ -10,6 +10,7 @@ pub struct Model {
     pub read_at: Option<DateTime>,
     pub user: Uuid,
     pub message: Uuid,
+    pub confirmed_at: Option<DateTime>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -11,10 +11,16 @@ pub struct Model {
     pub owner: Uuid,
     pub session: Uuid,
     pub name: Option<String>,
+    pub avatar: Option<Uuid>,
+    pub description: Option<String>,
+    #[sea_orm(column_type = "JsonBinary")]
+    pub settings: Json,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::invite::Entity")]
+    Invite,
     #[sea_orm(has_many = "super::member::Entity")]
     Member,
     #[sea_orm(
@@ -25,6 +31,14 @@ pub enum Relation {
         on_delete = "Cascade"
     )]
     Session,
+    #[sea_orm(
+        belongs_to = "super::upload::Entity",
+        from = "Column::Avatar",
+        to = "super::upload::Column::Uuid",
+        on_update = "NoAction",
+        on_delete = "SetNull"
+    )]
+    Upload,
     #[sea_orm(
         belongs_to = "super::user::Entity",
         from = "Column::Owner",
@@ -35,6 +49,12 @@ pub enum Relation {
     User,
 }
 
+impl Related<super::invite::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Invite.def()
+    }
+}
+
 impl Related<super::member::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Member.def()
@@ -47,6 +67,12 @@ impl Related<super::session::Entity> for Entity {
     }
 }
 
+impl Related<super::upload::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Upload.def()
+    }
+}
+
 impl Related<super::user::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::User.def()
@@ -13,6 +13,7 @@ pub struct Model {
     pub permission: i32,
     pub pin: bool,
     pub mute: bool,
+    pub banned_until: Option<DateTime>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
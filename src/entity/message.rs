@@ -21,8 +21,12 @@ pub struct Model {
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_one = "super::announcement::Entity")]
+    Announcement,
     #[sea_orm(has_many = "super::feed::Entity")]
     Feed,
+    #[sea_orm(has_many = "super::pinned_message::Entity")]
+    PinnedMessage,
     #[sea_orm(
         belongs_to = "Entity",
         from = "Column::Cite",
@@ -65,12 +69,24 @@ pub enum Relation {
     User,
 }
 
+impl Related<super::announcement::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Announcement.def()
+    }
+}
+
 impl Related<super::feed::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Feed.def()
     }
 }
 
+impl Related<super::pinned_message::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PinnedMessage.def()
+    }
+}
+
 impl Related<super::session::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Session.def()
@@ -2,11 +2,19 @@
 
 pub mod prelude;
 
+pub mod announcement;
+pub mod block;
+pub mod category;
 pub mod contact;
 pub mod feed;
 pub mod group;
+pub mod invite;
+pub mod login_audit;
 pub mod member;
 pub mod message;
+pub mod pinned_message;
+pub mod recovery_code;
 pub mod session;
 pub mod upload;
 pub mod user;
+pub mod user_token;
@@ -1,10 +1,18 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.1
 
+pub use super::announcement::Entity as Announcement;
+pub use super::block::Entity as Block;
+pub use super::category::Entity as Category;
 pub use super::contact::Entity as Contact;
 pub use super::feed::Entity as Feed;
 pub use super::group::Entity as Group;
+pub use super::invite::Entity as Invite;
+pub use super::login_audit::Entity as LoginAudit;
 pub use super::member::Entity as Member;
 pub use super::message::Entity as Message;
+pub use super::pinned_message::Entity as PinnedMessage;
+pub use super::recovery_code::Entity as RecoveryCode;
 pub use super::session::Entity as Session;
 pub use super::upload::Entity as Upload;
 pub use super::user::Entity as User;
+pub use super::user_token::Entity as UserToken;
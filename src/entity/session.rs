@@ -18,6 +18,8 @@ pub enum Relation {
     Group,
     #[sea_orm(has_many = "super::message::Entity")]
     Message,
+    #[sea_orm(has_many = "super::pinned_message::Entity")]
+    PinnedMessage,
 }
 
 impl Related<super::contact::Entity> for Entity {
@@ -38,4 +40,10 @@ impl Related<super::message::Entity> for Entity {
     }
 }
 
+impl Related<super::pinned_message::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PinnedMessage.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
@@ -8,25 +8,36 @@ pub struct Model {
     #[sea_orm(primary_key, auto_increment = false)]
     pub uuid: Uuid,
     pub typ: String,
+    pub uploader: Option<Uuid>,
+    pub size: i64,
+    pub created_at: DateTime,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::group::Entity")]
+    Group,
     #[sea_orm(has_many = "super::message::Entity")]
     Message,
-    #[sea_orm(has_many = "super::user::Entity")]
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::Uploader",
+        to = "super::user::Column::Id",
+        on_update = "NoAction",
+        on_delete = "SetNull"
+    )]
     User,
 }
 
-impl Related<super::message::Entity> for Entity {
+impl Related<super::group::Entity> for Entity {
     fn to() -> RelationDef {
-        Relation::Message.def()
+        Relation::Group.def()
     }
 }
 
-impl Related<super::user::Entity> for Entity {
+impl Related<super::message::Entity> for Entity {
     fn to() -> RelationDef {
-        Relation::User.def()
+        Relation::Message.def()
     }
 }
 
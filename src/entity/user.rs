@@ -21,6 +21,15 @@ pub struct Model {
     pub avatar: Option<Uuid>,
     pub bio: Option<String>,
     pub link: Option<String>,
+    #[sea_orm(column_type = "JsonBinary")]
+    pub privacy: Json,
+    pub deleted_at: Option<DateTime>,
+    pub email_verified_at: Option<DateTime>,
+    pub totp_secret: Option<String>,
+    pub totp_enabled_at: Option<DateTime>,
+    pub totp_last_step: Option<i64>,
+    #[sea_orm(unique)]
+    pub email_normalized: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -29,10 +38,18 @@ pub enum Relation {
     Feed,
     #[sea_orm(has_many = "super::group::Entity")]
     Group,
+    #[sea_orm(has_many = "super::invite::Entity")]
+    Invite,
+    #[sea_orm(has_many = "super::login_audit::Entity")]
+    LoginAudit,
     #[sea_orm(has_many = "super::member::Entity")]
     Member,
     #[sea_orm(has_many = "super::message::Entity")]
     Message,
+    #[sea_orm(has_many = "super::pinned_message::Entity")]
+    PinnedMessage,
+    #[sea_orm(has_many = "super::recovery_code::Entity")]
+    RecoveryCode,
     #[sea_orm(
         belongs_to = "super::upload::Entity",
         from = "Column::Avatar",
@@ -41,6 +58,8 @@ pub enum Relation {
         on_delete = "SetNull"
     )]
     Upload,
+    #[sea_orm(has_many = "super::user_token::Entity")]
+    UserToken,
 }
 
 impl Related<super::feed::Entity> for Entity {
@@ -55,6 +74,18 @@ impl Related<super::group::Entity> for Entity {
     }
 }
 
+impl Related<super::invite::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Invite.def()
+    }
+}
+
+impl Related<super::login_audit::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::LoginAudit.def()
+    }
+}
+
 impl Related<super::member::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Member.def()
@@ -67,10 +98,28 @@ impl Related<super::message::Entity> for Entity {
     }
 }
 
+impl Related<super::pinned_message::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PinnedMessage.def()
+    }
+}
+
+impl Related<super::recovery_code::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::RecoveryCode.def()
+    }
+}
+
 impl Related<super::upload::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Upload.def()
     }
 }
 
+impl Related<super::user_token::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::UserToken.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
@@ -1,66 +1,530 @@
 //! 错误响应
 
-use axum::{http::StatusCode, response::IntoResponse, Json};
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use sea_orm::{DbErr, SqlErr};
 use serde::Serialize;
+use tracing::{event, Level};
 #[cfg(feature = "dev")]
 use utoipa::ToSchema;
 
 /// 查看 [HTTP status code wiki](https://en.wikipedia.org/wiki/List_of_HTTP_status_codes)
+///
+/// 除服务端内部错误外均携带错误码, 附带的文本作为 `details.reason` 返回, 不做本地化
 #[derive(Debug)]
 pub enum AppError {
     /// 400 Bad Request
-    BadRequest(String),
+    BadRequest(ErrorCode, String),
     /// 401 Unauthorized
-    Unauthorized(String),
+    Unauthorized(ErrorCode, String),
     /// 403 Forbidden
-    Forbidden(String),
+    Forbidden(ErrorCode, String),
     /// 404 Not Found
-    NotFound(String),
+    NotFound(ErrorCode, String),
     /// 409 Conflict
-    Conflict(String),
+    Conflict(ErrorCode, String),
+    /// 429 Too Many Requests
+    TooManyRequests(ErrorCode, String),
+    /// 507 Insufficient Storage
+    InsufficientStorage(ErrorCode, String),
+    /// 数据库约束冲突, 状态码见 [`Violation`]
+    Constraint(Violation),
     /// 500 Internal Server Error
     Server(anyhow::Error),
 }
 
+/// 数据库约束冲突, 具体的 SQL 错误只记录在日志中
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Violation {
+    /// 409 Conflict, 违反唯一约束
+    Unique,
+    /// 404 Not Found, 引用的记录不存在
+    MissingReference,
+    /// 400 Bad Request, 记录仍被其他记录引用
+    StillReferenced,
+}
+
+impl Violation {
+    /// 从数据库错误中识别约束冲突
+    fn classify(err: &DbErr) -> Option<Self> {
+        match err.sql_err()? {
+            SqlErr::UniqueConstraintViolation(_) => Some(Self::Unique),
+            SqlErr::ForeignKeyConstraintViolation(msg) => Some(Self::foreign_key(&msg)),
+            _ => None,
+        }
+    }
+
+    /// 按 PostgreSQL 的错误信息区分外键冲突的方向
+    ///
+    /// 更新或删除被引用的记录时错误信息以 "update or delete on table" 开头,
+    /// "is still referenced" 只出现在错误详情中
+    fn foreign_key(msg: &str) -> Self {
+        if msg.starts_with("update or delete on table") {
+            Self::StillReferenced
+        } else {
+            Self::MissingReference
+        }
+    }
+}
+
+/// 错误码
+///
+/// 取值稳定, 客户端应当按错误码而不是错误信息区分错误
+#[cfg_attr(feature = "dev", derive(ToSchema))]
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    /// 缺少认证令牌
+    TokenMissing,
+    /// 认证令牌无效或已过期
+    InvalidToken,
+    /// 两步验证挑战令牌无效或已过期
+    InvalidChallenge,
+    /// 登录账号或密码为空
+    CredentialsRequired,
+    /// 账号或密码错误
+    InvalidCredentials,
+    /// 密码错误
+    WrongPassword,
+    /// 登录请求过于频繁
+    LoginRateLimited,
+    /// 账号已被临时锁定
+    AccountLocked,
+    /// 用户不存在
+    UserNotFound,
+    /// 用户名已被占用
+    NameTaken,
+    /// 邮箱已被占用
+    EmailTaken,
+    /// 电话已被占用
+    PhoneTaken,
+    /// 用户名不能是邮箱或电话
+    InvalidName,
+    /// 性别取值不合法
+    InvalidGender,
+    /// 邮箱格式不合法
+    InvalidEmail,
+    /// 电话格式不合法
+    InvalidPhone,
+    /// 密码为空
+    EmptyPassword,
+    /// 未提供查找条件
+    SearchConditionRequired,
+    /// 搜索内容为空
+    EmptySearchQuery,
+    /// 请求的范围不合法
+    InvalidRange,
+    /// 请求的范围过大
+    RangeTooLarge,
+    /// 两步验证已开启
+    TotpAlreadyEnabled,
+    /// 两步验证尚未开始绑定
+    TotpNotStarted,
+    /// 两步验证未开启
+    TotpNotEnabled,
+    /// 两步验证码错误
+    WrongTotpCode,
+    /// 邮件发送过于频繁
+    MailTooFrequent,
+    /// 邮件令牌无效
+    InvalidMailToken,
+    /// 邮件令牌已过期
+    MailTokenExpired,
+    /// 令牌签发后邮箱已变更
+    EmailChanged,
+    /// 未设置邮箱
+    EmailNotSet,
+    /// 邮箱已验证
+    EmailAlreadyVerified,
+    /// 文件不存在
+    FileNotFound,
+    /// 文件内容为空
+    EmptyContent,
+    /// 不支持的文件类型
+    UnsupportedFileType,
+    /// 上传配额已用尽
+    QuotaExceeded,
+    /// 不能发送系统消息
+    SystemMessage,
+    /// 消息不存在
+    MessageNotFound,
+    /// 消息已置顶
+    MessagePinned,
+    /// 消息未置顶
+    MessageNotPinned,
+    /// 不在会话中
+    SessionNotMember,
+    /// 已被对方屏蔽
+    BlockedByUser,
+    /// 好友不存在
+    ContactNotFound,
+    /// 好友关系已存在
+    ContactExists,
+    /// 不能添加或接受自己
+    ContactSelf,
+    /// 不能添加该用户
+    ContactForbidden,
+    /// 对方不接受好友申请
+    ContactRequestRefused,
+    /// 好友申请已接受
+    ContactAccepted,
+    /// 部分用户不是好友
+    NotContacts,
+    /// 不能屏蔽自己
+    BlockSelf,
+    /// 已屏蔽该用户
+    AlreadyBlocked,
+    /// 未屏蔽该用户
+    NotBlocked,
+    /// 分组名为空
+    EmptyCategoryName,
+    /// 分组不存在
+    CategoryNotFound,
+    /// 分组已存在
+    CategoryExists,
+    /// 分组排序不完整
+    InvalidCategoryOrder,
+    /// 群聊不存在
+    GroupNotFound,
+    /// 群聊成员不足
+    GroupTooSmall,
+    /// 不是群聊成员
+    MemberNotFound,
+    /// 已是群聊成员
+    AlreadyMember,
+    /// 入群申请待审核
+    JoinPending,
+    /// 待审核成员无权操作
+    PendingMember,
+    /// 群聊已全员禁言
+    GroupMuted,
+    /// 已被禁言
+    MemberBanned,
+    /// 群内角色不足
+    PermissionDenied,
+    /// 不能管理同级或更高角色
+    RoleOutranked,
+    /// 角色取值不合法
+    InvalidRole,
+    /// 不能转让给自己
+    TransferToSelf,
+    /// 不能转让给待审核成员
+    TransferToPending,
+    /// 不能设置或取消该管理员
+    InvalidAdminChange,
+    /// 不能移除自己
+    RemoveSelf,
+    /// 群主需要先转让群聊才能退出
+    GroupOwnerExit,
+    /// 邀请不存在
+    InviteNotFound,
+    /// 邀请已撤销
+    InviteRevoked,
+    /// 邀请已过期
+    InviteExpired,
+    /// 邀请次数已用尽
+    InviteUsedUp,
+    /// 最大使用次数不合法
+    InvalidMaxUses,
+    /// 群公告不存在
+    NoticeNotFound,
+    /// 群公告标题为空
+    EmptyTitle,
+    /// 资源已存在
+    UniqueViolation,
+    /// 引用的资源不存在
+    ReferenceNotFound,
+    /// 资源仍被引用
+    StillReferenced,
+    /// 记录不存在
+    RecordNotFound,
+    /// 服务端内部错误
+    Internal,
+}
+
+/// 错误信息语言
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Lang {
+    /// 简体中文
+    Zh,
+    /// 英文
+    #[default]
+    En,
+}
+
+impl Lang {
+    /// 按 `Accept-Language` 的权重选择语言, 无法识别时使用英文
+    pub fn from_accept_language(value: &str) -> Self {
+        let mut tags: Vec<(&str, f32)> = value
+            .split(',')
+            .filter_map(|tag| {
+                let mut parts = tag.split(';');
+                let lang = parts.next()?.trim();
+                let q = parts
+                    .find_map(|p| p.trim().strip_prefix("q="))
+                    .and_then(|q| q.parse().ok())
+                    .unwrap_or(1.0);
+                Some((lang, q))
+            })
+            .collect();
+        tags.sort_by(|a, b| b.1.total_cmp(&a.1));
+        tags.into_iter()
+            .find_map(|(lang, _)| {
+                let lang = lang.to_ascii_lowercase();
+                if lang.starts_with("zh") {
+                    Some(Self::Zh)
+                } else if lang.starts_with("en") {
+                    Some(Self::En)
+                } else {
+                    None
+                }
+            })
+            .unwrap_or_default()
+    }
+
+    fn from_headers(headers: &HeaderMap) -> Self {
+        headers
+            .get(header::ACCEPT_LANGUAGE)
+            .and_then(|v| v.to_str().ok())
+            .map(Self::from_accept_language)
+            .unwrap_or_default()
+    }
+}
+
+impl ErrorCode {
+    /// 本地化的错误信息
+    pub fn message(&self, lang: Lang) -> &'static str {
+        let (zh, en) = match self {
+            Self::TokenMissing => ("缺少认证令牌", "authorization token not found"),
+            Self::InvalidToken => ("认证令牌无效或已过期", "invalid or expired token"),
+            Self::InvalidChallenge => ("验证挑战无效或已过期", "invalid or expired challenge"),
+            Self::CredentialsRequired => ("账号和密码不能为空", "identifier and password required"),
+            Self::InvalidCredentials => ("账号或密码错误", "invalid identifier or password"),
+            Self::WrongPassword => ("密码错误", "wrong password"),
+            Self::LoginRateLimited => ("登录尝试过于频繁", "too many login attempts"),
+            Self::AccountLocked => ("账号已被临时锁定", "account temporarily locked"),
+            Self::UserNotFound => ("用户不存在", "user not found"),
+            Self::NameTaken => ("用户名已被占用", "name already taken"),
+            Self::EmailTaken => ("邮箱已被注册", "email already registered"),
+            Self::PhoneTaken => ("电话已被注册", "phone already registered"),
+            Self::InvalidName => ("用户名不能是邮箱或电话", "name cannot be an email or phone"),
+            Self::InvalidGender => ("性别不合法", "invalid gender"),
+            Self::InvalidEmail => ("邮箱格式不正确", "invalid email"),
+            Self::InvalidPhone => ("电话格式不正确", "invalid phone"),
+            Self::EmptyPassword => ("密码不能为空", "password cannot be empty"),
+            Self::SearchConditionRequired => {
+                ("至少需要一个查找条件", "no search condition provided")
+            }
+            Self::EmptySearchQuery => ("搜索内容不能为空", "empty search query"),
+            Self::InvalidRange => ("请求范围不正确", "invalid range"),
+            Self::RangeTooLarge => ("请求范围过大", "too many results requested"),
+            Self::TotpAlreadyEnabled => ("两步验证已开启", "totp already enabled"),
+            Self::TotpNotStarted => ("尚未开始绑定两步验证", "totp enrollment not started"),
+            Self::TotpNotEnabled => ("两步验证未开启", "totp not enabled"),
+            Self::WrongTotpCode => ("两步验证码错误", "wrong totp code"),
+            Self::MailTooFrequent => ("邮件发送过于频繁", "mail requested too frequently"),
+            Self::InvalidMailToken => ("链接无效", "invalid token"),
+            Self::MailTokenExpired => ("链接已过期", "token expired"),
+            Self::EmailChanged => ("邮箱已变更, 链接失效", "email changed after token issued"),
+            Self::EmailNotSet => ("尚未设置邮箱", "email not set"),
+            Self::EmailAlreadyVerified => ("邮箱已验证", "email already verified"),
+            Self::FileNotFound => ("文件不存在", "file not found"),
+            Self::EmptyContent => ("文件内容为空", "empty content"),
+            Self::UnsupportedFileType => ("不支持的文件类型", "unsupported file type"),
+            Self::QuotaExceeded => ("上传空间不足", "upload quota exceeded"),
+            Self::SystemMessage => ("不能发送系统消息", "cannot send system message"),
+            Self::MessageNotFound => ("消息不存在", "message not found"),
+            Self::MessagePinned => ("消息已置顶", "message already pinned"),
+            Self::MessageNotPinned => ("消息未置顶", "message not pinned"),
+            Self::SessionNotMember => ("不在该会话中", "not in session"),
+            Self::BlockedByUser => ("已被对方屏蔽", "blocked by user"),
+            Self::ContactNotFound => ("好友不存在", "contact not found"),
+            Self::ContactExists => ("好友关系已存在", "contact already exists"),
+            Self::ContactSelf => ("不能添加自己为好友", "cannot add self"),
+            Self::ContactForbidden => ("不能添加该用户", "cannot add user"),
+            Self::ContactRequestRefused => ("对方不接受好友申请", "contact requests not accepted"),
+            Self::ContactAccepted => ("好友申请已接受", "contact already accepted"),
+            Self::NotContacts => ("部分用户不是好友", "some users are not contacts"),
+            Self::BlockSelf => ("不能屏蔽自己", "cannot block self"),
+            Self::AlreadyBlocked => ("已屏蔽该用户", "already blocked"),
+            Self::NotBlocked => ("未屏蔽该用户", "not blocked"),
+            Self::EmptyCategoryName => ("分组名不能为空", "empty category name"),
+            Self::CategoryNotFound => ("分组不存在", "category not found"),
+            Self::CategoryExists => ("分组已存在", "category already exists"),
+            Self::InvalidCategoryOrder => (
+                "排序需要包含每个分组且仅一次",
+                "order must contain every category exactly once",
+            ),
+            Self::GroupNotFound => ("群聊不存在", "group not found"),
+            Self::GroupTooSmall => ("群聊至少需要两名成员", "at least 2 members"),
+            Self::MemberNotFound => ("不是群聊成员", "not a group member"),
+            Self::AlreadyMember => ("已是群聊成员", "already in group"),
+            Self::JoinPending => ("入群申请待审核", "join request pending"),
+            Self::PendingMember => ("入群申请待审核, 无权操作", "pending member not allowed"),
+            Self::GroupMuted => ("群聊已全员禁言", "group is muted"),
+            Self::MemberBanned => ("你已被禁言", "banned in group"),
+            Self::PermissionDenied => ("群内角色权限不足", "permission denied"),
+            Self::RoleOutranked => ("不能管理同级或更高角色", "cannot manage member with role"),
+            Self::InvalidRole => ("角色不合法", "invalid role"),
+            Self::TransferToSelf => ("不能转让给自己", "cannot transfer to self"),
+            Self::TransferToPending => {
+                ("不能转让给待审核成员", "cannot transfer to pending member")
+            }
+            Self::InvalidAdminChange => ("不能设置或取消该管理员", "invalid admin change"),
+            Self::RemoveSelf => ("不能移除自己", "cannot remove self"),
+            Self::GroupOwnerExit => (
+                "群主需要先转让群聊才能退出",
+                "owner cannot exit group before transferring the group",
+            ),
+            Self::InviteNotFound => ("邀请不存在", "invite not found"),
+            Self::InviteRevoked => ("邀请已撤销", "invite has been revoked"),
+            Self::InviteExpired => ("邀请已过期", "invite has expired"),
+            Self::InviteUsedUp => ("邀请次数已用尽", "invite has been used up"),
+            Self::InvalidMaxUses => ("最大使用次数必须为正数", "max uses must be positive"),
+            Self::NoticeNotFound => ("群公告不存在", "notice not found"),
+            Self::EmptyTitle => ("标题不能为空", "title cannot be empty"),
+            Self::UniqueViolation => ("资源已存在", "resource already exists"),
+            Self::ReferenceNotFound => ("引用的资源不存在", "referenced resource not found"),
+            Self::StillReferenced => ("资源仍被引用", "resource is still referenced"),
+            Self::RecordNotFound => ("记录不存在", "record not found"),
+            Self::Internal => ("服务端内部错误", "internal server error"),
+        };
+        match lang {
+            Lang::Zh => zh,
+            Lang::En => en,
+        }
+    }
+}
+
 /// 错误响应
 #[doc(hidden)]
 #[cfg_attr(feature = "dev", derive(ToSchema))]
 #[derive(Serialize)]
 pub struct AppErrorResponse {
+    /// 错误码
+    code: ErrorCode,
     /// 错误信息
-    msg: String,
+    ///
+    /// 按 `Accept-Language` 选择中文 (`zh-CN`) 或英文 (`en`), 默认英文
+    msg: &'static str,
+    /// 错误详情
+    ///
+    /// `reason` 字段为面向开发者的英文描述
+    #[cfg_attr(feature = "dev", schema(value_type = Option<Object>))]
+    details: Option<serde_json::Value>,
+    /// 请求标识, 取自请求头 `X-Request-Id`
+    request_id: Option<String>,
     /// 当前 API 版本
     ver: &'static str,
 }
 
+/// 错误响应中与请求无关的部分, 由 [`localize`] 按请求补全
+#[derive(Clone, Debug)]
+struct ErrorBody {
+    code: ErrorCode,
+    details: Option<serde_json::Value>,
+}
+
+impl ErrorBody {
+    fn render(self, lang: Lang, request_id: Option<String>) -> AppErrorResponse {
+        AppErrorResponse {
+            code: self.code,
+            msg: self.code.message(lang),
+            details: self.details,
+            request_id,
+            ver: env!("CARGO_PKG_VERSION"),
+        }
+    }
+}
+
 impl<E> From<E> for AppError
 where
     E: Into<anyhow::Error>,
 {
     fn from(value: E) -> Self {
-        Self::Server(value.into())
+        let err: anyhow::Error = value.into();
+        match err.downcast_ref::<DbErr>().and_then(Violation::classify) {
+            Some(violation) => {
+                event!(Level::WARN, "{:?}: [{}]", violation, err);
+                Self::Constraint(violation)
+            }
+            None => match err.downcast_ref::<DbErr>() {
+                Some(DbErr::RecordNotUpdated) => {
+                    Self::NotFound(ErrorCode::RecordNotFound, "record not found".to_string())
+                }
+                _ => Self::Server(err),
+            },
+        }
     }
 }
 
-impl IntoResponse for AppError {
-    fn into_response(self) -> axum::response::Response {
-        let (status, message) = match self {
-            Self::Server(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
-            Self::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg),
-            Self::Forbidden(msg) => (StatusCode::FORBIDDEN, msg),
-            Self::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
-            Self::Conflict(msg) => (StatusCode::CONFLICT, msg),
-            Self::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, msg),
+impl AppError {
+    fn status_and_body(self) -> (StatusCode, ErrorBody) {
+        let reason = |msg: String| Some(serde_json::json!({ "reason": msg }));
+        let (status, code, details) = match self {
+            Self::Server(e) => {
+                event!(Level::ERROR, "internal error: [{:?}]", e);
+                (StatusCode::INTERNAL_SERVER_ERROR, ErrorCode::Internal, None)
+            }
+            Self::BadRequest(code, msg) => (StatusCode::BAD_REQUEST, code, reason(msg)),
+            Self::Forbidden(code, msg) => (StatusCode::FORBIDDEN, code, reason(msg)),
+            Self::NotFound(code, msg) => (StatusCode::NOT_FOUND, code, reason(msg)),
+            Self::Conflict(code, msg) => (StatusCode::CONFLICT, code, reason(msg)),
+            Self::TooManyRequests(code, msg) => (StatusCode::TOO_MANY_REQUESTS, code, reason(msg)),
+            Self::Unauthorized(code, msg) => (StatusCode::UNAUTHORIZED, code, reason(msg)),
+            Self::InsufficientStorage(code, msg) => {
+                (StatusCode::INSUFFICIENT_STORAGE, code, reason(msg))
+            }
+            Self::Constraint(Violation::Unique) => {
+                (StatusCode::CONFLICT, ErrorCode::UniqueViolation, None)
+            }
+            Self::Constraint(Violation::MissingReference) => {
+                (StatusCode::NOT_FOUND, ErrorCode::ReferenceNotFound, None)
+            }
+            Self::Constraint(Violation::StillReferenced) => {
+                (StatusCode::BAD_REQUEST, ErrorCode::StillReferenced, None)
+            }
         };
-        (
-            status,
-            Json(AppErrorResponse {
-                msg: message,
-                ver: env!("CARGO_PKG_VERSION"),
-            }),
-        )
-            .into_response()
+        (status, ErrorBody { code, details })
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let (status, body) = self.status_and_body();
+        let mut res = (status, Json(body.clone().render(Lang::default(), None))).into_response();
+        res.extensions_mut().insert(body);
+        res
+    }
+}
+
+/// 按请求的 `Accept-Language` 与 `X-Request-Id` 重新生成错误响应
+pub async fn localize(req: Request, next: Next) -> Response {
+    let lang = Lang::from_headers(req.headers());
+    let request_id = req
+        .headers()
+        .get(&crate::trace::REQUEST_ID)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    let mut res = next.run(req).await;
+    if let Some(body) = res.extensions_mut().remove::<ErrorBody>() {
+        if lang != Lang::default() || request_id.is_some() {
+            match serde_json::to_vec(&body.render(lang, request_id)) {
+                Ok(bytes) => {
+                    res.headers_mut().remove(header::CONTENT_LENGTH);
+                    *res.body_mut() = Body::from(bytes);
+                }
+                Err(e) => event!(Level::ERROR, "fail to render error: [{:?}]", e),
+            }
+        }
     }
+    res
 }
 
 #[cfg(test)]
@@ -71,8 +535,56 @@ mod tests {
     #[test]
     fn convert_std_error_to_response() {
         let res: axum::response::Response =
-            AppError::from(std::io::Error::new(std::io::ErrorKind::Other, "test")).into_response();
+            AppError::from(std::io::Error::other("test")).into_response();
 
         assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
     }
+
+    #[test]
+    fn convert_db_error_to_response() {
+        let res = AppError::from(DbErr::RecordNotUpdated).into_response();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        let res = AppError::from(DbErr::Custom("test".to_string())).into_response();
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let res = AppError::Constraint(Violation::Unique).into_response();
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+        let res = AppError::Constraint(Violation::StillReferenced).into_response();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn classify_foreign_key_violation() {
+        assert_eq!(
+            Violation::foreign_key(
+                "update or delete on table \"user\" violates foreign key constraint \"fk-message-sender\" on table \"message\""
+            ),
+            Violation::StillReferenced
+        );
+        assert_eq!(
+            Violation::foreign_key(
+                "insert or update on table \"message\" violates foreign key constraint \"fk-message-session\""
+            ),
+            Violation::MissingReference
+        );
+    }
+
+    #[test]
+    fn choose_lang_by_weight() {
+        assert_eq!(Lang::from_accept_language("zh-CN,zh;q=0.9"), Lang::Zh);
+        assert_eq!(Lang::from_accept_language("en-US,zh-CN;q=0.8"), Lang::En);
+        assert_eq!(Lang::from_accept_language("fr;q=1, zh;q=0.5"), Lang::Zh);
+        assert_eq!(Lang::from_accept_language("fr"), Lang::En);
+        assert_eq!(
+            ErrorCode::ContactSelf.message(Lang::Zh),
+            "不能添加自己为好友"
+        );
+    }
+
+    #[test]
+    fn serialize_error_code() {
+        assert_eq!(
+            serde_json::to_value(ErrorCode::GroupOwnerExit).unwrap(),
+            "GROUP_OWNER_EXIT"
+        );
+    }
 }
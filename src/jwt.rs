@@ -2,7 +2,12 @@
 
 use std::sync::{LazyLock, OnceLock};
 
-use axum::{async_trait, extract::FromRequestParts, http::request::Parts, RequestPartsExt};
+use axum::{
+    async_trait,
+    extract::{FromRef, FromRequestParts},
+    http::request::Parts,
+    RequestPartsExt,
+};
 use axum_extra::{
     headers::{authorization::Bearer, Authorization},
     TypedHeader,
@@ -13,7 +18,8 @@ use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 use uuid::Uuid;
 
-use crate::error::AppError;
+use crate::error::{AppError, ErrorCode};
+use crate::view::AppState;
 
 pub(super) static JWT_ALG: LazyLock<jsonwebtoken::Validation> =
     LazyLock::new(|| jsonwebtoken::Validation::new(jsonwebtoken::Algorithm::HS256));
@@ -28,7 +34,7 @@ pub(super) struct JwtSetting {
 
 /// JWT 载荷
 #[cfg_attr(feature = "dev", derive(ToSchema))]
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct JWTPayload {
     /// 用户唯一标识
     pub id: Uuid,
@@ -43,6 +49,20 @@ impl JWTPayload {
     ) -> Result<super::entity::user::Model, AppError> {
         super::entity::user::Model::from_uuid(self.id, conn).await
     }
+
+    /// 检查令牌对应的账号未被注销
+    pub(super) async fn check_active(
+        &self,
+        conn: &sea_orm::DatabaseConnection,
+    ) -> Result<(), AppError> {
+        self.to_user(conn).await.map(|_| ()).map_err(|e| match e {
+            AppError::NotFound(..) => AppError::Unauthorized(
+                ErrorCode::InvalidToken,
+                format!("account [{}] is deleted", self.id),
+            ),
+            e => e,
+        })
+    }
 }
 
 impl From<JWTPayload> for String {
@@ -68,24 +88,90 @@ impl From<Uuid> for JWTPayload {
 impl TryFrom<&str> for JWTPayload {
     type Error = AppError;
     fn try_from(token: &str) -> Result<Self, Self::Error> {
-        jsonwebtoken::decode::<JWTPayload>(token, &JWT_SETTING.get().unwrap().de_key, &*JWT_ALG)
-            .map_err(|e| AppError::Unauthorized(format!("invalid JWT: [{}]", e)))
+        jsonwebtoken::decode::<JWTPayload>(token, &JWT_SETTING.get().unwrap().de_key, &JWT_ALG)
+            .map_err(|e| {
+                AppError::Unauthorized(ErrorCode::InvalidToken, format!("invalid JWT: [{}]", e))
+            })
             .map(|t| t.claims)
     }
 }
 
+/// 两步验证的挑战令牌有效期, 单位为秒
+pub(super) const CHALLENGE_EXP: u64 = 300;
+
+/// 两步验证挑战令牌的载荷
+///
+/// 字段与 [`JWTPayload`] 不同, 因此不能作为登录凭证使用
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ChallengePayload {
+    /// 等待验证的用户唯一标识
+    pub sub: Uuid,
+    /// 过期时间戳
+    pub exp: u64,
+}
+
+impl From<Uuid> for ChallengePayload {
+    fn from(sub: Uuid) -> Self {
+        Self {
+            sub,
+            exp: jsonwebtoken::get_current_timestamp() + CHALLENGE_EXP,
+        }
+    }
+}
+
+impl From<ChallengePayload> for String {
+    fn from(payload: ChallengePayload) -> Self {
+        jsonwebtoken::encode(
+            &jsonwebtoken::Header::default(),
+            &payload,
+            &JWT_SETTING.get().unwrap().en_key,
+        )
+        .unwrap()
+    }
+}
+
+impl TryFrom<&str> for ChallengePayload {
+    type Error = AppError;
+    fn try_from(token: &str) -> Result<Self, Self::Error> {
+        jsonwebtoken::decode::<ChallengePayload>(
+            token,
+            &JWT_SETTING.get().unwrap().de_key,
+            &JWT_ALG,
+        )
+        .map_err(|e| {
+            AppError::Unauthorized(
+                ErrorCode::InvalidChallenge,
+                format!("invalid challenge: [{}]", e),
+            )
+        })
+        .map(|t| t.claims)
+    }
+}
+
+/// 提取并验证请求中的令牌
+///
+/// 已注销 (包括保留期内) 的账号的令牌不再有效; 验证通过的载荷保存在请求扩展中,
+/// 鉴权中间件与处理函数重复提取时不再查询数据库
 #[async_trait]
 impl<S> FromRequestParts<S> for JWTPayload
 where
     S: Send + Sync,
+    AppState: FromRef<S>,
 {
     type Rejection = AppError;
-    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        if let Some(payload) = parts.extensions.get::<JWTPayload>() {
+            return Ok(payload.clone());
+        }
         let TypedHeader(Authorization(bearer)) = parts
             .extract::<TypedHeader<Authorization<Bearer>>>()
             .await
-            .map_err(|e| AppError::BadRequest(format!("token not found: [{}]", e)))?;
-        let token = bearer.token().try_into()?;
+            .map_err(|e| {
+                AppError::BadRequest(ErrorCode::TokenMissing, format!("token not found: [{}]", e))
+            })?;
+        let token: JWTPayload = bearer.token().try_into()?;
+        token.check_active(&AppState::from_ref(state).conn).await?;
+        parts.extensions.insert(token.clone());
         Ok(token)
     }
 }
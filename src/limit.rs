@@ -0,0 +1,227 @@
+//! 请求频率限制
+
+use std::collections::VecDeque;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+
+use crate::config::Login;
+use crate::mail::MAIL_SETTING;
+
+pub(super) static LOGIN_LIMIT: OnceLock<LoginLimit> = OnceLock::new();
+
+/// 频率限制接口
+///
+/// 按键统计一段时间内发生的事件数
+pub trait RateLimiter: Send + Sync {
+    /// 记录一次事件, 返回窗口内包含本次在内的事件数
+    fn hit(&self, key: &str) -> usize;
+    /// 窗口内的事件数
+    fn count(&self, key: &str) -> usize;
+    /// 清除该键的全部记录
+    fn reset(&self, key: &str);
+    /// 清除全部已过期的记录
+    fn sweep(&self);
+}
+
+/// 基于 [`DashMap`] 的内存滑动窗口
+///
+/// 记录窗口内每次事件的时间, 仅适用于单实例部署
+pub struct SlidingWindow {
+    window: Duration,
+    hits: DashMap<String, VecDeque<Instant>>,
+}
+
+impl SlidingWindow {
+    /// 新建窗口长度为 `window` 的滑动窗口
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            hits: DashMap::new(),
+        }
+    }
+
+    fn expire(&self, hits: &mut VecDeque<Instant>, now: Instant) {
+        while hits
+            .front()
+            .is_some_and(|t| now.duration_since(*t) >= self.window)
+        {
+            hits.pop_front();
+        }
+    }
+}
+
+impl RateLimiter for SlidingWindow {
+    fn hit(&self, key: &str) -> usize {
+        let now = Instant::now();
+        let mut hits = self.hits.entry(key.to_string()).or_default();
+        self.expire(&mut hits, now);
+        hits.push_back(now);
+        hits.len()
+    }
+
+    fn count(&self, key: &str) -> usize {
+        let now = Instant::now();
+        let count = match self.hits.get_mut(key) {
+            Some(mut hits) => {
+                self.expire(&mut hits, now);
+                hits.len()
+            }
+            None => return 0,
+        };
+        if count == 0 {
+            // 窗口已空, 移除该键, 避免长期占用内存
+            self.hits.remove_if(key, |_, hits| hits.is_empty());
+        }
+        count
+    }
+
+    fn reset(&self, key: &str) {
+        self.hits.remove(key);
+    }
+
+    fn sweep(&self) {
+        let now = Instant::now();
+        self.hits.retain(|_, hits| {
+            self.expire(hits, now);
+            !hits.is_empty()
+        });
+    }
+}
+
+/// 登录频率限制与账号锁定
+pub(super) struct LoginLimit {
+    /// 每个 IP 的登录请求数
+    pub(super) ip: Box<dyn RateLimiter>,
+    pub(super) ip_limit: usize,
+    /// 每个账号的登录失败数
+    account: Box<dyn RateLimiter>,
+    max_failures: usize,
+    /// 账号锁定的截止时间
+    locked: DashMap<String, Instant>,
+    lockout: Duration,
+    pub(super) forwarded_for: bool,
+}
+
+impl LoginLimit {
+    pub(super) fn from_config(config: &Login) -> Self {
+        Self {
+            ip: Box::new(SlidingWindow::new(Duration::from_secs(config.ip_window))),
+            ip_limit: config.ip_limit,
+            account: Box::new(SlidingWindow::new(Duration::from_secs(
+                config.failure_window,
+            ))),
+            max_failures: config.max_failures,
+            locked: DashMap::new(),
+            lockout: Duration::from_secs(config.lockout),
+            forwarded_for: config.forwarded_for,
+        }
+    }
+
+    /// 账号剩余的锁定时间
+    pub(super) fn locked_for(&self, account: &str) -> Option<Duration> {
+        let until = *self.locked.get(account)?;
+        let now = Instant::now();
+        if until > now {
+            Some(until - now)
+        } else {
+            self.locked.remove(account);
+            None
+        }
+    }
+
+    /// 记录一次登录失败, 失败次数达到上限时锁定账号并返回 `true`
+    pub(super) fn fail(&self, account: &str) -> bool {
+        if self.account.hit(account) >= self.max_failures {
+            self.account.reset(account);
+            self.locked
+                .insert(account.to_string(), Instant::now() + self.lockout);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// 登录成功后清除失败记录
+    pub(super) fn succeed(&self, account: &str) {
+        self.account.reset(account);
+    }
+
+    /// 清除过期的请求记录, 失败记录与锁定
+    pub(super) fn sweep(&self) {
+        self.ip.sweep();
+        self.account.sweep();
+        let now = Instant::now();
+        self.locked.retain(|_, until| *until > now);
+    }
+}
+
+/// 定期清除过期的登录与重置密码限制记录
+pub async fn sweep_limits(interval: u64) {
+    let mut ticker = tokio::time::interval(Duration::from_secs(interval.max(1)));
+    loop {
+        ticker.tick().await;
+        LOGIN_LIMIT.get().unwrap().sweep();
+        MAIL_SETTING.get().unwrap().reset_ip.sweep();
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sliding_window_expires() {
+        let limiter = SlidingWindow::new(Duration::from_millis(50));
+        assert_eq!(limiter.hit("a"), 1);
+        assert_eq!(limiter.hit("a"), 2);
+        assert_eq!(limiter.count("b"), 0);
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(limiter.count("a"), 0);
+        assert!(limiter.hits.is_empty());
+        assert_eq!(limiter.hit("a"), 1);
+        limiter.reset("a");
+        assert_eq!(limiter.count("a"), 0);
+    }
+
+    #[test]
+    fn sweep_expired_entries() {
+        let limit = LoginLimit::from_config(&Login {
+            ip_window: 1,
+            max_failures: 1,
+            lockout: 1,
+            ..Default::default()
+        });
+        limit.ip.hit("127.0.0.1");
+        assert!(limit.fail("a"));
+        limit.sweep();
+        assert_eq!(limit.ip.count("127.0.0.1"), 1);
+        assert!(limit.locked.contains_key("a"));
+        std::thread::sleep(Duration::from_millis(1100));
+        limit.sweep();
+        assert!(limit.locked.is_empty());
+        let window = SlidingWindow::new(Duration::from_millis(50));
+        window.hit("a");
+        window.hit("b");
+        std::thread::sleep(Duration::from_millis(60));
+        window.sweep();
+        assert!(window.hits.is_empty());
+    }
+
+    #[test]
+    fn lock_after_failures() {
+        let limit = LoginLimit::from_config(&Login {
+            max_failures: 2,
+            ..Default::default()
+        });
+        assert!(!limit.fail("a"));
+        limit.succeed("a");
+        assert!(!limit.fail("a"));
+        assert!(limit.locked_for("a").is_none());
+        assert!(limit.fail("a"));
+        assert!(limit.locked_for("a").is_some());
+        assert!(limit.locked_for("b").is_none());
+    }
+}
@@ -0,0 +1,369 @@
+//! 邮件发送
+
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use axum::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio_rustls::{rustls, TlsConnector};
+use tracing::{event, Level};
+
+use crate::config::{Mail, MailBackend};
+use crate::limit::{RateLimiter, SlidingWindow};
+
+pub(super) static MAIL_SETTING: OnceLock<MailSetting> = OnceLock::new();
+
+#[doc(hidden)]
+pub(super) struct MailSetting {
+    pub(super) mailer: Box<dyn Mailer>,
+    pub(super) verify_expire: u64,
+    pub(super) reset_expire: u64,
+    pub(super) resend_interval: u64,
+    /// 每个 IP 的重置密码请求数
+    pub(super) reset_ip: Box<dyn RateLimiter>,
+    pub(super) reset_ip_limit: usize,
+}
+
+impl MailSetting {
+    pub(super) fn from_config(config: Mail) -> anyhow::Result<Self> {
+        let mailer: Box<dyn Mailer> = match config.backend {
+            MailBackend::Log => Box::new(LogMailer {
+                from: config.from,
+                dir: config.dir.map(PathBuf::from),
+            }),
+            MailBackend::Smtp => {
+                let smtp = config.smtp.context("missing [mail.smtp] configuration")?;
+                Box::new(SmtpMailer {
+                    from: config.from,
+                    address: smtp.address,
+                    port: smtp.port,
+                    credential: smtp.username.zip(smtp.password),
+                    timeout: Duration::from_secs(smtp.timeout),
+                    tls: tls_connector(),
+                })
+            }
+        };
+        Ok(Self {
+            mailer,
+            verify_expire: config.verify_expire_after,
+            reset_expire: config.reset_expire_after,
+            resend_interval: config.resend_interval,
+            reset_ip: Box::new(SlidingWindow::new(Duration::from_secs(
+                config.reset_ip_window,
+            ))),
+            reset_ip_limit: config.reset_ip_limit,
+        })
+    }
+}
+
+/// 邮件发送接口
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    /// 向 `to` 发送纯文本邮件
+    async fn send(&self, to: &str, subject: &str, body: &str) -> anyhow::Result<()>;
+}
+
+/// 生成 RFC 5322 格式的邮件, 标题与正文使用 UTF-8 并以 base64 编码
+fn format_message(from: &str, to: &str, subject: &str, body: &str) -> String {
+    let body = STANDARD.encode(body);
+    let lines: Vec<&str> = body
+        .as_bytes()
+        .chunks(76)
+        .map(|c| std::str::from_utf8(c).unwrap())
+        .collect();
+    format!(
+        "From: {from}\r\nTo: {to}\r\nSubject: =?UTF-8?B?{}?=\r\nDate: {}\r\nMIME-Version: 1.0\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Transfer-Encoding: base64\r\n\r\n{}\r\n",
+        STANDARD.encode(subject),
+        chrono::Utc::now().to_rfc2822(),
+        lines.join("\r\n")
+    )
+}
+
+/// 不实际发送邮件, 仅记录日志
+///
+/// 设置目录时, 每个收件人最近一封邮件的正文保存为 `{dir}/{to}.txt`
+pub struct LogMailer {
+    from: String,
+    dir: Option<PathBuf>,
+}
+
+#[async_trait]
+impl Mailer for LogMailer {
+    async fn send(&self, to: &str, subject: &str, body: &str) -> anyhow::Result<()> {
+        event!(
+            Level::INFO,
+            "mail from [{}] to [{}]: [{}]",
+            self.from,
+            to,
+            subject
+        );
+        if let Some(dir) = self.dir.as_ref() {
+            tokio::fs::create_dir_all(dir).await?;
+            tokio::fs::write(dir.join(format!("{to}.txt")), body).await?;
+        }
+        Ok(())
+    }
+}
+
+/// 通过 SMTP 服务器发送邮件
+///
+/// 服务器支持时通过 `STARTTLS` 升级为加密连接, 使用 `AUTH LOGIN` 认证;
+/// 配置了凭据而服务器不支持 `STARTTLS` 时拒绝发送. 整个会话受超时限制
+pub struct SmtpMailer {
+    from: String,
+    address: String,
+    port: u16,
+    credential: Option<(String, String)>,
+    timeout: Duration,
+    tls: TlsConnector,
+}
+
+/// 使用 Mozilla 根证书验证服务器的 TLS 连接器
+fn tls_connector() -> TlsConnector {
+    let mut roots = rustls::RootCertStore::empty();
+    roots.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|ta| {
+        rustls::OwnedTrustAnchor::from_subject_spki_name_constraints(
+            ta.subject,
+            ta.spki,
+            ta.name_constraints,
+        )
+    }));
+    let config = rustls::ClientConfig::builder()
+        .with_safe_defaults()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    TlsConnector::from(Arc::new(config))
+}
+
+/// 读取一条可能跨多行的 SMTP 响应
+async fn read_reply<R: AsyncBufRead + Unpin>(reader: &mut R) -> anyhow::Result<(u16, String)> {
+    let mut text = String::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await? == 0 {
+            bail!("smtp connection closed");
+        }
+        let line = line.trim_end();
+        if line.len() < 3 {
+            bail!("malformed smtp reply [{line}]");
+        }
+        let code: u16 = line[..3].parse()?;
+        text.push_str(line.get(4..).unwrap_or_default());
+        if line.as_bytes().get(3) != Some(&b'-') {
+            return Ok((code, text));
+        }
+        text.push('\n');
+    }
+}
+
+/// 读取响应并检查状态码
+async fn expect<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    code: u16,
+    step: &'static str,
+) -> anyhow::Result<()> {
+    let (c, text) = read_reply(reader).await?;
+    if c != code {
+        bail!("smtp [{step}] failed: [{c} {text}]");
+    }
+    Ok(())
+}
+
+/// 发送 `EHLO` 并返回服务器支持的扩展
+async fn ehlo<S: AsyncBufRead + AsyncWrite + Unpin>(conn: &mut S) -> anyhow::Result<Vec<String>> {
+    conn.write_all(b"EHLO veloquent\r\n").await?;
+    let (code, text) = read_reply(conn).await?;
+    if code != 250 {
+        bail!("smtp [EHLO] failed: [{code} {text}]");
+    }
+    Ok(text.lines().map(|l| l.trim().to_uppercase()).collect())
+}
+
+impl SmtpMailer {
+    async fn session(&self, to: &str, message: &str) -> anyhow::Result<()> {
+        let stream = tokio::net::TcpStream::connect((self.address.as_str(), self.port)).await?;
+        let mut conn = BufReader::new(stream);
+        expect(&mut conn, 220, "greeting").await?;
+        if ehlo(&mut conn).await?.iter().any(|e| e == "STARTTLS") {
+            conn.write_all(b"STARTTLS\r\n").await?;
+            expect(&mut conn, 220, "STARTTLS").await?;
+            let name = rustls::ServerName::try_from(self.address.as_str())?;
+            let stream = self.tls.connect(name, conn.into_inner()).await?;
+            let mut conn = BufReader::new(stream);
+            ehlo(&mut conn).await?;
+            return self.deliver(&mut conn, to, message).await;
+        }
+        if self.credential.is_some() {
+            bail!("smtp server does not support STARTTLS, refuse to send credentials in plaintext");
+        }
+        self.deliver(&mut conn, to, message).await
+    }
+
+    async fn deliver<S: AsyncBufRead + AsyncWrite + Unpin>(
+        &self,
+        conn: &mut S,
+        to: &str,
+        message: &str,
+    ) -> anyhow::Result<()> {
+        if let Some((username, password)) = self.credential.as_ref() {
+            conn.write_all(b"AUTH LOGIN\r\n").await?;
+            expect(conn, 334, "AUTH").await?;
+            conn.write_all(format!("{}\r\n", STANDARD.encode(username)).as_bytes())
+                .await?;
+            expect(conn, 334, "AUTH").await?;
+            conn.write_all(format!("{}\r\n", STANDARD.encode(password)).as_bytes())
+                .await?;
+            expect(conn, 235, "AUTH").await?;
+        }
+        conn.write_all(format!("MAIL FROM:<{}>\r\n", self.from).as_bytes())
+            .await?;
+        expect(conn, 250, "MAIL FROM").await?;
+        conn.write_all(format!("RCPT TO:<{to}>\r\n").as_bytes())
+            .await?;
+        expect(conn, 250, "RCPT TO").await?;
+        conn.write_all(b"DATA\r\n").await?;
+        expect(conn, 354, "DATA").await?;
+        conn.write_all(message.as_bytes()).await?;
+        conn.write_all(b".\r\n").await?;
+        expect(conn, 250, "DATA").await?;
+        conn.write_all(b"QUIT\r\n").await?;
+        conn.flush().await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, to: &str, subject: &str, body: &str) -> anyhow::Result<()> {
+        let message = format_message(&self.from, to, subject, body);
+        tokio::time::timeout(self.timeout, self.session(to, &message))
+            .await
+            .context("smtp session timed out")??;
+        event!(Level::INFO, "send mail to [{}]: [{}]", to, subject);
+        Ok(())
+    }
+}
+
+/// 发送邮件, 失败时仅记录日志
+pub(super) async fn send_mail(to: &str, subject: &str, body: &str) {
+    if let Err(e) = MAIL_SETTING
+        .get()
+        .unwrap()
+        .mailer
+        .send(to, subject, body)
+        .await
+    {
+        event!(Level::ERROR, "fail to send mail to [{}]: [{:?}]", to, e);
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_utf8_message() {
+        let msg = format_message("a@example.com", "b@example.com", "验证", "令牌: 123");
+        assert!(msg.contains(&format!("Subject: =?UTF-8?B?{}?=", STANDARD.encode("验证"))));
+        assert!(msg.ends_with(&format!("\r\n\r\n{}\r\n", STANDARD.encode("令牌: 123"))));
+    }
+
+    #[tokio::test]
+    async fn smtp_session() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+            let mut received = Vec::new();
+            writer.write_all(b"220 ready\r\n").await.unwrap();
+            while let Some(line) = lines.next_line().await.unwrap() {
+                let reply: &[u8] = match line.as_str() {
+                    "EHLO veloquent" => b"250-localhost\r\n250 AUTH LOGIN\r\n",
+                    "DATA" => b"354 go ahead\r\n",
+                    "QUIT" => break,
+                    l if l.starts_with("MAIL FROM") || l.starts_with("RCPT TO") || l == "." => {
+                        b"250 ok\r\n"
+                    }
+                    _ => b"",
+                };
+                writer.write_all(reply).await.unwrap();
+                received.push(line);
+            }
+            received
+        });
+        let mailer = mailer(port, None, Duration::from_secs(5));
+        mailer
+            .send("b@example.com", "subject", "body")
+            .await
+            .unwrap();
+        let received = server.await.unwrap();
+        assert!(received.contains(&"RCPT TO:<b@example.com>".to_string()));
+        assert!(received.contains(&STANDARD.encode("body")));
+    }
+
+    fn mailer(port: u16, credential: Option<(String, String)>, timeout: Duration) -> SmtpMailer {
+        SmtpMailer {
+            from: "a@example.com".to_string(),
+            address: "127.0.0.1".to_string(),
+            port,
+            credential,
+            timeout,
+            tls: tls_connector(),
+        }
+    }
+
+    #[tokio::test]
+    async fn smtp_refuse_plaintext_credentials() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+            writer.write_all(b"220 ready\r\n").await.unwrap();
+            let mut received = Vec::new();
+            while let Some(line) = lines.next_line().await.unwrap() {
+                if line == "EHLO veloquent" {
+                    writer
+                        .write_all(b"250-localhost\r\n250 AUTH LOGIN\r\n")
+                        .await
+                        .unwrap();
+                }
+                received.push(line);
+            }
+            received
+        });
+        let credential = Some(("user".to_string(), "pass".to_string()));
+        let e = mailer(port, credential, Duration::from_secs(5))
+            .send("b@example.com", "subject", "body")
+            .await
+            .unwrap_err();
+        assert!(e.to_string().contains("STARTTLS"));
+        let received = server.await.unwrap();
+        assert_eq!(received, vec!["EHLO veloquent".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn smtp_session_timeout() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            // 接受连接但不发送问候
+            let (stream, _) = listener.accept().await.unwrap();
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            drop(stream);
+        });
+        let e = mailer(port, None, Duration::from_millis(100))
+            .send("b@example.com", "subject", "body")
+            .await
+            .unwrap_err();
+        assert!(e.to_string().contains("timed out"));
+        server.await.unwrap();
+    }
+}
@@ -15,7 +15,11 @@ pub mod config;
 pub mod entity;
 pub mod error;
 pub mod jwt;
+pub mod limit;
+pub mod mail;
+pub mod metrics;
 pub mod param;
+pub mod trace;
 #[doc(hidden)]
 pub mod utility;
 pub mod view;
@@ -30,25 +34,14 @@ async fn app(config: Config) -> Result<()> {
         "set upload directory to {:?}",
         &config.upload.dir
     );
-    std::fs::create_dir_all(&config.upload.dir)?;
-    let mut opt = ConnectOptions::new(format!(
-        "postgres://{}:{}@{}:{}/{}",
-        config.database.username,
-        config.database.password,
-        config.database.address,
-        config.database.port,
-        config.database.name,
-    ));
+    let mut opt = ConnectOptions::new(config.database.url());
     opt.max_connections(config.database.max_connections);
     event!(Level::INFO, "connecting to database");
     let db: DatabaseConnection = Database::connect(opt).await?;
     event!(
         Level::INFO,
-        "connected to database {} at {}:{} as {}",
-        config.database.name,
-        config.database.address,
-        config.database.port,
-        config.database.username
+        "connected to database {}",
+        config.database.redacted_url()
     );
     #[cfg(feature = "dev")]
     {
@@ -66,7 +59,7 @@ async fn app(config: Config) -> Result<()> {
     {
         use migration::MigratorTrait;
         use sea_orm::ConnectionTrait;
-        migration::Migrator::down(&db, Some(8)).await.unwrap();
+        migration::Migrator::down(&db, Some(23)).await.unwrap();
         db.execute(sea_orm::Statement::from_string(
             sea_orm::DatabaseBackend::Postgres,
             "CREATE EXTENSION IF NOT EXISTS \"uuid-ossp\";".to_owned(),
@@ -81,12 +74,53 @@ async fn app(config: Config) -> Result<()> {
         de_key: jwt::DecodingKey::from_secret(secret.as_bytes()),
         en_key: jwt::EncodingKey::from_secret(secret.as_bytes()),
     });
+    utility::UPLOAD_QUOTA.get_or_init(|| utility::UploadQuota {
+        default: config.upload.quota,
+        overrides: config.upload.quotas,
+    });
     utility::UPLOAD_DIR.get_or_init(|| config.upload.dir);
+    utility::CONTACT_EXPIRE.get_or_init(|| config.contact.request_expire_after);
+    utility::DELETION_GRACE.get_or_init(|| config.account.deletion_grace);
+    utility::SHUTDOWN_GRACE.get_or_init(|| config.shutdown.grace_period);
+    event!(
+        Level::INFO,
+        "send mail via {:?} as {}",
+        config.mail.backend,
+        config.mail.from
+    );
+    limit::LOGIN_LIMIT.get_or_init(|| limit::LoginLimit::from_config(&config.login));
+    let mail = mail::MailSetting::from_config(config.mail)?;
+    mail::MAIL_SETTING.get_or_init(|| mail);
+    tokio::spawn(limit::sweep_limits(config.login.sweep_interval));
+    tokio::spawn(view::purge_deleted_users(
+        db.clone(),
+        config.account.purge_interval,
+    ));
     let state = AppState {
         conn: db,
         ws_pool: Default::default(),
     };
-    let app = view::router(state);
+    let ws_pool = state.ws_pool.clone();
+    let app = view::router(state.clone());
+    let app = match config.metrics.listen {
+        Some(admin) => {
+            let listener =
+                tokio::net::TcpListener::bind(format!("{}:{}", admin.address, admin.port)).await?;
+            event!(
+                Level::INFO,
+                "serve metrics on http://{}:{}/metrics",
+                admin.address,
+                admin.port
+            );
+            tokio::spawn(async move {
+                axum::serve(listener, view::metrics_router(state))
+                    .with_graceful_shutdown(utility::shutdown_signal())
+                    .await
+            });
+            app
+        }
+        None => app.merge(view::metrics_router(state)),
+    };
     let listener =
         tokio::net::TcpListener::bind(format!("{}:{}", config.listen.address, config.listen.port))
             .await?;
@@ -104,9 +138,37 @@ async fn app(config: Config) -> Result<()> {
         config.listen.port,
         view::DOC_PATH
     );
-    axum::serve(listener, app)
-        .with_graceful_shutdown(utility::shutdown_signal())
-        .await?;
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+    )
+    .with_graceful_shutdown(utility::shutdown_signal())
+    .await?;
+    let limit = std::time::Duration::from_secs(config.shutdown.drain_timeout);
+    if !trace::drain(limit).await {
+        event!(
+            Level::WARN,
+            "background tasks still running after {}s",
+            limit.as_secs()
+        );
+    }
+    ws_pool.close_all().await;
+    Ok(())
+}
+
+/// 按配置初始化日志, 设置了 `RUST_LOG` 时以环境变量为准
+fn init_tracing(log: &config::Log) -> Result<()> {
+    // see https://stackoverflow.com/questions/73247589/how-to-turn-off-tracing-events-emitted-by-other-crates
+    let filter = match std::env::var(tracing_subscriber::EnvFilter::DEFAULT_ENV) {
+        Ok(env) => tracing_subscriber::EnvFilter::builder().parse(env)?,
+        Err(_) => tracing_subscriber::EnvFilter::builder().parse(&log.filter)?,
+    };
+    let fmt = tracing_subscriber::fmt().with_env_filter(filter);
+    match log.format {
+        config::LogFormat::Compact => fmt.compact().init(),
+        config::LogFormat::Pretty => fmt.pretty().init(),
+        config::LogFormat::Json => fmt.json().flatten_event(true).init(),
+    }
     Ok(())
 }
 
@@ -114,19 +176,14 @@ async fn app(config: Config) -> Result<()> {
 #[instrument(name = "veloquent_main")]
 #[tokio::main]
 async fn main() -> Result<()> {
-    // see https://stackoverflow.com/questions/73247589/how-to-turn-off-tracing-events-emitted-by-other-crates
-    let filter = tracing_subscriber::EnvFilter::builder()
-        .with_default_directive(tracing_subscriber::filter::LevelFilter::WARN.into())
-        .from_env()?
-        .add_directive("veloquent_core=debug".parse()?);
-    tracing_subscriber::fmt()
-        .with_env_filter(filter)
-        .compact()
-        .init();
     let args = Args::parse();
-    let config_path = std::path::Path::new(args.config.as_str());
-    event!(Level::WARN, "reading configuration from {:?}", config_path);
-    let config = std::fs::read_to_string(config_path)?;
-    let config: Config = toml::from_str(config.as_str())?;
+    let file = args.config_file();
+    let config = Config::load(file, std::env::vars(), &args.overrides()?)?;
+    init_tracing(&config.log)?;
+    match file {
+        Some(file) => event!(Level::WARN, "read configuration from {:?}", file),
+        None => event!(Level::WARN, "no configuration file, use defaults"),
+    }
+    config.validate()?;
     app(config).await
 }
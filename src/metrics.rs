@@ -0,0 +1,314 @@
+//! 运行指标
+//!
+//! 在内存中统计请求, 推送, 消息与上传等指标, 以 Prometheus 文本格式导出
+
+use std::{
+    fmt::Write,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        LazyLock,
+    },
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{MatchedPath, Request},
+    middleware::Next,
+    response::Response,
+};
+use dashmap::DashMap;
+
+/// 全局指标
+pub static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::default);
+
+/// 请求耗时直方图的桶上界, 单位为秒
+const BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// 累积直方图
+#[derive(Default, Clone)]
+struct Histogram {
+    buckets: [u64; BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, value: f64) {
+        for (bucket, le) in self.buckets.iter_mut().zip(BUCKETS) {
+            if value <= le {
+                *bucket += 1;
+            }
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+/// 推送通知的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Push {
+    /// 推送成功
+    Sent,
+    /// 写入 websocket 失败
+    Failed,
+    /// 用户没有在线的 websocket
+    Offline,
+}
+
+impl Push {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Sent => "sent",
+            Self::Failed => "failed",
+            Self::Offline => "offline",
+        }
+    }
+}
+
+/// 导出时采集的瞬时值
+pub struct Gauges {
+    /// 在线的 websocket 连接数
+    pub websockets: usize,
+    /// 数据库连接池中的连接数
+    pub db_connections: u32,
+    /// 数据库连接池中空闲的连接数
+    pub db_idle: usize,
+    /// 数据库连接池的最大连接数
+    pub db_max: u32,
+}
+
+/// 指标集合
+#[derive(Default)]
+pub struct Metrics {
+    /// `(method, route, status)` 对应的请求数
+    http_requests: DashMap<(String, String, u16), u64>,
+    /// `(method, route)` 对应的请求耗时
+    http_duration: DashMap<(String, String), Histogram>,
+    notifications: DashMap<Push, u64>,
+    /// 消息类型对应的消息数
+    messages: DashMap<&'static str, u64>,
+    upload_bytes: AtomicU64,
+}
+
+impl Metrics {
+    /// 记录一次 HTTP 请求
+    pub fn observe_http(&self, method: &str, route: &str, status: u16, elapsed: Duration) {
+        *self
+            .http_requests
+            .entry((method.to_string(), route.to_string(), status))
+            .or_default() += 1;
+        self.http_duration
+            .entry((method.to_string(), route.to_string()))
+            .or_default()
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// 记录一次通知推送
+    pub fn push(&self, result: Push) {
+        *self.notifications.entry(result).or_default() += 1;
+    }
+
+    /// 记录一条发送的消息, `kind` 为 `chat`, `notice` 或 `system`
+    pub fn message(&self, kind: &'static str) {
+        *self.messages.entry(kind).or_default() += 1;
+    }
+
+    /// 记录上传的字节数
+    pub fn upload(&self, bytes: u64) {
+        self.upload_bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// 以 Prometheus 文本格式导出
+    pub fn render(&self, gauges: &Gauges) -> String {
+        let mut out = String::new();
+        header(
+            &mut out,
+            "veloquent_http_requests_total",
+            "counter",
+            "HTTP requests by method, route and status",
+        );
+        let mut requests: Vec<_> = self
+            .http_requests
+            .iter()
+            .map(|e| (e.key().clone(), *e.value()))
+            .collect();
+        requests.sort();
+        for ((method, route, status), n) in requests {
+            let _ = writeln!(
+                out,
+                "veloquent_http_requests_total{{method=\"{}\",route=\"{}\",status=\"{status}\"}} {n}",
+                escape(&method),
+                escape(&route)
+            );
+        }
+        header(
+            &mut out,
+            "veloquent_http_request_duration_seconds",
+            "histogram",
+            "HTTP request latency by method and route",
+        );
+        let mut durations: Vec<_> = self
+            .http_duration
+            .iter()
+            .map(|e| (e.key().clone(), e.value().clone()))
+            .collect();
+        durations.sort_by(|a, b| a.0.cmp(&b.0));
+        for ((method, route), h) in durations {
+            let labels = format!(
+                "method=\"{}\",route=\"{}\"",
+                escape(&method),
+                escape(&route)
+            );
+            for (le, n) in BUCKETS.iter().zip(h.buckets) {
+                let _ = writeln!(
+                    out,
+                    "veloquent_http_request_duration_seconds_bucket{{{labels},le=\"{le}\"}} {n}"
+                );
+            }
+            let _ = writeln!(
+                out,
+                "veloquent_http_request_duration_seconds_bucket{{{labels},le=\"+Inf\"}} {}",
+                h.count
+            );
+            let _ = writeln!(
+                out,
+                "veloquent_http_request_duration_seconds_sum{{{labels}}} {}",
+                h.sum
+            );
+            let _ = writeln!(
+                out,
+                "veloquent_http_request_duration_seconds_count{{{labels}}} {}",
+                h.count
+            );
+        }
+        header(
+            &mut out,
+            "veloquent_notifications_total",
+            "counter",
+            "websocket notification pushes by result",
+        );
+        for result in [Push::Sent, Push::Failed, Push::Offline] {
+            let n = self.notifications.get(&result).map_or(0, |n| *n);
+            let _ = writeln!(
+                out,
+                "veloquent_notifications_total{{result=\"{}\"}} {n}",
+                result.as_str()
+            );
+        }
+        header(
+            &mut out,
+            "veloquent_messages_total",
+            "counter",
+            "messages sent by kind",
+        );
+        for kind in ["chat", "notice", "system"] {
+            let n = self.messages.get(kind).map_or(0, |n| *n);
+            let _ = writeln!(out, "veloquent_messages_total{{kind=\"{kind}\"}} {n}");
+        }
+        header(
+            &mut out,
+            "veloquent_upload_bytes_total",
+            "counter",
+            "bytes written to the upload directory",
+        );
+        let _ = writeln!(
+            out,
+            "veloquent_upload_bytes_total {}",
+            self.upload_bytes.load(Ordering::Relaxed)
+        );
+        header(
+            &mut out,
+            "veloquent_websocket_connections",
+            "gauge",
+            "registered websocket connections",
+        );
+        let _ = writeln!(out, "veloquent_websocket_connections {}", gauges.websockets);
+        header(
+            &mut out,
+            "veloquent_db_connections",
+            "gauge",
+            "database pool connections by state",
+        );
+        let idle = gauges.db_idle as u64;
+        let active = (gauges.db_connections as u64).saturating_sub(idle);
+        let _ = writeln!(out, "veloquent_db_connections{{state=\"active\"}} {active}");
+        let _ = writeln!(out, "veloquent_db_connections{{state=\"idle\"}} {idle}");
+        header(
+            &mut out,
+            "veloquent_db_connections_max",
+            "gauge",
+            "maximum database pool connections",
+        );
+        let _ = writeln!(out, "veloquent_db_connections_max {}", gauges.db_max);
+        out
+    }
+}
+
+fn header(out: &mut String, name: &str, typ: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {typ}");
+}
+
+/// 转义标签值中的反斜杠, 引号与换行
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// 统计请求数与耗时, 按匹配的路由模板聚合, 未匹配的请求记为 `unmatched`
+pub async fn track_http(req: Request, next: Next) -> Response {
+    let method = req.method().to_string();
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or("unmatched".to_string(), |p| p.as_str().to_string());
+    let start = Instant::now();
+    let res = next.run(req).await;
+    METRICS.observe_http(&method, &route, res.status().as_u16(), start.elapsed());
+    res
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_prometheus_text() {
+        let metrics = Metrics::default();
+        metrics.observe_http("GET", "/msg/:id", 200, Duration::from_millis(20));
+        metrics.observe_http("GET", "/msg/:id", 200, Duration::from_millis(200));
+        metrics.push(Push::Sent);
+        metrics.message("chat");
+        metrics.upload(42);
+        let out = metrics.render(&Gauges {
+            websockets: 3,
+            db_connections: 5,
+            db_idle: 2,
+            db_max: 10,
+        });
+        assert!(out.contains(
+            "veloquent_http_requests_total{method=\"GET\",route=\"/msg/:id\",status=\"200\"} 2"
+        ));
+        assert!(out.contains(
+            "veloquent_http_request_duration_seconds_bucket{method=\"GET\",route=\"/msg/:id\",le=\"0.025\"} 1"
+        ));
+        assert!(out.contains(
+            "veloquent_http_request_duration_seconds_bucket{method=\"GET\",route=\"/msg/:id\",le=\"+Inf\"} 2"
+        ));
+        assert!(out.contains("veloquent_notifications_total{result=\"sent\"} 1"));
+        assert!(out.contains("veloquent_messages_total{kind=\"chat\"} 1"));
+        assert!(out.contains("veloquent_upload_bytes_total 42"));
+        assert!(out.contains("veloquent_websocket_connections 3"));
+        assert!(out.contains("veloquent_db_connections{state=\"active\"} 3"));
+    }
+
+    #[test]
+    fn escape_label_value() {
+        assert_eq!(escape("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+}
@@ -0,0 +1,176 @@
+//! 请求追踪
+//!
+//! 为每个请求分配请求标识, 在请求 span 中处理请求并记录访问日志
+
+use std::{
+    future::Future,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::Request,
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use tokio::{sync::Notify, task::JoinHandle};
+use tracing::{event, info_span, Instrument, Level};
+
+/// 请求标识的请求头与响应头
+pub static REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+/// 请求标识的最大长度, 超过时重新生成
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// 沿用客户端提供的 `X-Request-Id`, 不存在或不合法时生成新的标识
+fn request_id(req: &Request) -> HeaderValue {
+    req.headers()
+        .get(&REQUEST_ID)
+        .filter(|v| !v.is_empty() && v.len() <= MAX_REQUEST_ID_LEN && v.to_str().is_ok())
+        .cloned()
+        .unwrap_or_else(|| HeaderValue::from_str(&uuid::Uuid::new_v4().to_string()).unwrap())
+}
+
+/// 请求追踪中间件
+///
+/// 请求标识写回请求头供后续处理使用, 并在响应头中返回
+pub async fn trace_request(mut req: Request, next: Next) -> Response {
+    let id = request_id(&req);
+    req.headers_mut().insert(&REQUEST_ID, id.clone());
+    let method = req.method().clone();
+    let path = req.uri().path().to_string();
+    let span = info_span!(
+        "request",
+        request_id = id.to_str().unwrap_or_default(),
+        %method,
+        path,
+    );
+    let start = Instant::now();
+    let mut res = next.run(req).instrument(span.clone()).await;
+    let latency = start.elapsed();
+    span.in_scope(|| {
+        event!(
+            Level::INFO,
+            status = res.status().as_u16(),
+            latency_ms = latency.as_secs_f64() * 1000.0,
+            "access"
+        )
+    });
+    res.headers_mut().insert(&REQUEST_ID, id);
+    res
+}
+
+/// 后台任务计数
+///
+/// 记录运行中的任务数, 全部结束时通知等待者
+pub(crate) struct TaskTracker {
+    tasks: AtomicUsize,
+    idle: Notify,
+}
+
+/// 由 [`spawn`] 启动的后台任务
+static TRACKER: TaskTracker = TaskTracker::new();
+
+/// 后台任务结束 (包括被取消或 panic) 时减少计数
+struct TaskGuard(&'static TaskTracker);
+
+impl Drop for TaskGuard {
+    fn drop(&mut self) {
+        if self.0.tasks.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.0.idle.notify_waiters();
+        }
+    }
+}
+
+impl TaskTracker {
+    pub(crate) const fn new() -> Self {
+        Self {
+            tasks: AtomicUsize::new(0),
+            idle: Notify::const_new(),
+        }
+    }
+
+    /// 在当前 span 中启动计数的后台任务
+    pub(crate) fn spawn<F>(&'static self, fut: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        self.tasks.fetch_add(1, Ordering::AcqRel);
+        let guard = TaskGuard(self);
+        tokio::task::spawn(
+            async move {
+                let _guard = guard;
+                fut.await
+            }
+            .in_current_span(),
+        )
+    }
+
+    /// 等待计数的任务全部结束, 超时返回 `false`
+    pub(crate) async fn drain(&self, limit: Duration) -> bool {
+        tokio::time::timeout(limit, async {
+            loop {
+                let notified = self.idle.notified();
+                if self.tasks.load(Ordering::Acquire) == 0 {
+                    break;
+                }
+                notified.await;
+            }
+        })
+        .await
+        .is_ok()
+    }
+}
+
+/// 在当前 span 中启动后台任务, 使任务中的日志可以关联到发起的请求
+///
+/// 任务会被计数, 停机时由 [`drain`] 等待其结束
+pub fn spawn<F>(fut: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    TRACKER.spawn(fut)
+}
+
+/// 等待由 [`spawn`] 启动的后台任务全部结束, 超时返回 `false`
+pub async fn drain(limit: Duration) -> bool {
+    TRACKER.drain(limit).await
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+
+    #[test]
+    fn reuse_or_generate_request_id() {
+        let req = Request::builder()
+            .header(&REQUEST_ID, "abc")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(request_id(&req), "abc");
+        let req = Request::builder()
+            .header(&REQUEST_ID, "a".repeat(MAX_REQUEST_ID_LEN + 1))
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(request_id(&req).len(), 36);
+        let req = Request::builder().body(Body::empty()).unwrap();
+        assert_eq!(request_id(&req).len(), 36);
+    }
+
+    #[tokio::test]
+    async fn drain_background_tasks() {
+        // 使用独立的计数, 不受其他测试启动的后台任务影响
+        static TRACKER: TaskTracker = TaskTracker::new();
+        TRACKER.spawn(tokio::time::sleep(Duration::from_millis(50)));
+        assert!(TRACKER.drain(Duration::from_secs(5)).await);
+        let task = TRACKER.spawn(tokio::time::sleep(Duration::from_secs(60)));
+        assert!(!TRACKER.drain(Duration::from_millis(50)).await);
+        task.abort();
+        assert!(TRACKER.drain(Duration::from_secs(5)).await);
+    }
+}
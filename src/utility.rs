@@ -17,12 +17,19 @@ pub(super) async fn shutdown_signal() {
         _ = ctrl_c => {},
         _ = terminate => {},
     }
-    tracing::event!(tracing::Level::INFO, "gracefully shutting down");
+    SHUTTING_DOWN.store(true, Ordering::Relaxed);
+    let grace = *SHUTDOWN_GRACE.get().unwrap();
+    tracing::event!(tracing::Level::INFO, "gracefully shutting down in {grace}s");
+    // 就绪检查已失败, 继续处理请求直到负载均衡摘除实例
+    tokio::time::sleep(std::time::Duration::from_secs(grace)).await;
 }
 
 use crate::entity;
 
-use std::sync::{LazyLock, OnceLock};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    LazyLock, OnceLock,
+};
 
 use regex::Regex;
 
@@ -41,6 +48,11 @@ pub fn good_phone(phone: &str) -> bool {
     RE.is_match(phone)
 }
 
+/// 规范化邮箱地址, 用于不区分大小写的匹配与查重
+pub fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
 pub fn validate_passwd(passwd: &str, salt: &str, hash: &str) -> anyhow::Result<bool> {
     use sha2::{Digest, Sha256};
     let mut h = Sha256::new();
@@ -58,15 +70,19 @@ pub fn validate_passwd(passwd: &str, salt: &str, hash: &str) -> anyhow::Result<b
     }
 }
 
-pub fn gen_hash_and_salt(passwd: &str) -> Result<(String, String), anyhow::Error> {
+pub fn gen_random_code(len: usize) -> String {
     use rand::distributions::Alphanumeric;
     use rand::{thread_rng, Rng};
-    use sha2::{Digest, Sha256};
-    let salt: String = thread_rng()
+    thread_rng()
         .sample_iter(&Alphanumeric)
-        .take(30)
+        .take(len)
         .map(char::from)
-        .collect();
+        .collect()
+}
+
+pub fn gen_hash_and_salt(passwd: &str) -> Result<(String, String), anyhow::Error> {
+    use sha2::{Digest, Sha256};
+    let salt = gen_random_code(30);
     let mut hash = Sha256::new();
     hash.update(passwd);
     hash.update(salt.clone());
@@ -78,8 +94,44 @@ pub fn gen_hash_and_salt(passwd: &str) -> Result<(String, String), anyhow::Error
     Ok((hash, salt))
 }
 
+/// 一次性令牌的 SHA-256 摘要, 数据库中不保存令牌原文
+pub fn hash_token(token: &str) -> String {
+    use sha2::{Digest, Sha256};
+    let mut buf = [0u8; 64];
+    base16ct::lower::encode_str(&Sha256::digest(token), &mut buf)
+        .unwrap()
+        .to_string()
+}
+
 pub(super) static UPLOAD_DIR: OnceLock<String> = OnceLock::new();
 
+/// 收到停止信号后置位, 此后就绪检查失败
+pub(super) static SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+
+/// 收到停止信号后继续接收请求的时间, 单位为秒
+pub(super) static SHUTDOWN_GRACE: OnceLock<u64> = OnceLock::new();
+
+#[derive(Default)]
+pub(super) struct UploadQuota {
+    pub(super) default: Option<u64>,
+    pub(super) overrides: std::collections::HashMap<uuid::Uuid, u64>,
+}
+
+impl UploadQuota {
+    /// 用户的存储配额, `None` 表示不限制
+    pub(super) fn of(&self, user: uuid::Uuid) -> Option<u64> {
+        self.overrides.get(&user).copied().or(self.default)
+    }
+}
+
+pub(super) static UPLOAD_QUOTA: OnceLock<UploadQuota> = OnceLock::new();
+
+/// 好友申请过期时间, 单位为秒
+pub(super) static CONTACT_EXPIRE: OnceLock<u64> = OnceLock::new();
+
+/// 注销账号的保留期, 单位为秒
+pub(super) static DELETION_GRACE: OnceLock<u64> = OnceLock::new();
+
 impl From<entity::upload::Model> for std::path::PathBuf {
     fn from(upload: entity::upload::Model) -> Self {
         let mut buf = std::path::PathBuf::new();
@@ -112,4 +164,30 @@ mod tests {
         assert!(validate_passwd("123456", &salt, &hash).unwrap());
         assert!(!validate_passwd("1234356", &salt, &hash).unwrap());
     }
+
+    #[test]
+    fn normalize_email_case() {
+        assert_eq!(normalize_email(" Test@Example.COM "), "test@example.com");
+        assert!(good_email(&normalize_email("Test@Example.com")));
+    }
+
+    #[test]
+    fn hash_token_hex() {
+        let h = hash_token("token");
+        assert_eq!(h.len(), 64);
+        assert_eq!(h, hash_token("token"));
+        assert_ne!(h, hash_token("token2"));
+    }
+
+    #[test]
+    fn override_upload_quota() {
+        let user = uuid::Uuid::new_v5(&UPLOAD_UUID, b"user");
+        let quota = UploadQuota {
+            default: Some(1024),
+            overrides: [(user, 0)].into_iter().collect(),
+        };
+        assert_eq!(quota.of(user), Some(0));
+        assert_eq!(quota.of(*UUID_NIL), Some(1024));
+        assert_eq!(UploadQuota::default().of(user), None);
+    }
 }
@@ -2,7 +2,10 @@
 
 use super::entity;
 use super::jwt::JWTPayload;
-use crate::{error::AppError, utility};
+use crate::{
+    error::{AppError, ErrorCode},
+    trace, utility,
+};
 use ws::WebSocketPool;
 
 use axum::{
@@ -40,18 +43,31 @@ use utoipa::{IntoParams, ToSchema};
 use utoipa_swagger_ui::SwaggerUi;
 
 mod avatar;
+mod category;
 mod contact;
 mod download;
+mod email;
+mod export;
 mod feed;
 mod group;
 mod history;
+mod invite;
 mod login;
 mod message;
+mod monitor;
+mod notice;
 #[cfg(feature = "dev")]
 mod openapi;
+mod pin;
+mod privacy;
+mod role;
+mod search;
+mod totp;
 mod user;
 mod ws;
 
+pub use user::purge_deleted_users;
+
 #[doc(hidden)]
 #[derive(Clone, Debug)]
 pub struct AppState {
@@ -65,7 +81,7 @@ pub static DOC_PATH: &str = "/doc";
 
 /// Veloquent 路由
 pub fn router(state: AppState) -> Router {
-    let auth = middleware::from_extractor::<JWTPayload>();
+    let auth = middleware::from_extractor_with_state::<JWTPayload, _>(state.clone());
     let router = {
         #[cfg(feature = "dev")]
         {
@@ -80,9 +96,18 @@ pub fn router(state: AppState) -> Router {
     };
 
     router
+        .route("/healthz", get(monitor::health_handler))
+        .route("/readyz", get(monitor::ready_handler))
         .route("/login", post(login::login_handler))
+        .route("/login/totp", post(totp::login_totp_handler))
         .route("/renew", get(login::renew_handler))
         .route("/register", post(user::register_handler))
+        .route("/email/verify", post(email::confirm_verify_handler))
+        .route("/password/reset", post(email::request_reset_handler))
+        .route(
+            "/password/reset/confirm",
+            post(email::confirm_reset_handler),
+        )
         .route(
             "/logout",
             delete(login::logout_handler).route_layer(auth.clone()),
@@ -98,6 +123,42 @@ pub fn router(state: AppState) -> Router {
                 .put(user::update_profile_handler)
                 .route_layer(auth.clone()),
         )
+        .route(
+            "/user/email/verify",
+            post(email::request_verify_handler).route_layer(auth.clone()),
+        )
+        .route(
+            "/user/totp",
+            post(totp::enroll_totp_handler)
+                .delete(totp::disable_totp_handler)
+                .route_layer(auth.clone()),
+        )
+        .route(
+            "/user/totp/confirm",
+            post(totp::confirm_totp_handler).route_layer(auth.clone()),
+        )
+        .route(
+            "/user/totp/recovery",
+            post(totp::regenerate_recovery_handler).route_layer(auth.clone()),
+        )
+        .route(
+            "/user/logins",
+            get(login::list_login_handler).route_layer(auth.clone()),
+        )
+        .route(
+            "/user/export",
+            get(export::export_user_handler).route_layer(auth.clone()),
+        )
+        .route(
+            "/user/search",
+            get(search::search_user_handler).route_layer(auth.clone()),
+        )
+        .route(
+            "/user/privacy",
+            get(privacy::get_privacy_handler)
+                .put(privacy::update_privacy_handler)
+                .route_layer(auth.clone()),
+        )
         .route(
             "/contact/list",
             get(contact::get_contacts_handler).route_layer(auth.clone()),
@@ -126,6 +187,36 @@ pub fn router(state: AppState) -> Router {
             "/contact/reject/:id",
             put(contact::reject_contact_handler).route_layer(auth.clone()),
         )
+        .route(
+            "/contact/category",
+            post(category::create_category_handler)
+                .get(category::list_category_handler)
+                .route_layer(auth.clone()),
+        )
+        .route(
+            "/contact/category/order",
+            put(category::order_category_handler).route_layer(auth.clone()),
+        )
+        .route(
+            "/contact/category/assign/:id",
+            put(category::assign_category_handler).route_layer(auth.clone()),
+        )
+        .route(
+            "/contact/category/:id",
+            put(category::rename_category_handler)
+                .delete(category::delete_category_handler)
+                .route_layer(auth.clone()),
+        )
+        .route(
+            "/contact/block",
+            get(contact::get_blocks_handler).route_layer(auth.clone()),
+        )
+        .route(
+            "/contact/block/:id",
+            put(contact::block_contact_handler)
+                .delete(contact::unblock_contact_handler)
+                .route_layer(auth.clone()),
+        )
         .route(
             "/contact/delete/:id",
             delete(contact::delete_contact_handler).route_layer(auth.clone()),
@@ -150,6 +241,36 @@ pub fn router(state: AppState) -> Router {
             "/msg/mask/:id",
             put(message::mask_msg_handler).route_layer(auth.clone()),
         )
+        .route(
+            "/notice/group/:id",
+            post(notice::create_notice_handler)
+                .get(notice::list_notice_handler)
+                .route_layer(auth.clone()),
+        )
+        .route(
+            "/notice/:id",
+            put(notice::edit_notice_handler)
+                .delete(notice::delete_notice_handler)
+                .route_layer(auth.clone()),
+        )
+        .route(
+            "/notice/confirm/:id",
+            put(notice::confirm_notice_handler).route_layer(auth.clone()),
+        )
+        .route(
+            "/notice/reads/:id",
+            get(notice::reads_notice_handler).route_layer(auth.clone()),
+        )
+        .route(
+            "/msg/pin/:id",
+            put(pin::pin_msg_handler)
+                .delete(pin::unpin_msg_handler)
+                .route_layer(auth.clone()),
+        )
+        .route(
+            "/msg/pin/session/:id",
+            get(pin::list_pin_handler).route_layer(auth.clone()),
+        )
         .route(
             "/group/:id",
             get(group::get_group_handler)
@@ -178,6 +299,14 @@ pub fn router(state: AppState) -> Router {
             "/group/edit/:id",
             put(group::pin_group_handler).route_layer(auth.clone()),
         )
+        .route(
+            "/group/profile/:id",
+            put(group::edit_group_handler).route_layer(auth.clone()),
+        )
+        .route(
+            "/group/speak/:id",
+            put(group::speak_group_handler).route_layer(auth.clone()),
+        )
         .route(
             "/group/invite/:id",
             post(group::invite_group_handler).route_layer(auth.clone()),
@@ -186,10 +315,26 @@ pub fn router(state: AppState) -> Router {
             "/group/approve/:id",
             put(group::approve_group_handler).route_layer(auth.clone()),
         )
+        .route(
+            "/invite/group/:id",
+            post(invite::create_invite_handler)
+                .get(invite::list_invite_handler)
+                .route_layer(auth.clone()),
+        )
+        .route(
+            "/invite/:code",
+            post(invite::redeem_invite_handler)
+                .delete(invite::revoke_invite_handler)
+                .route_layer(auth.clone()),
+        )
         .route(
             "/upload",
             post(avatar::upload_handler).route_layer(auth.clone()),
         )
+        .route(
+            "/upload/usage",
+            get(avatar::get_usage_handler).route_layer(auth.clone()),
+        )
         .route(
             "/upload/avatar",
             post(avatar::upload_avatar_handler).route_layer(auth.clone()),
@@ -199,6 +344,16 @@ pub fn router(state: AppState) -> Router {
             get(download::download_handler).route_layer(auth.clone()),
         )
         .route("/ws", get(ws::ws_upgrade_handler))
+        .layer(middleware::from_fn(crate::error::localize))
+        .layer(middleware::from_fn(crate::metrics::track_http))
+        .layer(middleware::from_fn(trace::trace_request))
+        .with_state(state)
+}
+
+/// 监控路由, 可以与 [`router`] 合并, 也可以单独监听
+pub fn metrics_router(state: AppState) -> Router {
+    Router::new()
+        .route("/metrics", get(monitor::metrics_handler))
         .with_state(state)
 }
 
@@ -236,7 +391,7 @@ mod tests {
     async fn create_app_state() -> AppState {
         use migration::MigratorTrait;
         let conn = connect_db_from_env().await;
-        migration::Migrator::down(&conn, Some(8)).await.unwrap();
+        migration::Migrator::down(&conn, Some(23)).await.unwrap();
         conn.execute(Statement::from_string(
             DatabaseBackend::Postgres,
             "CREATE EXTENSION IF NOT EXISTS \"uuid-ossp\";".to_owned(),
@@ -258,16 +413,40 @@ mod tests {
             en_key: jwt::EncodingKey::from_secret(secret.as_bytes()),
         });
         utility::UPLOAD_DIR.get_or_init(|| "upload".to_string());
+        utility::UPLOAD_QUOTA.get_or_init(utility::UploadQuota::default);
+        utility::CONTACT_EXPIRE
+            .get_or_init(|| crate::config::Contact::default().request_expire_after);
+        utility::DELETION_GRACE.get_or_init(|| crate::config::Account::default().deletion_grace);
+        utility::SHUTDOWN_GRACE.get_or_init(|| 0);
+        crate::limit::LOGIN_LIMIT.get_or_init(|| {
+            crate::limit::LoginLimit::from_config(&crate::config::Login {
+                ip_limit: 1000,
+                max_failures: 3,
+                ..Default::default()
+            })
+        });
+        crate::mail::MAIL_SETTING.get_or_init(|| {
+            crate::mail::MailSetting::from_config(crate::config::Mail {
+                dir: Some("mail".to_string()),
+                reset_ip_limit: 3,
+                ..Default::default()
+            })
+            .unwrap()
+        });
     }
 
     async fn start_http_server(addr: &str) -> anyhow::Result<()> {
         init_constants();
+        std::fs::create_dir_all(utility::UPLOAD_DIR.get().unwrap())?;
         let state = create_app_state().await;
         let listener = tokio::net::TcpListener::bind(addr).await?;
-        let app = router(state);
-        axum::serve(listener, app)
-            .with_graceful_shutdown(utility::shutdown_signal())
-            .await?;
+        let app = router(state.clone()).merge(metrics_router(state));
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+        )
+        .with_graceful_shutdown(utility::shutdown_signal())
+        .await?;
         Ok(())
     }
 
@@ -299,14 +478,207 @@ mod tests {
             .unwrap()
     }
 
-    fn request_add_contact(addr: &str, token: &str, id: Uuid) -> Request<Body> {
+    fn request_add_contact(addr: &str, token: &str, id: Uuid, params: &str) -> Request<Body> {
+        request_post_json()
+            .header("Authorization", format!("Bearer {token}"))
+            .uri(format!("{addr}/contact/new/{id}{params}"))
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    fn request_block_contact(addr: &str, token: &str, id: Uuid) -> Request<Body> {
+        request_put_json()
+            .header("Authorization", format!("Bearer {token}"))
+            .uri(format!("{addr}/contact/block/{id}"))
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    fn request_unblock_contact(addr: &str, token: &str, id: Uuid) -> Request<Body> {
+        request_delete()
+            .header("Authorization", format!("Bearer {token}"))
+            .uri(format!("{addr}/contact/block/{id}"))
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    fn request_get_blocks(addr: &str, token: &str) -> Request<Body> {
+        request_get_json()
+            .header("Authorization", format!("Bearer {token}"))
+            .uri(format!("{addr}/contact/block"))
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    fn request_find_user(addr: &str, token: &str, params: &str) -> Request<Body> {
+        request_get_json()
+            .header("Authorization", format!("Bearer {token}"))
+            .uri(format!("{addr}/user{params}"))
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    fn request_search_user(addr: &str, token: &str, params: &str) -> Request<Body> {
+        request_get_json()
+            .header("Authorization", format!("Bearer {token}"))
+            .uri(format!("{addr}/user/search{params}"))
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    fn user_id_of(token: &str) -> Uuid {
+        JWTPayload::try_from(token).unwrap().id
+    }
+
+    fn request_verify_email(addr: &str, token: &str) -> Request<Body> {
+        request_post_json()
+            .header("Authorization", format!("Bearer {token}"))
+            .uri(format!("{addr}/user/email/verify"))
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    fn request_confirm_email(addr: &str, token: &str) -> Request<Body> {
+        request_post_json()
+            .uri(format!("{addr}/email/verify"))
+            .body(Body::from(
+                serde_json::to_vec(&email::TokenPost {
+                    token: token.to_string(),
+                })
+                .unwrap(),
+            ))
+            .unwrap()
+    }
+
+    fn request_reset_password(addr: &str, email: &str) -> Request<Body> {
+        request_post_json()
+            .uri(format!("{addr}/password/reset"))
+            .body(Body::from(
+                serde_json::to_vec(&email::ResetRequest {
+                    email: email.to_string(),
+                })
+                .unwrap(),
+            ))
+            .unwrap()
+    }
+
+    fn request_confirm_reset(addr: &str, token: &str, password: &str) -> Request<Body> {
+        request_post_json()
+            .uri(format!("{addr}/password/reset/confirm"))
+            .body(Body::from(
+                serde_json::to_vec(&email::ResetConfirm {
+                    token: token.to_string(),
+                    password: password.to_string(),
+                })
+                .unwrap(),
+            ))
+            .unwrap()
+    }
+
+    fn request_enroll_totp(addr: &str, token: &str) -> Request<Body> {
+        request_post_json()
+            .header("Authorization", format!("Bearer {token}"))
+            .uri(format!("{addr}/user/totp"))
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    fn request_confirm_totp(addr: &str, token: &str, code: &str) -> Request<Body> {
+        request_post_json()
+            .header("Authorization", format!("Bearer {token}"))
+            .uri(format!("{addr}/user/totp/confirm"))
+            .body(Body::from(
+                serde_json::to_vec(&totp::TotpCode {
+                    code: code.to_string(),
+                })
+                .unwrap(),
+            ))
+            .unwrap()
+    }
+
+    fn request_disable_totp(addr: &str, token: &str, password: &str) -> Request<Body> {
+        request_delete()
+            .header("Content-Type", "application/json")
+            .header("Authorization", format!("Bearer {token}"))
+            .uri(format!("{addr}/user/totp"))
+            .body(Body::from(
+                serde_json::to_vec(&totp::PasswordConfirm {
+                    password: password.to_string(),
+                })
+                .unwrap(),
+            ))
+            .unwrap()
+    }
+
+    fn request_regenerate_recovery(addr: &str, token: &str, password: &str) -> Request<Body> {
+        request_post_json()
+            .header("Authorization", format!("Bearer {token}"))
+            .uri(format!("{addr}/user/totp/recovery"))
+            .body(Body::from(
+                serde_json::to_vec(&totp::PasswordConfirm {
+                    password: password.to_string(),
+                })
+                .unwrap(),
+            ))
+            .unwrap()
+    }
+
+    fn request_login_totp(addr: &str, challenge: &str, code: &str) -> Request<Body> {
         request_post_json()
+            .uri(format!("{addr}/login/totp"))
+            .body(Body::from(
+                serde_json::to_vec(&totp::TotpLogin {
+                    challenge: challenge.to_string(),
+                    code: code.to_string(),
+                })
+                .unwrap(),
+            ))
+            .unwrap()
+    }
+
+    fn request_get_logins(addr: &str, token: &str) -> Request<Body> {
+        request_get_json()
             .header("Authorization", format!("Bearer {token}"))
-            .uri(format!("{addr}/contact/new/{id}"))
+            .uri(format!("{addr}/user/logins"))
             .body(Body::empty())
             .unwrap()
     }
 
+    /// 从测试邮件目录中读取最近一封包含 `keyword` 的邮件里的令牌
+    ///
+    /// 邮件可能在后台发送, 因此轮询等待
+    async fn token_from_mail(to: &str, keyword: &str) -> String {
+        for _ in 0..50 {
+            if let Ok(body) = std::fs::read_to_string(format!("mail/{to}.txt")) {
+                if body.contains(keyword) {
+                    return body.split_once("令牌: ").unwrap().1.trim().to_string();
+                }
+            }
+            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        }
+        panic!("no mail to [{to}] containing [{keyword}]");
+    }
+
+    fn request_get_profile(addr: &str, token: &str, id: Uuid) -> Request<Body> {
+        request_get_json()
+            .header("Authorization", format!("Bearer {token}"))
+            .uri(format!("{addr}/user/profile?id={id}"))
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    fn request_update_privacy(
+        addr: &str,
+        token: &str,
+        settings: privacy::PrivacySettings,
+    ) -> Request<Body> {
+        request_put_json()
+            .header("Authorization", format!("Bearer {token}"))
+            .uri(format!("{addr}/user/privacy"))
+            .body(Body::from(serde_json::to_vec(&settings).unwrap()))
+            .unwrap()
+    }
+
     fn request_get_json() -> http::request::Builder {
         Request::builder()
             .header("Content-Type", "application/json")
@@ -367,123 +739,334 @@ mod tests {
         Request::builder().method("DELETE")
     }
 
-    fn request_delete_user(addr: &str, token: &str) -> Request<Body> {
-        request_delete()
+    fn request_get_group(addr: &str, token: &str, group: Uuid) -> Request<Body> {
+        request_get_json()
             .header("Authorization", format!("Bearer {token}"))
-            .uri(format!("{addr}/user/profile"))
+            .uri(format!("{addr}/group/{group}"))
             .body(Body::empty())
             .unwrap()
     }
 
-    fn request_edit_contact(addr: &str, token: &str, id: Uuid, params: &str) -> Request<Body> {
-        request_put_json()
+    fn request_create_notice(
+        addr: &str,
+        token: &str,
+        group: Uuid,
+        notice: notice::NoticePost,
+    ) -> Request<Body> {
+        request_post_json()
             .header("Authorization", format!("Bearer {token}"))
-            .uri(format!("{addr}/contact/edit/{id}{params}"))
-            .body(Body::empty())
+            .uri(format!("{addr}/notice/group/{group}"))
+            .body(Body::from(serde_json::to_vec(&notice).unwrap()))
             .unwrap()
     }
 
-    fn request_get_categories(addr: &str, token: &str) -> Request<Body> {
+    fn request_list_notices(addr: &str, token: &str, group: Uuid) -> Request<Body> {
         request_get_json()
             .header("Authorization", format!("Bearer {token}"))
-            .uri(format!("{addr}/contact/categories"))
+            .uri(format!("{addr}/notice/group/{group}"))
             .body(Body::empty())
             .unwrap()
     }
 
-    fn request_create_group(addr: &str, token: &str, group: group::GroupPost) -> Request<Body> {
-        request_post_json()
+    fn request_edit_notice(
+        addr: &str,
+        token: &str,
+        notice: Uuid,
+        edition: notice::NoticeEdition,
+    ) -> Request<Body> {
+        request_put_json()
             .header("Authorization", format!("Bearer {token}"))
-            .uri(format!("{addr}/group/new"))
-            .body(Body::from(serde_json::to_vec(&group).unwrap()))
+            .uri(format!("{addr}/notice/{notice}"))
+            .body(Body::from(serde_json::to_vec(&edition).unwrap()))
             .unwrap()
     }
 
-    fn request_edit_group(addr: &str, token: &str, group: Uuid, params: &str) -> Request<Body> {
-        request_put_json()
+    fn request_delete_notice(addr: &str, token: &str, notice: Uuid) -> Request<Body> {
+        request_delete()
             .header("Authorization", format!("Bearer {token}"))
-            .uri(format!("{addr}/group/edit/{group}{params}"))
+            .uri(format!("{addr}/notice/{notice}"))
             .body(Body::empty())
             .unwrap()
     }
 
-    fn request_group_invite(
-        addr: &str,
-        token: &str,
-        group: Uuid,
-        users: Vec<Uuid>,
-    ) -> Request<Body> {
-        request_post_json()
+    fn request_confirm_notice(addr: &str, token: &str, notice: Uuid) -> Request<Body> {
+        request_put_json()
             .header("Authorization", format!("Bearer {token}"))
-            .uri(format!("{addr}/group/invite/{group}"))
-            .body(Body::from(serde_json::to_vec(&users).unwrap()))
+            .uri(format!("{addr}/notice/confirm/{notice}"))
+            .body(Body::empty())
             .unwrap()
     }
 
-    fn request_group_view(addr: &str, token: &str, group: Uuid) -> Request<Body> {
+    fn request_notice_reads(addr: &str, token: &str, notice: Uuid) -> Request<Body> {
         request_get_json()
             .header("Authorization", format!("Bearer {token}"))
-            .uri(format!("{addr}/group/manage/{group}"))
+            .uri(format!("{addr}/notice/reads/{notice}"))
             .body(Body::empty())
             .unwrap()
     }
 
-    fn request_group_manage(addr: &str, token: &str, group: Uuid, params: &str) -> Request<Body> {
+    fn request_pin_msg(addr: &str, token: &str, msg: Uuid) -> Request<Body> {
         request_put_json()
             .header("Authorization", format!("Bearer {token}"))
-            .uri(format!("{addr}/group/manage/{group}{params}"))
+            .uri(format!("{addr}/msg/pin/{msg}"))
             .body(Body::empty())
             .unwrap()
     }
 
-    fn request_group_approve(addr: &str, token: &str, group: Uuid, params: &str) -> Request<Body> {
-        request_put_json()
+    fn request_unpin_msg(addr: &str, token: &str, msg: Uuid) -> Request<Body> {
+        request_delete()
             .header("Authorization", format!("Bearer {token}"))
-            .uri(format!("{addr}/group/approve/{group}{params}"))
+            .uri(format!("{addr}/msg/pin/{msg}"))
             .body(Body::empty())
             .unwrap()
     }
 
-    fn request_send_msg(
-        addr: &str,
-        token: &str,
-        session: Uuid,
-        msg: super::message::MsgPost,
-    ) -> Request<Body> {
-        request_post_json()
+    fn request_list_pins(addr: &str, token: &str, session: Uuid) -> Request<Body> {
+        request_get_json()
             .header("Authorization", format!("Bearer {token}"))
-            .uri(format!("{addr}/msg/session/{session}"))
-            .body(Body::from(serde_json::to_vec(&msg).unwrap()))
+            .uri(format!("{addr}/msg/pin/session/{session}"))
+            .body(Body::empty())
             .unwrap()
     }
 
-    fn request_get_msg(addr: &str, token: &str, session: Uuid) -> Request<Body> {
-        request_get_json()
+    fn request_delete_group(addr: &str, token: &str, group: Uuid, params: &str) -> Request<Body> {
+        request_delete()
             .header("Authorization", format!("Bearer {token}"))
-            .uri(format!("{addr}/msg/session/{session}"))
+            .uri(format!("{addr}/group/{group}{params}"))
             .body(Body::empty())
             .unwrap()
     }
 
-    fn request_renew_jwt(addr: &str, token: &str) -> Request<Body> {
-        request_get_json()
+    fn request_delete_user(addr: &str, token: &str) -> Request<Body> {
+        request_delete()
             .header("Authorization", format!("Bearer {token}"))
-            .uri(format!("{addr}/renew"))
+            .uri(format!("{addr}/user/profile"))
             .body(Body::empty())
             .unwrap()
     }
 
-    #[tokio::test]
-    async fn integration() {
-        let addr = "127.0.0.1:8000";
-        tokio::spawn(start_http_server(addr));
-        let ws_url = format!("ws://{addr}/ws");
-        let addr = format!("http://{addr}");
-        tokio::time::sleep(tokio::time::Duration::from_secs(6)).await;
-        let client =
-            hyper_util::client::legacy::Client::builder(hyper_util::rt::TokioExecutor::new())
-                .build_http();
-        // test if swagger doc is available
+    fn request_edit_contact(addr: &str, token: &str, id: Uuid, params: &str) -> Request<Body> {
+        request_put_json()
+            .header("Authorization", format!("Bearer {token}"))
+            .uri(format!("{addr}/contact/edit/{id}{params}"))
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    fn request_get_categories(addr: &str, token: &str) -> Request<Body> {
+        request_get_json()
+            .header("Authorization", format!("Bearer {token}"))
+            .uri(format!("{addr}/contact/categories"))
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    fn request_create_category(addr: &str, token: &str, name: &str) -> Request<Body> {
+        request_post_json()
+            .header("Authorization", format!("Bearer {token}"))
+            .uri(format!("{addr}/contact/category"))
+            .body(Body::from(
+                serde_json::to_vec(&category::CategoryPost {
+                    name: name.to_string(),
+                })
+                .unwrap(),
+            ))
+            .unwrap()
+    }
+
+    fn request_rename_category(addr: &str, token: &str, id: Uuid, name: &str) -> Request<Body> {
+        request_put_json()
+            .header("Authorization", format!("Bearer {token}"))
+            .uri(format!("{addr}/contact/category/{id}"))
+            .body(Body::from(
+                serde_json::to_vec(&category::CategoryPost {
+                    name: name.to_string(),
+                })
+                .unwrap(),
+            ))
+            .unwrap()
+    }
+
+    fn request_list_category(addr: &str, token: &str) -> Request<Body> {
+        request_get_json()
+            .header("Authorization", format!("Bearer {token}"))
+            .uri(format!("{addr}/contact/category"))
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    fn request_delete_category(addr: &str, token: &str, id: Uuid) -> Request<Body> {
+        request_delete()
+            .header("Authorization", format!("Bearer {token}"))
+            .uri(format!("{addr}/contact/category/{id}"))
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    fn request_order_category(addr: &str, token: &str, order: Vec<Uuid>) -> Request<Body> {
+        request_put_json()
+            .header("Authorization", format!("Bearer {token}"))
+            .uri(format!("{addr}/contact/category/order"))
+            .body(Body::from(serde_json::to_vec(&order).unwrap()))
+            .unwrap()
+    }
+
+    fn request_assign_category(
+        addr: &str,
+        token: &str,
+        id: Uuid,
+        contacts: Vec<Uuid>,
+    ) -> Request<Body> {
+        request_put_json()
+            .header("Authorization", format!("Bearer {token}"))
+            .uri(format!("{addr}/contact/category/assign/{id}"))
+            .body(Body::from(serde_json::to_vec(&contacts).unwrap()))
+            .unwrap()
+    }
+
+    fn request_create_group(addr: &str, token: &str, group: group::GroupPost) -> Request<Body> {
+        request_post_json()
+            .header("Authorization", format!("Bearer {token}"))
+            .uri(format!("{addr}/group/new"))
+            .body(Body::from(serde_json::to_vec(&group).unwrap()))
+            .unwrap()
+    }
+
+    fn request_edit_group(addr: &str, token: &str, group: Uuid, params: &str) -> Request<Body> {
+        request_put_json()
+            .header("Authorization", format!("Bearer {token}"))
+            .uri(format!("{addr}/group/edit/{group}{params}"))
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    fn request_edit_group_profile(
+        addr: &str,
+        token: &str,
+        group: Uuid,
+        edition: group::GroupEdition,
+    ) -> Request<Body> {
+        request_put_json()
+            .header("Authorization", format!("Bearer {token}"))
+            .uri(format!("{addr}/group/profile/{group}"))
+            .body(Body::from(serde_json::to_vec(&edition).unwrap()))
+            .unwrap()
+    }
+
+    fn request_create_invite(
+        addr: &str,
+        token: &str,
+        group: Uuid,
+        invite: invite::InvitePost,
+    ) -> Request<Body> {
+        request_post_json()
+            .header("Authorization", format!("Bearer {token}"))
+            .uri(format!("{addr}/invite/group/{group}"))
+            .body(Body::from(serde_json::to_vec(&invite).unwrap()))
+            .unwrap()
+    }
+
+    fn request_redeem_invite(addr: &str, token: &str, code: &str) -> Request<Body> {
+        request_post_json()
+            .header("Authorization", format!("Bearer {token}"))
+            .uri(format!("{addr}/invite/{code}"))
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    fn request_group_invite(
+        addr: &str,
+        token: &str,
+        group: Uuid,
+        users: Vec<Uuid>,
+    ) -> Request<Body> {
+        request_post_json()
+            .header("Authorization", format!("Bearer {token}"))
+            .uri(format!("{addr}/group/invite/{group}"))
+            .body(Body::from(serde_json::to_vec(&users).unwrap()))
+            .unwrap()
+    }
+
+    fn request_group_view(addr: &str, token: &str, group: Uuid) -> Request<Body> {
+        request_get_json()
+            .header("Authorization", format!("Bearer {token}"))
+            .uri(format!("{addr}/group/manage/{group}"))
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    fn request_group_manage(addr: &str, token: &str, group: Uuid, params: &str) -> Request<Body> {
+        request_put_json()
+            .header("Authorization", format!("Bearer {token}"))
+            .uri(format!("{addr}/group/manage/{group}{params}"))
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    fn request_group_speak(addr: &str, token: &str, group: Uuid, params: &str) -> Request<Body> {
+        request_put_json()
+            .header("Authorization", format!("Bearer {token}"))
+            .uri(format!("{addr}/group/speak/{group}{params}"))
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    fn request_group_approve(addr: &str, token: &str, group: Uuid, params: &str) -> Request<Body> {
+        request_put_json()
+            .header("Authorization", format!("Bearer {token}"))
+            .uri(format!("{addr}/group/approve/{group}{params}"))
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    fn request_send_msg(
+        addr: &str,
+        token: &str,
+        session: Uuid,
+        msg: super::message::MsgPost,
+    ) -> Request<Body> {
+        request_post_json()
+            .header("Authorization", format!("Bearer {token}"))
+            .uri(format!("{addr}/msg/session/{session}"))
+            .body(Body::from(serde_json::to_vec(&msg).unwrap()))
+            .unwrap()
+    }
+
+    fn request_get_msg(addr: &str, token: &str, session: Uuid) -> Request<Body> {
+        request_get_json()
+            .header("Authorization", format!("Bearer {token}"))
+            .uri(format!("{addr}/msg/session/{session}"))
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    fn request_renew_jwt(addr: &str, token: &str) -> Request<Body> {
+        request_get_json()
+            .header("Authorization", format!("Bearer {token}"))
+            .uri(format!("{addr}/renew"))
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    fn request_get_usage(addr: &str, token: &str) -> Request<Body> {
+        request_get_json()
+            .header("Authorization", format!("Bearer {token}"))
+            .uri(format!("{addr}/upload/usage"))
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn integration() {
+        let addr = "127.0.0.1:8000";
+        tokio::spawn(start_http_server(addr));
+        let ws_url = format!("ws://{addr}/ws");
+        let addr = format!("http://{addr}");
+        tokio::time::sleep(tokio::time::Duration::from_secs(6)).await;
+        let client =
+            hyper_util::client::legacy::Client::builder(hyper_util::rt::TokioExecutor::new())
+                .build_http();
+        // test if swagger doc is available
         #[cfg(feature = "dev")]
         {
             let response = client.request(request_doc(&addr)).await.unwrap();
@@ -519,12 +1102,12 @@ mod tests {
                     bio: None,
                     link: None,
                     password: "123456".to_string(),
-                    email: "test@example.com".to_string(),
+                    email: "Test@Example.com".to_string(),
                 },
             ))
             .await
             .unwrap();
-        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(response.status(), StatusCode::CONFLICT);
         // test if register with bad phone is rejected
         let response = client
             .request(request_register(
@@ -548,7 +1131,7 @@ mod tests {
             .request(request_login(
                 &addr,
                 login::LoginRequest {
-                    name: "test_user_1".to_string(),
+                    identifier: "test_user_1".to_string(),
                     password: "123456".to_string(),
                 },
             ))
@@ -558,6 +1141,20 @@ mod tests {
         let token = response.into_body().collect().await.unwrap().aggregate();
         let token: login::LoginResponse = serde_json::from_reader(token.reader()).unwrap();
         let user_1_token = token.token;
+        // test if login with email or phone is available
+        for identifier in ["TEST@example.com", "18999990000"] {
+            let response = client
+                .request(request_login(
+                    &addr,
+                    login::LoginRequest {
+                        identifier: identifier.to_string(),
+                        password: "123456".to_string(),
+                    },
+                ))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
         let user_1 = jwt::JWTPayload::try_from(user_1_token.as_str()).unwrap().id;
         let (mut socket_1, _response) = tokio_tungstenite::connect_async(&ws_url).await.unwrap();
         assert!(socket_1
@@ -633,90 +1230,389 @@ mod tests {
             .await
             .is_ok());
         let socket_3 = Arc::new(Mutex::new(socket_3));
-        let socket = socket_2.clone();
-        let task = tokio::task::spawn(async move {
-            let feed_2: feed::Notification =
-                match socket.lock().await.next().await.unwrap().unwrap() {
-                    tungstenite::Message::Text(msg) => serde_json::from_str(&msg).unwrap(),
-                    _ => panic!("unexpected message"),
-                };
-            let feed_2 = match feed_2 {
-                feed::Notification::ContactRequests { items } => items,
-                _ => panic!("unexpected message"),
-            };
-            assert_eq!(feed_2.num, 1);
-            assert_eq!(feed_2.items[0].id, user_1);
-        });
+        // test email verification
+        let verify_token = token_from_mail("test_3@example.com", "验证你的邮箱").await;
+        let response = client
+            .request(request_verify_email(&addr, &user_3_token))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let response = client
+            .request(request_confirm_email(&addr, "wrong"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
         let response = client
-            .request(request_add_contact(&addr, &user_1_token, user_2))
+            .request(request_confirm_email(&addr, &verify_token))
             .await
             .unwrap();
         assert_eq!(response.status(), StatusCode::OK);
-        task.await.unwrap();
-        let response: contact::ContactList = serde_json::from_reader(
+        let response = client
+            .request(request_confirm_email(&addr, &verify_token))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let profile: user::UserProfile = serde_json::from_reader(
             res_to_json(
                 client
-                    .request(request_get_new_contacts(&addr, &user_2_token))
+                    .request(request_get_profile(&addr, &user_3_token, user_3))
                     .await
                     .unwrap(),
             )
             .await,
         )
         .unwrap();
-        assert_eq!(response.num, 1);
-        assert_eq!(response.items[0].id, user_1);
-        let socket = socket_1.clone();
-        let task = tokio::task::spawn(async move {
-            let feed: feed::Notification = match socket.lock().await.next().await.unwrap().unwrap()
-            {
-                tungstenite::Message::Text(msg) => serde_json::from_str(&msg).unwrap(),
-                _ => panic!("unexpected message"),
-            };
-            let feed = match feed {
-                feed::Notification::ContactRequests { items } => items,
-                _ => panic!("unexpected message"),
-            };
-            assert_eq!(feed.num, 1);
-            assert_eq!(feed.items[0].id, user_3);
-        });
+        assert!(profile.email_verified);
         let response = client
-            .request(request_add_contact(&addr, &user_3_token, user_1))
+            .request(request_verify_email(&addr, &user_3_token))
             .await
             .unwrap();
-        assert_eq!(response.status(), StatusCode::OK);
-        task.await.unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        // test password reset
         let response = client
-            .request(request_add_contact(&addr, &user_3_token, user_3))
+            .request(request_reset_password(&addr, "nobody@example.com"))
             .await
             .unwrap();
-        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
         let response = client
-            .request(request_reject_contact(&addr, &user_1_token, user_3))
+            .request(request_reset_password(&addr, "test_3@example.com"))
             .await
             .unwrap();
-        assert_eq!(response.status(), StatusCode::OK);
-        let socket = socket_1.clone();
-        let task = tokio::task::spawn(async move {
-            let feed: feed::Notification = match socket.lock().await.next().await.unwrap().unwrap()
-            {
-                tungstenite::Message::Text(msg) => serde_json::from_str(&msg).unwrap(),
-                _ => panic!("unexpected message"),
-            };
-            let feed = match feed {
-                feed::Notification::ContactAccepts { items } => items,
-                _ => panic!("unexpected message"),
-            };
-            assert_eq!(feed.num, 1);
-            assert_eq!(feed.items[0].id, user_2);
-        });
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        let reset_token = token_from_mail("test_3@example.com", "重置你的密码").await;
+        // test if throttled reset request is indistinguishable and keeps the token
         let response = client
-            .request(request_accept_contact(&addr, &user_2_token, user_1))
+            .request(request_reset_password(&addr, "test_3@example.com"))
             .await
             .unwrap();
-        assert_eq!(response.status(), StatusCode::OK);
-        task.await.unwrap();
-        let response: contact::ContactList = serde_json::from_reader(
-            res_to_json(
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        // test if too many reset requests from one ip are rejected
+        let response = client
+            .request(request_reset_password(&addr, "nobody@example.com"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let response = client
+            .request(request_confirm_reset(&addr, &reset_token, "654321"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = client
+            .request(request_login(
+                &addr,
+                login::LoginRequest {
+                    identifier: "test_user_3".to_string(),
+                    password: "654321".to_string(),
+                },
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        // test if login failures are uniform and lead to lockout
+        let response = client
+            .request(request_login(
+                &addr,
+                login::LoginRequest {
+                    identifier: "nobody".to_string(),
+                    password: "654321".to_string(),
+                },
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let mut unknown: serde_json::Value =
+            serde_json::from_reader(res_to_json(response).await).unwrap();
+        unknown["request_id"].take();
+        let user_3_login = |password: &str| login::LoginRequest {
+            identifier: "test_user_3".to_string(),
+            password: password.to_string(),
+        };
+        for _ in 0..3 {
+            let response = client
+                .request(request_login(&addr, user_3_login("wrong")))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+            let mut wrong: serde_json::Value =
+                serde_json::from_reader(res_to_json(response).await).unwrap();
+            wrong["request_id"].take();
+            assert_eq!(wrong, unknown);
+        }
+        let response = client
+            .request(request_login(&addr, user_3_login("654321")))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let records: Vec<login::LoginRecord> = serde_json::from_reader(
+            res_to_json(
+                client
+                    .request(request_get_logins(&addr, &user_3_token))
+                    .await
+                    .unwrap(),
+            )
+            .await,
+        )
+        .unwrap();
+        assert_eq!(records.len(), 5);
+        assert_eq!(records[0].reason.as_deref(), Some("locked"));
+        assert_eq!(records[1].reason.as_deref(), Some("wrong_password"));
+        assert!(records[4].success);
+        assert_eq!(records[4].ip, "127.0.0.1");
+        // test two-factor authentication
+        let user_2_login = login::LoginRequest {
+            identifier: "test_user_2".to_string(),
+            password: "123456".to_string(),
+        };
+        let response = client
+            .request(request_confirm_totp(&addr, &user_2_token, "000000"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let enrollment: totp::TotpEnrollment = serde_json::from_reader(
+            res_to_json(
+                client
+                    .request(request_enroll_totp(&addr, &user_2_token))
+                    .await
+                    .unwrap(),
+            )
+            .await,
+        )
+        .unwrap();
+        assert!(enrollment
+            .uri
+            .starts_with("otpauth://totp/Veloquent:test_user_2?"));
+        let totp_now =
+            || totp::totp_code(&enrollment.secret, jsonwebtoken::get_current_timestamp()).unwrap();
+        let wrong_code = format!("{:06}", (totp_now().parse::<u32>().unwrap() + 1) % 1000000);
+        let response = client
+            .request(request_confirm_totp(&addr, &user_2_token, &wrong_code))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = client
+            .request(request_login(&addr, user_2_login.clone()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let recovery: totp::RecoveryCodes = serde_json::from_reader(
+            res_to_json(
+                client
+                    .request(request_confirm_totp(&addr, &user_2_token, &totp_now()))
+                    .await
+                    .unwrap(),
+            )
+            .await,
+        )
+        .unwrap();
+        assert_eq!(recovery.codes.len(), 10);
+        let response = client
+            .request(request_login(&addr, user_2_login.clone()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        let challenge: totp::TotpChallenge =
+            serde_json::from_reader(res_to_json(response).await).unwrap();
+        let response = client
+            .request(request_get_profile(&addr, &challenge.challenge, user_2))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = client
+            .request(request_login_totp(&addr, &challenge.challenge, &wrong_code))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        // 确认绑定时已使用当前时间步, 使用下一个时间步的验证码登录
+        let totp_next = totp::totp_code(
+            &enrollment.secret,
+            jsonwebtoken::get_current_timestamp() + 30,
+        )
+        .unwrap();
+        let response = client
+            .request(request_login_totp(&addr, &challenge.challenge, &totp_next))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = client
+            .request(request_login_totp(&addr, &challenge.challenge, &totp_next))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = client
+            .request(request_login_totp(
+                &addr,
+                &challenge.challenge,
+                &recovery.codes[0],
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = client
+            .request(request_login_totp(
+                &addr,
+                &challenge.challenge,
+                &recovery.codes[0],
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = client
+            .request(request_regenerate_recovery(&addr, &user_2_token, "wrong"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let regenerated: totp::RecoveryCodes = serde_json::from_reader(
+            res_to_json(
+                client
+                    .request(request_regenerate_recovery(&addr, &user_2_token, "123456"))
+                    .await
+                    .unwrap(),
+            )
+            .await,
+        )
+        .unwrap();
+        let response = client
+            .request(request_login_totp(
+                &addr,
+                &challenge.challenge,
+                &recovery.codes[1],
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = client
+            .request(request_login_totp(
+                &addr,
+                &challenge.challenge,
+                &regenerated.codes[1],
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = client
+            .request(request_disable_totp(&addr, &user_2_token, "wrong"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = client
+            .request(request_disable_totp(&addr, &user_2_token, "123456"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        let response = client
+            .request(request_login(&addr, user_2_login))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        // test if error code is stable and message follows accept-language
+        let mut req = request_add_contact(&addr, &user_1_token, user_1, "");
+        req.headers_mut()
+            .insert("Accept-Language", "zh-CN,zh;q=0.9".parse().unwrap());
+        req.headers_mut()
+            .insert("X-Request-Id", "test-request".parse().unwrap());
+        let response = client.request(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(response.headers()["x-request-id"], "test-request");
+        let error: serde_json::Value =
+            serde_json::from_reader(res_to_json(response).await).unwrap();
+        assert_eq!(error["code"], "CONTACT_SELF");
+        assert_eq!(error["msg"], "不能添加自己为好友");
+        assert_eq!(error["request_id"], "test-request");
+        assert_eq!(error["details"]["reason"], "cannot add self");
+        let socket = socket_2.clone();
+        let task = tokio::task::spawn(async move {
+            let feed_2: feed::Notification =
+                match socket.lock().await.next().await.unwrap().unwrap() {
+                    tungstenite::Message::Text(msg) => serde_json::from_str(&msg).unwrap(),
+                    _ => panic!("unexpected message"),
+                };
+            let feed_2 = match feed_2 {
+                feed::Notification::ContactRequests { items } => items,
+                _ => panic!("unexpected message"),
+            };
+            assert_eq!(feed_2.num, 1);
+            assert_eq!(feed_2.items[0].id, user_1);
+        });
+        let response = client
+            .request(request_add_contact(
+                &addr,
+                &user_1_token,
+                user_2,
+                "?message=hi&source=search",
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        task.await.unwrap();
+        let response: contact::ContactList = serde_json::from_reader(
+            res_to_json(
+                client
+                    .request(request_get_new_contacts(&addr, &user_2_token))
+                    .await
+                    .unwrap(),
+            )
+            .await,
+        )
+        .unwrap();
+        assert_eq!(response.num, 1);
+        assert_eq!(response.items[0].id, user_1);
+        assert_eq!(response.items[0].message, Some("hi".to_string()));
+        assert_eq!(
+            response.items[0].source,
+            Some(contact::ContactSource::Search)
+        );
+        let socket = socket_1.clone();
+        let task = tokio::task::spawn(async move {
+            let feed: feed::Notification = match socket.lock().await.next().await.unwrap().unwrap()
+            {
+                tungstenite::Message::Text(msg) => serde_json::from_str(&msg).unwrap(),
+                _ => panic!("unexpected message"),
+            };
+            let feed = match feed {
+                feed::Notification::ContactRequests { items } => items,
+                _ => panic!("unexpected message"),
+            };
+            assert_eq!(feed.num, 1);
+            assert_eq!(feed.items[0].id, user_3);
+        });
+        let response = client
+            .request(request_add_contact(&addr, &user_3_token, user_1, ""))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        task.await.unwrap();
+        let response = client
+            .request(request_add_contact(&addr, &user_3_token, user_3, ""))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response = client
+            .request(request_reject_contact(&addr, &user_1_token, user_3))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let socket = socket_1.clone();
+        let task = tokio::task::spawn(async move {
+            let feed: feed::Notification = match socket.lock().await.next().await.unwrap().unwrap()
+            {
+                tungstenite::Message::Text(msg) => serde_json::from_str(&msg).unwrap(),
+                _ => panic!("unexpected message"),
+            };
+            let feed = match feed {
+                feed::Notification::ContactAccepts { items } => items,
+                _ => panic!("unexpected message"),
+            };
+            assert_eq!(feed.num, 1);
+            assert_eq!(feed.items[0].id, user_2);
+        });
+        let response = client
+            .request(request_accept_contact(&addr, &user_2_token, user_1))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        task.await.unwrap();
+        let response: contact::ContactList = serde_json::from_reader(
+            res_to_json(
                 client
                     .request(request_get_contacts(&addr, &user_2_token, ""))
                     .await
@@ -749,11 +1645,11 @@ mod tests {
             let _ = socket.lock().await.next().await.unwrap();
         };
         let response = client
-            .request(request_add_contact(&addr, &user_1_token, user_3))
+            .request(request_add_contact(&addr, &user_1_token, user_3, ""))
             .await
             .unwrap();
         assert_eq!(response.status(), StatusCode::OK);
-        consume_msg(socket_3).await;
+        consume_msg(socket_3.clone()).await;
         let response = client
             .request(request_accept_contact(&addr, &user_3_token, user_1))
             .await
@@ -790,6 +1686,55 @@ mod tests {
             .await
             .unwrap();
         assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        let response = client
+            .request(request_get_profile(
+                &addr,
+                &user_1_token,
+                user_id_of(&user_token),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        // test if token of deleted user is rejected
+        let response = client
+            .request(request_search_user(&addr, &user_token, "?q=test"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        // test if deleted user can be restored by login within grace period
+        let login = login::LoginRequest {
+            identifier: "test_user1".to_string(),
+            password: "123456".to_string(),
+        };
+        let response = client
+            .request(request_login(&addr, login.clone()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let user_token: login::LoginResponse =
+            serde_json::from_reader(res_to_json(response).await).unwrap();
+        let user_token = user_token.token;
+        let response = client
+            .request(request_get_profile(
+                &addr,
+                &user_1_token,
+                user_id_of(&user_token),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = client
+            .request(request_delete_user(&addr, &user_token))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        // test if deleted user is purged after grace period
+        let purged = entity::user::Model::purge_deleted(0, &connect_db_from_env().await)
+            .await
+            .unwrap();
+        assert_eq!(purged, 1);
+        let response = client.request(request_login(&addr, login)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
         // test if user profile can be edited
         let response = client
             .request(request_edit_user(
@@ -829,6 +1774,8 @@ mod tests {
                         &user_1_token,
                         group::GroupPost {
                             name: Some("test_group".to_string()),
+                            avatar: None,
+                            description: None,
                             members: vec![user_1, user_2],
                         },
                     ))
@@ -939,13 +1886,151 @@ mod tests {
         .unwrap();
         assert_eq!(response.len(), 1);
         assert_eq!(response[0], "family");
-        // test if user can send message to contact
+        // test if user can manage categories
+        let work: category::CategoryItem = serde_json::from_reader(
+            res_to_json(
+                client
+                    .request(request_create_category(&addr, &user_1_token, "work"))
+                    .await
+                    .unwrap(),
+            )
+            .await,
+        )
+        .unwrap();
+        assert_eq!(work.position, 1);
+        assert_eq!(work.num, 0);
         let response = client
-            .request(request_send_msg(
-                &addr,
-                &user_1_token,
-                chat_1_2,
-                super::message::MsgPost {
+            .request(request_create_category(&addr, &user_1_token, "work"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let response = client
+            .request(request_create_category(&addr, &user_1_token, " "))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response = client
+            .request(request_rename_category(
+                &addr,
+                &user_1_token,
+                work.id,
+                "family",
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let response = client
+            .request(request_rename_category(
+                &addr,
+                &user_2_token,
+                work.id,
+                "office",
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = client
+            .request(request_order_category(&addr, &user_1_token, vec![work.id]))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let family = client
+            .request(request_list_category(&addr, &user_1_token))
+            .await
+            .unwrap();
+        let family: Vec<category::CategoryItem> =
+            serde_json::from_reader(res_to_json(family).await).unwrap();
+        assert_eq!(family.len(), 2);
+        assert_eq!(family[0].name, "family");
+        assert_eq!(family[0].num, 1);
+        let family = family[0].id;
+        let categories: Vec<category::CategoryItem> = serde_json::from_reader(
+            res_to_json(
+                client
+                    .request(request_order_category(
+                        &addr,
+                        &user_1_token,
+                        vec![work.id, family],
+                    ))
+                    .await
+                    .unwrap(),
+            )
+            .await,
+        )
+        .unwrap();
+        assert_eq!(categories[0].id, work.id);
+        assert_eq!(categories[1].id, family);
+        let response = client
+            .request(request_assign_category(
+                &addr,
+                &user_1_token,
+                work.id,
+                vec![user_2],
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = client
+            .request(request_assign_category(
+                &addr,
+                &user_1_token,
+                work.id,
+                vec![group.id],
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = client
+            .request(request_rename_category(
+                &addr,
+                &user_1_token,
+                work.id,
+                "office",
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response: contact::ContactList = serde_json::from_reader(
+            res_to_json(
+                client
+                    .request(request_get_contacts(&addr, &user_1_token, ""))
+                    .await
+                    .unwrap(),
+            )
+            .await,
+        )
+        .unwrap();
+        assert_eq!(response.groups.len(), 3);
+        assert_eq!(response.groups[0].category, Some(work.id));
+        assert_eq!(response.groups[0].items, vec![user_2]);
+        assert!(response.groups[1].items.is_empty());
+        assert_eq!(response.groups[2].category, None);
+        assert_eq!(response.groups[2].items, vec![user_3]);
+        let response = client
+            .request(request_delete_category(&addr, &user_1_token, work.id))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        let response: contact::ContactList = serde_json::from_reader(
+            res_to_json(
+                client
+                    .request(request_get_contacts(&addr, &user_1_token, ""))
+                    .await
+                    .unwrap(),
+            )
+            .await,
+        )
+        .unwrap();
+        assert_eq!(response.groups.len(), 2);
+        assert_eq!(response.groups[0].category, Some(family));
+        assert_eq!(response.groups[1].items.len(), 2);
+        // test if user can send message to contact
+        let response = client
+            .request(request_send_msg(
+                &addr,
+                &user_1_token,
+                chat_1_2,
+                super::message::MsgPost {
                     content: Some("Hello, world".to_string()),
                     typ: 0,
                     cite: None,
@@ -959,6 +2044,51 @@ mod tests {
         consume_msg(socket_1.clone()).await;
         consume_msg(socket_2.clone()).await;
         assert_eq!(response.status(), StatusCode::OK);
+        // test if message to nonexistent session is rejected without leaking sql
+        let response = client
+            .request(request_send_msg(
+                &addr,
+                &user_1_token,
+                Uuid::new_v5(&Uuid::NAMESPACE_URL, b"nowhere"),
+                super::message::MsgPost {
+                    content: Some("Hello, world".to_string()),
+                    typ: 0,
+                    cite: None,
+                    file: None,
+                    forward: None,
+                    notice: None,
+                },
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let error: serde_json::Value =
+            serde_json::from_reader(res_to_json(response).await).unwrap();
+        assert_eq!(error["code"], "REFERENCE_NOT_FOUND");
+        // test if deleting a row that is still referenced is classified as a client error
+        {
+            use crate::error::{AppError, Violation};
+            use sea_orm::TransactionTrait;
+            let conn = connect_db_from_env().await;
+            let txn = conn.begin().await.unwrap();
+            for sql in [
+                "CREATE TEMPORARY TABLE parent (id INT PRIMARY KEY) ON COMMIT DROP",
+                "CREATE TEMPORARY TABLE child (parent INT REFERENCES parent (id)) ON COMMIT DROP",
+                "INSERT INTO parent VALUES (1)",
+                "INSERT INTO child VALUES (1)",
+            ] {
+                txn.execute_unprepared(sql).await.unwrap();
+            }
+            let err = txn
+                .execute_unprepared("DELETE FROM parent")
+                .await
+                .unwrap_err();
+            assert!(matches!(
+                AppError::from(err),
+                AppError::Constraint(Violation::StillReferenced)
+            ));
+            txn.rollback().await.unwrap();
+        }
         tokio::time::sleep(tokio::time::Duration::from_millis(1000)).await;
         // test if user can get message from contact
         let history: history::History = serde_json::from_reader(
@@ -976,14 +2106,40 @@ mod tests {
         assert_eq!(history.end, 1);
         assert_eq!(history.cnt, 1);
         assert_eq!(history.msgs[0].content, Some("Hello, world".to_string()));
-        // test if user can send message to group
+        // test if blocked user can neither send message, add contact nor find blocker
+        let response = client
+            .request(request_block_contact(&addr, &user_2_token, user_1))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = client
+            .request(request_block_contact(&addr, &user_2_token, user_1))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let response = client
+            .request(request_block_contact(&addr, &user_2_token, user_3))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response: Vec<Uuid> = serde_json::from_reader(
+            res_to_json(
+                client
+                    .request(request_get_blocks(&addr, &user_2_token))
+                    .await
+                    .unwrap(),
+            )
+            .await,
+        )
+        .unwrap();
+        assert_eq!(response, vec![user_3, user_1]);
         let response = client
             .request(request_send_msg(
                 &addr,
                 &user_1_token,
-                group.session,
+                chat_1_2,
                 super::message::MsgPost {
-                    content: Some("Hallo, Welt!".to_string()),
+                    content: Some("Are you there?".to_string()),
                     typ: 0,
                     cite: None,
                     file: None,
@@ -993,32 +2149,926 @@ mod tests {
             ))
             .await
             .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = client
+            .request(request_add_contact(&addr, &user_3_token, user_2, ""))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = client
+            .request(request_block_contact(&addr, &user_1_token, user_3))
+            .await
+            .unwrap();
         assert_eq!(response.status(), StatusCode::OK);
-        tokio::time::sleep(tokio::time::Duration::from_millis(1000)).await;
-        // test if user can get message from group
-        let history: history::History = serde_json::from_reader(
+        let response: user::UserList = serde_json::from_reader(
             res_to_json(
                 client
-                    .request(request_get_msg(&addr, &user_3_token, group.session))
+                    .request(request_find_user(&addr, &user_3_token, "?alias=monika"))
                     .await
                     .unwrap(),
             )
             .await,
         )
         .unwrap();
-        assert_eq!(history.msgs.len(), 1);
-        assert_eq!(history.msgs[0].content, Some("Hallo, Welt!".to_string()));
-        // test if user can renew jwt
-        let response: login::LoginResponse = serde_json::from_reader(
+        assert!(response.users.is_empty());
+        let response: user::UserList = serde_json::from_reader(
             res_to_json(
                 client
-                    .request(request_renew_jwt(&addr, &user_1_token))
+                    .request(request_find_user(&addr, &user_2_token, "?alias=monika"))
                     .await
                     .unwrap(),
             )
             .await,
         )
         .unwrap();
-        assert_ne!(response.token, user_1_token);
+        assert_eq!(response.users, vec![user_1]);
+        let response = client
+            .request(request_unblock_contact(&addr, &user_1_token, user_3))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        for u in [user_1, user_3] {
+            let response = client
+                .request(request_unblock_contact(&addr, &user_2_token, u))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        }
+        let response = client
+            .request(request_unblock_contact(&addr, &user_2_token, user_1))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        // test if user can export data
+        let response = client
+            .request(
+                request_get_json()
+                    .header("Authorization", format!("Bearer {user_1_token}"))
+                    .uri(format!("{addr}/user/export"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get("Content-Type").unwrap(),
+            "application/zip"
+        );
+        let data = response.into_body().collect().await.unwrap().to_bytes();
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(data.to_vec())).unwrap();
+        for name in [
+            "profile.json",
+            "contacts.json",
+            "groups.json",
+            "messages.json",
+            "uploads.json",
+        ] {
+            assert!(archive.by_name(name).is_ok());
+        }
+        let msgs: Vec<super::message::Msg> =
+            serde_json::from_reader(archive.by_name("messages.json").unwrap()).unwrap();
+        assert!(msgs
+            .iter()
+            .any(|m| m.content == Some("Hello, world".to_string())));
+        // test if user search is ranked and shows relation
+        let response: search::SearchResult = serde_json::from_reader(
+            res_to_json(
+                client
+                    .request(request_search_user(&addr, &user_3_token, "?q=test_user"))
+                    .await
+                    .unwrap(),
+            )
+            .await,
+        )
+        .unwrap();
+        assert_eq!(response.cnt, 2);
+        assert_eq!(response.items[0].id, user_1);
+        assert_eq!(response.items[0].name, "test_user1");
+        assert_eq!(response.items[0].relation, search::Relation::Contact);
+        assert_eq!(response.items[1].id, user_2);
+        assert_eq!(response.items[1].relation, search::Relation::None);
+        let response: search::SearchResult = serde_json::from_reader(
+            res_to_json(
+                client
+                    .request(request_search_user(
+                        &addr,
+                        &user_3_token,
+                        "?q=test_user_2&start=0&end=1",
+                    ))
+                    .await
+                    .unwrap(),
+            )
+            .await,
+        )
+        .unwrap();
+        assert_eq!(response.cnt, 1);
+        assert_eq!(response.end, 1);
+        assert_eq!(response.items[0].id, user_2);
+        let response: search::SearchResult = serde_json::from_reader(
+            res_to_json(
+                client
+                    .request(request_search_user(&addr, &user_3_token, "?q=MONIKA"))
+                    .await
+                    .unwrap(),
+            )
+            .await,
+        )
+        .unwrap();
+        assert_eq!(response.items[0].id, user_1);
+        let response: search::SearchResult = serde_json::from_reader(
+            res_to_json(
+                client
+                    .request(request_search_user(&addr, &user_3_token, "?q=user%5F"))
+                    .await
+                    .unwrap(),
+            )
+            .await,
+        )
+        .unwrap();
+        assert_eq!(response.cnt, 1);
+        let response = client
+            .request(request_search_user(&addr, &user_3_token, "?q=%20"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        // test if privacy settings restrict search and profile
+        let response = client
+            .request(request_find_user(&addr, &user_2_token, ""))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response: user::UserList = serde_json::from_reader(
+            res_to_json(
+                client
+                    .request(request_find_user(
+                        &addr,
+                        &user_2_token,
+                        "?email=test@example.com",
+                    ))
+                    .await
+                    .unwrap(),
+            )
+            .await,
+        )
+        .unwrap();
+        assert_eq!(response.users, vec![user_1]);
+        let response: user::UserList = serde_json::from_reader(
+            res_to_json(
+                client
+                    .request(request_find_user(&addr, &user_2_token, "?email=test@"))
+                    .await
+                    .unwrap(),
+            )
+            .await,
+        )
+        .unwrap();
+        assert!(response.users.is_empty());
+        let settings = privacy::PrivacySettings {
+            searchable_by_email: false,
+            profile: privacy::Visibility::Nobody,
+            ..Default::default()
+        };
+        let response: privacy::PrivacySettings = serde_json::from_reader(
+            res_to_json(
+                client
+                    .request(request_update_privacy(
+                        &addr,
+                        &user_1_token,
+                        settings.clone(),
+                    ))
+                    .await
+                    .unwrap(),
+            )
+            .await,
+        )
+        .unwrap();
+        assert_eq!(response, settings);
+        let response: user::UserList = serde_json::from_reader(
+            res_to_json(
+                client
+                    .request(request_find_user(
+                        &addr,
+                        &user_2_token,
+                        "?email=test@example.com",
+                    ))
+                    .await
+                    .unwrap(),
+            )
+            .await,
+        )
+        .unwrap();
+        assert!(response.users.is_empty());
+        let profile: user::UserProfile = serde_json::from_reader(
+            res_to_json(
+                client
+                    .request(request_get_profile(&addr, &user_2_token, user_1))
+                    .await
+                    .unwrap(),
+            )
+            .await,
+        )
+        .unwrap();
+        assert_eq!(profile.email, "test@example.com");
+        assert_eq!(profile.bio, "");
+        let profile: user::UserProfile = serde_json::from_reader(
+            res_to_json(
+                client
+                    .request(request_get_profile(&addr, &user_1_token, user_1))
+                    .await
+                    .unwrap(),
+            )
+            .await,
+        )
+        .unwrap();
+        assert_eq!(profile.bio, "test_bio");
+        let profile: user::UserProfile = serde_json::from_reader(
+            res_to_json(
+                client
+                    .request(request_get_profile(&addr, &user_3_token, user_2))
+                    .await
+                    .unwrap(),
+            )
+            .await,
+        )
+        .unwrap();
+        assert_eq!(profile.email, "");
+        assert_eq!(profile.phone, "");
+        let response = client
+            .request(request_update_privacy(
+                &addr,
+                &user_1_token,
+                Default::default(),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        // test if contact request policy is enforced
+        let response = client
+            .request(request_update_privacy(
+                &addr,
+                &user_2_token,
+                privacy::PrivacySettings {
+                    contact_request: privacy::RequestPolicy::Nobody,
+                    ..Default::default()
+                },
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = client
+            .request(request_add_contact(&addr, &user_3_token, user_2, ""))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = client
+            .request(request_update_privacy(
+                &addr,
+                &user_2_token,
+                privacy::PrivacySettings {
+                    contact_request: privacy::RequestPolicy::GroupMembers,
+                    ..Default::default()
+                },
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = client
+            .request(request_add_contact(&addr, &user_3_token, user_2, ""))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        consume_msg(socket_2.clone()).await;
+        let response = client
+            .request(request_reject_contact(&addr, &user_2_token, user_3))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = client
+            .request(request_update_privacy(
+                &addr,
+                &user_2_token,
+                Default::default(),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        // test if user can send message to group
+        let response = client
+            .request(request_send_msg(
+                &addr,
+                &user_1_token,
+                group.session,
+                super::message::MsgPost {
+                    content: Some("Hallo, Welt!".to_string()),
+                    typ: 0,
+                    cite: None,
+                    file: None,
+                    forward: None,
+                    notice: None,
+                },
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        tokio::time::sleep(tokio::time::Duration::from_millis(1000)).await;
+        // test if user can get message from group
+        let history: history::History = serde_json::from_reader(
+            res_to_json(
+                client
+                    .request(request_get_msg(&addr, &user_3_token, group.session))
+                    .await
+                    .unwrap(),
+            )
+            .await,
+        )
+        .unwrap();
+        assert_eq!(history.msgs.iter().filter(|m| m.typ == 0).count(), 1);
+        assert_eq!(history.msgs[0].content, Some("Hallo, Welt!".to_string()));
+        // test if membership changes are recorded as system messages
+        let events: Vec<super::message::SystemEvent> = history
+            .msgs
+            .iter()
+            .filter(|m| m.typ == 3)
+            .map(|m| serde_json::from_str(m.content.as_ref().unwrap()).unwrap())
+            .collect();
+        assert!(events.iter().any(|e| matches!(
+            e,
+            super::message::SystemEvent::Join { operator, user }
+                if *operator == Some(user_2) && *user == user_3
+        )));
+        assert!(events.iter().any(|e| matches!(
+            e,
+            super::message::SystemEvent::Transfer { operator, user }
+                if *operator == user_1 && *user == user_2
+        )));
+        // test if user can renew jwt
+        let response: login::LoginResponse = serde_json::from_reader(
+            res_to_json(
+                client
+                    .request(request_renew_jwt(&addr, &user_1_token))
+                    .await
+                    .unwrap(),
+            )
+            .await,
+        )
+        .unwrap();
+        assert_ne!(response.token, user_1_token);
+        // test if user can view their storage usage
+        let usage: avatar::UploadUsage = serde_json::from_reader(
+            res_to_json(
+                client
+                    .request(request_get_usage(&addr, &user_1_token))
+                    .await
+                    .unwrap(),
+            )
+            .await,
+        )
+        .unwrap();
+        assert_eq!(usage.used, 0);
+        assert_eq!(usage.quota, None);
+        // test if group owner can edit group profile
+        // test if group members receive notification
+        let socket = socket_1.clone();
+        let task = tokio::task::spawn(async move {
+            loop {
+                let feed: feed::Notification =
+                    match socket.lock().await.next().await.unwrap().unwrap() {
+                        tungstenite::Message::Text(msg) => serde_json::from_str(&msg).unwrap(),
+                        _ => panic!("unexpected message"),
+                    };
+                if let feed::Notification::GroupEdits { items } = feed {
+                    assert_eq!(items[0].group, group.id);
+                    assert_eq!(items[0].user, user_2);
+                    break;
+                }
+            }
+        });
+        let response = client
+            .request(request_edit_group_profile(
+                &addr,
+                &user_3_token,
+                group.id,
+                group::GroupEdition {
+                    name: None,
+                    avatar: None,
+                    description: Some("test description".to_string()),
+                    settings: None,
+                },
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let profile: group::GroupProfile = serde_json::from_reader(
+            res_to_json(
+                client
+                    .request(request_edit_group_profile(
+                        &addr,
+                        &user_2_token,
+                        group.id,
+                        group::GroupEdition {
+                            name: Some("renamed_group".to_string()),
+                            avatar: None,
+                            description: Some("test description".to_string()),
+                            settings: None,
+                        },
+                    ))
+                    .await
+                    .unwrap(),
+            )
+            .await,
+        )
+        .unwrap();
+        assert_eq!(profile.name, Some("renamed_group".to_string()));
+        assert_eq!(profile.description, Some("test description".to_string()));
+        task.await.unwrap();
+        // test if group member can create invite link
+        let link: invite::InviteLink = serde_json::from_reader(
+            res_to_json(
+                client
+                    .request(request_create_invite(
+                        &addr,
+                        &user_1_token,
+                        group.id,
+                        invite::InvitePost {
+                            expire_after: Some(3600),
+                            max_uses: Some(1),
+                        },
+                    ))
+                    .await
+                    .unwrap(),
+            )
+            .await,
+        )
+        .unwrap();
+        assert_eq!(link.group, group.id);
+        assert_eq!(link.uses, 0);
+        // test if user can request to join group by invite link
+        let user_4: login::LoginResponse = serde_json::from_reader(
+            res_to_json(
+                client
+                    .request(request_register(
+                        &addr,
+                        user::RegisterProfile {
+                            name: "test_user_4".to_string(),
+                            alias: None,
+                            phone: "18999990005".to_string(),
+                            gender: None,
+                            bio: None,
+                            link: None,
+                            password: "123456".to_string(),
+                            email: "test_4@example.com".to_string(),
+                        },
+                    ))
+                    .await
+                    .unwrap(),
+            )
+            .await,
+        )
+        .unwrap();
+        let user_4_token = user_4.token;
+        let response = client
+            .request(request_redeem_invite(&addr, &user_4_token, &link.code))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        let response: Vec<Uuid> = serde_json::from_reader(
+            res_to_json(
+                client
+                    .request(request_group_view(&addr, &user_2_token, group.id))
+                    .await
+                    .unwrap(),
+            )
+            .await,
+        )
+        .unwrap();
+        assert_eq!(response.len(), 1);
+        // test if used up invite link is rejected
+        let response = client
+            .request(request_redeem_invite(&addr, &user_4_token, &link.code))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        // test if group owner can restrict invite permission
+        let profile: group::GroupProfile = serde_json::from_reader(
+            res_to_json(
+                client
+                    .request(request_edit_group_profile(
+                        &addr,
+                        &user_2_token,
+                        group.id,
+                        group::GroupEdition {
+                            name: None,
+                            avatar: None,
+                            description: None,
+                            settings: Some(group::GroupSettings {
+                                auto_approve: false,
+                                mute_all: false,
+                                permissions: role::PermissionMatrix {
+                                    invite: role::Role::Admin,
+                                    ..Default::default()
+                                },
+                            }),
+                        },
+                    ))
+                    .await
+                    .unwrap(),
+            )
+            .await,
+        )
+        .unwrap();
+        assert_eq!(profile.role, Some(role::Role::Owner));
+        let response = client
+            .request(request_create_invite(
+                &addr,
+                &user_3_token,
+                group.id,
+                invite::InvitePost {
+                    expire_after: None,
+                    max_uses: None,
+                },
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        // test if former owner lost management permission
+        let response = client
+            .request(request_group_manage(
+                &addr,
+                &user_1_token,
+                group.id,
+                &format!("?admin={user_3}"),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        // test if group owner can mute all members
+        let response = client
+            .request(request_group_speak(
+                &addr,
+                &user_2_token,
+                group.id,
+                "?mute_all=true",
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let group_msg = || super::message::MsgPost {
+            content: Some("spam".to_string()),
+            typ: 0,
+            cite: None,
+            file: None,
+            forward: None,
+            notice: None,
+        };
+        let response = client
+            .request(request_send_msg(
+                &addr,
+                &user_3_token,
+                group.session,
+                group_msg(),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        // test if group owner can ban a member
+        let response = client
+            .request(request_group_speak(
+                &addr,
+                &user_2_token,
+                group.id,
+                &format!("?mute_all=false&member={user_3}&duration=60"),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = client
+            .request(request_send_msg(
+                &addr,
+                &user_3_token,
+                group.session,
+                group_msg(),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = client
+            .request(request_group_speak(
+                &addr,
+                &user_3_token,
+                group.id,
+                &format!("?member={user_2}&duration=60"),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        // test if ban is announced by system message
+        tokio::time::sleep(tokio::time::Duration::from_millis(1000)).await;
+        let history: history::History = serde_json::from_reader(
+            res_to_json(
+                client
+                    .request(request_get_msg(&addr, &user_3_token, group.session))
+                    .await
+                    .unwrap(),
+            )
+            .await,
+        )
+        .unwrap();
+        assert_eq!(history.msgs[0].typ, 3);
+        assert_eq!(history.msgs[0].sender, None);
+        let event: super::message::SystemEvent =
+            serde_json::from_str(history.msgs[0].content.as_ref().unwrap()).unwrap();
+        match event {
+            super::message::SystemEvent::Ban { user, until, .. } => {
+                assert_eq!(user, user_3);
+                assert!(until.is_some());
+            }
+            _ => panic!("unexpected system event"),
+        }
+        // test if banned member can speak after ban is lifted
+        let response = client
+            .request(request_group_speak(
+                &addr,
+                &user_2_token,
+                group.id,
+                &format!("?member={user_3}"),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = client
+            .request(request_send_msg(
+                &addr,
+                &user_3_token,
+                group.session,
+                group_msg(),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        // test if only admin or owner can post notice
+        let response = client
+            .request(request_create_notice(
+                &addr,
+                &user_3_token,
+                group.id,
+                notice::NoticePost {
+                    title: "rules".to_string(),
+                    content: "be nice".to_string(),
+                    file: None,
+                },
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = client
+            .request(request_create_notice(
+                &addr,
+                &user_2_token,
+                group.id,
+                notice::NoticePost {
+                    title: "rules".to_string(),
+                    content: "be nice".to_string(),
+                    file: None,
+                },
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let created: notice::Notice = serde_json::from_reader(res_to_json(response).await).unwrap();
+        assert_eq!(created.title, Some("rules".to_string()));
+        // test if latest notice is shown in group profile
+        let profile: group::GroupProfile = serde_json::from_reader(
+            res_to_json(
+                client
+                    .request(request_get_group(&addr, &user_3_token, group.id))
+                    .await
+                    .unwrap(),
+            )
+            .await,
+        )
+        .unwrap();
+        let latest = profile.notice.unwrap();
+        assert_eq!(latest.id, created.id);
+        assert!(!latest.confirmed);
+        // test if member can confirm notice and admin can view confirmations
+        let response = client
+            .request(request_confirm_notice(&addr, &user_3_token, created.id))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = client
+            .request(request_notice_reads(&addr, &user_3_token, created.id))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let reads: notice::NoticeReads = serde_json::from_reader(
+            res_to_json(
+                client
+                    .request(request_notice_reads(&addr, &user_2_token, created.id))
+                    .await
+                    .unwrap(),
+            )
+            .await,
+        )
+        .unwrap();
+        assert_eq!(reads.confirmed, vec![user_3]);
+        assert!(reads.unconfirmed.contains(&user_2));
+        // test if owner can edit notice
+        let edited: notice::Notice = serde_json::from_reader(
+            res_to_json(
+                client
+                    .request(request_edit_notice(
+                        &addr,
+                        &user_2_token,
+                        created.id,
+                        notice::NoticeEdition {
+                            title: Some("new rules".to_string()),
+                            content: None,
+                            file: Some(Uuid::nil()),
+                        },
+                    ))
+                    .await
+                    .unwrap(),
+            )
+            .await,
+        )
+        .unwrap();
+        assert_eq!(edited.title, Some("new rules".to_string()));
+        assert_eq!(edited.content, Some("be nice".to_string()));
+        assert_eq!(edited.file, None);
+        let notices: Vec<notice::Notice> = serde_json::from_reader(
+            res_to_json(
+                client
+                    .request(request_list_notices(&addr, &user_3_token, group.id))
+                    .await
+                    .unwrap(),
+            )
+            .await,
+        )
+        .unwrap();
+        assert_eq!(notices.len(), 1);
+        assert!(notices[0].confirmed);
+        // test if owner can delete notice
+        let response = client
+            .request(request_delete_notice(&addr, &user_2_token, created.id))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        let notices: Vec<notice::Notice> = serde_json::from_reader(
+            res_to_json(
+                client
+                    .request(request_list_notices(&addr, &user_3_token, group.id))
+                    .await
+                    .unwrap(),
+            )
+            .await,
+        )
+        .unwrap();
+        assert!(notices.is_empty());
+        // test if only admin or owner can pin group message
+        let pinned = history.msgs[0].id;
+        let response = client
+            .request(request_pin_msg(&addr, &user_3_token, pinned))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = client
+            .request(request_pin_msg(&addr, &user_2_token, pinned))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = client
+            .request(request_pin_msg(&addr, &user_2_token, pinned))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        // test if member can list pinned messages
+        let pins: Vec<super::message::Msg> = serde_json::from_reader(
+            res_to_json(
+                client
+                    .request(request_list_pins(&addr, &user_3_token, group.session))
+                    .await
+                    .unwrap(),
+            )
+            .await,
+        )
+        .unwrap();
+        assert_eq!(pins.len(), 1);
+        assert_eq!(pins[0].id, pinned);
+        let response = client
+            .request(request_unpin_msg(&addr, &user_2_token, pinned))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        let pins: Vec<super::message::Msg> = serde_json::from_reader(
+            res_to_json(
+                client
+                    .request(request_list_pins(&addr, &user_3_token, group.session))
+                    .await
+                    .unwrap(),
+            )
+            .await,
+        )
+        .unwrap();
+        assert!(pins.is_empty());
+        // test if removed member receives notification
+        let socket = socket_3.clone();
+        let task = tokio::task::spawn(async move {
+            loop {
+                let feed: feed::Notification =
+                    match socket.lock().await.next().await.unwrap().unwrap() {
+                        tungstenite::Message::Text(msg) => serde_json::from_str(&msg).unwrap(),
+                        _ => panic!("unexpected message"),
+                    };
+                if let feed::Notification::GroupRemovals { items } = feed {
+                    assert_eq!(items[0].group, group.id);
+                    assert_eq!(items[0].user, user_3);
+                    break;
+                }
+            }
+        });
+        let response = client
+            .request(request_delete_group(
+                &addr,
+                &user_2_token,
+                group.id,
+                &format!("?user={user_3}"),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        task.await.unwrap();
+        // test if members receive notification when group is dissolved
+        let socket = socket_1.clone();
+        let task = tokio::task::spawn(async move {
+            loop {
+                let feed: feed::Notification =
+                    match socket.lock().await.next().await.unwrap().unwrap() {
+                        tungstenite::Message::Text(msg) => serde_json::from_str(&msg).unwrap(),
+                        _ => panic!("unexpected message"),
+                    };
+                if let feed::Notification::GroupDissolved { items } = feed {
+                    assert_eq!(items[0].group, group.id);
+                    assert_eq!(items[0].user, user_2);
+                    break;
+                }
+            }
+        });
+        let response = client
+            .request(request_delete_group(&addr, &user_1_token, group.id, ""))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = client
+            .request(request_delete_group(&addr, &user_2_token, group.id, ""))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        task.await.unwrap();
+        // test if health and readiness checks pass
+        let response = client
+            .request(
+                Request::builder()
+                    .uri(format!("{addr}/healthz"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = client
+            .request(
+                Request::builder()
+                    .uri(format!("{addr}/readyz"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().aggregate();
+        let readiness: monitor::Readiness = serde_json::from_reader(body.reader()).unwrap();
+        assert!(readiness.ready);
+        assert!(readiness.database.ok && readiness.upload_dir.ok && readiness.migrations.ok);
+        // test if metrics are exported with matched route templates
+        let response = client
+            .request(
+                Request::builder()
+                    .uri(format!("{addr}/metrics"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let metrics = response.into_body().collect().await.unwrap().to_bytes();
+        let metrics = String::from_utf8(metrics.to_vec()).unwrap();
+        assert!(metrics.contains(
+            "veloquent_http_requests_total{method=\"POST\",route=\"/register\",status=\"201\"}"
+        ));
+        assert!(metrics.contains("veloquent_messages_total{kind=\"chat\"}"));
+        std::fs::remove_dir_all("mail").unwrap();
+        std::fs::remove_dir_all("upload").unwrap();
     }
 }
@@ -1,11 +1,14 @@
 use super::*;
+use crate::metrics::METRICS;
 use download::Resource;
 use entity::{
     prelude::{Upload, User},
     upload, user,
 };
+use sea_orm::{ConnectionTrait, TransactionTrait};
+use sea_query::OnConflict;
 use tokio::io::AsyncWriteExt;
-use utility::{bytes_as_uuid, UPLOAD_DIR, UUID_NIL};
+use utility::{bytes_as_uuid, UPLOAD_DIR, UPLOAD_QUOTA, UUID_NIL};
 
 /// 上传用户头像
 #[cfg_attr(feature = "dev",
@@ -14,7 +17,8 @@ utoipa::path(
     path = "/upload/avatar",
     request_body = Resource,
     responses(
-        (status = 201, description = "上传成功")
+        (status = 201, description = "上传成功"),
+        (status = 507, description = "超出存储配额", body = AppErrorResponse)
     ),
     tag = "static"
 ))]
@@ -25,17 +29,20 @@ pub async fn upload_avatar_handler(
     Protobuf(avatar): Protobuf<Resource>,
 ) -> Result<Response, AppError> {
     if avatar.typ.is_empty() {
-        return Err(AppError::BadRequest("empty type".to_string()));
+        return Err(AppError::BadRequest(
+            ErrorCode::UnsupportedFileType,
+            "empty type".to_string(),
+        ));
     }
     if avatar.typ.ne("png") && avatar.typ.ne("jpg") {
-        return Err(AppError::BadRequest(format!(
-            "unsupported type: [{}]",
-            avatar.typ
-        )));
+        return Err(AppError::BadRequest(
+            ErrorCode::UnsupportedFileType,
+            format!("unsupported type: [{}]", avatar.typ),
+        ));
     }
     let user = payload.to_user(&state.conn).await?;
     let mut user: user::ActiveModel = user.into();
-    let uuid = save_file(&avatar, &state.conn).await?;
+    let uuid = save_file(&avatar, payload.id, &state.conn).await?;
     user.avatar = ActiveValue::set(Some(uuid));
     User::update(user).exec(&state.conn).await?;
     event!(Level::INFO, "update user:avatar [{}:{}]", payload.id, uuid);
@@ -58,7 +65,8 @@ utoipa::path(
     path = "/upload",
     request_body = Resource,
     responses(
-        (status = 201, description = "上传成功")
+        (status = 201, description = "上传成功"),
+        (status = 507, description = "超出存储配额", body = AppErrorResponse)
     ),
     tag = "static"
 ))]
@@ -68,49 +76,133 @@ pub async fn upload_handler(
     payload: JWTPayload,
     Protobuf(avatar): Protobuf<Resource>,
 ) -> Result<Json<UploadRes>, AppError> {
-    let uuid = save_file(&avatar, &state.conn).await?;
+    let uuid = save_file(&avatar, payload.id, &state.conn).await?;
     Ok(Json(UploadRes {
         typ: avatar.typ,
         uuid,
     }))
 }
 
-async fn save_file(r: &Resource, c: &DatabaseConnection) -> Result<Uuid, AppError> {
+/// 存储用量
+#[cfg_attr(feature = "dev", derive(ToSchema))]
+#[derive(Serialize)]
+#[cfg_attr(test, derive(Deserialize))]
+pub struct UploadUsage {
+    /// 已使用的字节数
+    ///
+    /// 重复上传的相同文件只计入首次上传者
+    pub used: u64,
+    /// 配额字节数, 为空表示不限制
+    pub quota: Option<u64>,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct UsedBytes {
+    used: i64,
+}
+
+impl Upload {
+    pub(super) async fn usage(user: Uuid, conn: &impl ConnectionTrait) -> Result<u64, AppError> {
+        let used = UsedBytes::find_by_statement(Statement::from_sql_and_values(
+            Postgres,
+            "SELECT COALESCE(SUM(upload.size), 0)::BIGINT AS used FROM upload WHERE upload.uploader = $1",
+            [user.into()],
+        ))
+        .one(conn)
+        .await?
+        .map(|u| u.used)
+        .unwrap_or_default();
+        Ok(used.max(0) as u64)
+    }
+}
+
+/// 获取存储用量
+#[cfg_attr(feature = "dev",
+utoipa::path(
+    get,
+    path = "/upload/usage",
+    responses(
+        (status = 200, description = "获取成功", body = UploadUsage)
+    ),
+    tag = "static"
+))]
+#[instrument(skip(state))]
+pub async fn get_usage_handler(
+    State(state): State<AppState>,
+    payload: JWTPayload,
+) -> Result<Json<UploadUsage>, AppError> {
+    let used = Upload::usage(payload.id, &state.conn).await?;
+    let quota = UPLOAD_QUOTA.get().and_then(|q| q.of(payload.id));
+    Ok(Json(UploadUsage { used, quota }))
+}
+
+async fn check_quota(user: Uuid, size: u64, c: &impl ConnectionTrait) -> Result<(), AppError> {
+    if let Some(quota) = UPLOAD_QUOTA.get().and_then(|q| q.of(user)) {
+        let used = Upload::usage(user, c).await?;
+        if used + size > quota {
+            event!(
+                Level::WARN,
+                "user [{user}] exceeds upload quota [{used}+{size}/{quota}]"
+            );
+            return Err(AppError::InsufficientStorage(
+                ErrorCode::QuotaExceeded,
+                format!("upload quota exceeded: [{used}+{size}/{quota}]"),
+            ));
+        }
+    }
+    Ok(())
+}
+
+async fn save_file(r: &Resource, uploader: Uuid, c: &DatabaseConnection) -> Result<Uuid, AppError> {
     let data = &r.data;
-    let uuid = bytes_as_uuid(&data);
+    let uuid = bytes_as_uuid(data);
     if uuid.eq(&UUID_NIL) {
-        Err(AppError::BadRequest("empty content".to_string()))
+        Err(AppError::BadRequest(
+            ErrorCode::EmptyContent,
+            "empty content".to_string(),
+        ))
     } else {
+        let size = data.len() as u64;
+        let record = upload::ActiveModel {
+            uuid: ActiveValue::set(uuid),
+            typ: ActiveValue::set(r.typ.clone()),
+            uploader: ActiveValue::set(Some(uploader)),
+            size: ActiveValue::set(size as i64),
+            created_at: ActiveValue::not_set(),
+        };
+        // 锁定上传者, 同一用户的并发上传依次检查配额并写入记录
+        let txn = c.begin().await?;
+        User::find_by_id(uploader)
+            .lock_exclusive()
+            .one(&txn)
+            .await?;
+        if Upload::find_by_id(uuid).one(&txn).await?.is_none() {
+            check_quota(uploader, size, &txn).await?;
+            // 其他用户可能同时上传了相同的文件, 此时只计入首次上传者
+            Upload::insert(record)
+                .on_conflict(
+                    OnConflict::column(upload::Column::Uuid)
+                        .do_nothing()
+                        .to_owned(),
+                )
+                .exec_without_returning(&txn)
+                .await?;
+        }
         let file = tokio::fs::File::create_new(
             std::path::Path::new(&UPLOAD_DIR.get().unwrap()).join(uuid.to_string()),
         )
         .await;
         match file {
             Ok(mut f) => {
-                f.write_all(&data).await?;
+                f.write_all(data).await?;
+                METRICS.upload(size);
                 event!(Level::INFO, "write file: [{}]", uuid);
-                let file = upload::ActiveModel {
-                    uuid: ActiveValue::set(uuid),
-                    typ: ActiveValue::set(r.typ.clone()),
-                };
-                Upload::insert(file).exec(c).await?;
             }
             Err(e) => {
                 event!(Level::DEBUG, "create file error: [{}]", e); // 文件已经存在
-                let file = Upload::find_by_id(uuid).one(c).await?;
-                match file {
-                    Some(_) => {} // 数据库当中有记录
-                    None => {
-                        event!(Level::ERROR, "cannot from database find file: [{}]", uuid);
-                        let file = upload::ActiveModel {
-                            uuid: ActiveValue::set(uuid),
-                            typ: ActiveValue::set(r.typ.clone()),
-                        };
-                        Upload::insert(file).exec(c).await?;
-                    }
-                }
             }
         };
+        txn.commit().await?;
         Ok(uuid)
     }
 }
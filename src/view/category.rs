@@ -0,0 +1,366 @@
+use super::*;
+use entity::{
+    category, contact,
+    prelude::{Category, Contact},
+};
+use sea_orm::sea_query::Expr;
+use utility::UUID_NIL;
+
+/// 新建或重命名好友分组请求
+#[cfg_attr(feature = "dev", derive(ToSchema))]
+#[derive(Deserialize, Debug)]
+#[cfg_attr(test, derive(Serialize))]
+pub struct CategoryPost {
+    /// 分组名称
+    #[cfg(test)]
+    pub name: String,
+    #[cfg(not(test))]
+    name: String,
+}
+
+/// 好友分组
+#[cfg_attr(feature = "dev", derive(ToSchema))]
+#[derive(Serialize, Debug)]
+#[cfg_attr(test, derive(Deserialize))]
+pub struct CategoryItem {
+    /// 分组 UUID
+    pub id: Uuid,
+    /// 分组名称
+    pub name: String,
+    /// 排列顺序, 从小到大
+    pub position: i32,
+    /// 分组内的好友数量
+    pub num: u64,
+}
+
+impl CategoryPost {
+    fn validate(self) -> Result<String, AppError> {
+        let name = self.name.trim();
+        if name.is_empty() {
+            return Err(AppError::BadRequest(
+                ErrorCode::EmptyCategoryName,
+                "empty category name".to_string(),
+            ));
+        }
+        Ok(name.to_string())
+    }
+}
+
+impl category::Model {
+    /// 查找属于 `user` 的分组
+    pub(super) async fn from_user_and_id(
+        user: Uuid,
+        id: Uuid,
+        conn: &DatabaseConnection,
+    ) -> Result<Self, AppError> {
+        Category::find_by_id(id)
+            .filter(category::Column::User.eq(user))
+            .one(conn)
+            .await?
+            .ok_or(AppError::NotFound(
+                ErrorCode::CategoryNotFound,
+                format!("cannot find category [{id}]"),
+            ))
+    }
+
+    async fn from_user_and_name(
+        user: Uuid,
+        name: &str,
+        conn: &DatabaseConnection,
+    ) -> Result<Option<Self>, AppError> {
+        Ok(Category::find()
+            .filter(category::Column::User.eq(user))
+            .filter(category::Column::Name.eq(name))
+            .one(conn)
+            .await?)
+    }
+
+    /// 新建分组, 排在已有分组之后
+    async fn create(user: Uuid, name: String, conn: &DatabaseConnection) -> Result<Self, AppError> {
+        let position = Category::find()
+            .filter(category::Column::User.eq(user))
+            .order_by(category::Column::Position, sea_orm::Order::Desc)
+            .one(conn)
+            .await?
+            .map(|c| c.position + 1)
+            .unwrap_or(0);
+        let c = category::ActiveModel {
+            id: ActiveValue::not_set(),
+            user: ActiveValue::set(user),
+            name: ActiveValue::set(name),
+            position: ActiveValue::set(position),
+            created_at: ActiveValue::not_set(),
+        };
+        Ok(Category::insert(c).exec_with_returning(conn).await?)
+    }
+
+    /// 按名称查找分组, 不存在时新建
+    pub(super) async fn from_name_or_create(
+        user: Uuid,
+        name: String,
+        conn: &DatabaseConnection,
+    ) -> Result<Self, AppError> {
+        let name = CategoryPost { name }.validate()?;
+        match Self::from_user_and_name(user, &name, conn).await? {
+            Some(c) => Ok(c),
+            None => Self::create(user, name, conn).await,
+        }
+    }
+
+    /// 用户的全部分组, 按排列顺序
+    pub(super) async fn list(user: Uuid, conn: &DatabaseConnection) -> Result<Vec<Self>, AppError> {
+        Ok(Category::find()
+            .filter(category::Column::User.eq(user))
+            .order_by(category::Column::Position, sea_orm::Order::Asc)
+            .order_by(category::Column::CreatedAt, sea_orm::Order::Asc)
+            .all(conn)
+            .await?)
+    }
+}
+
+impl CategoryItem {
+    async fn from_model(c: category::Model, conn: &DatabaseConnection) -> Result<Self, AppError> {
+        let num = Contact::find()
+            .filter(contact::Column::CategoryId.eq(c.id))
+            .filter(contact::Column::RefUser.is_not_null())
+            .count(conn)
+            .await?;
+        Ok(Self {
+            id: c.id,
+            name: c.name,
+            position: c.position,
+            num,
+        })
+    }
+}
+
+/// 新建好友分组
+#[cfg_attr(feature = "dev",
+utoipa::path(
+    post,
+    path = "/contact/category",
+    request_body = CategoryPost,
+    responses(
+        (status = 201, description = "新建成功", body = CategoryItem),
+        (status = 409, description = "分组已存在", body = AppErrorResponse),
+    ),
+    tag = "contact"
+))]
+#[instrument(skip(state))]
+pub async fn create_category_handler(
+    State(state): State<AppState>,
+    payload: JWTPayload,
+    Json(post): Json<CategoryPost>,
+) -> Result<Response, AppError> {
+    let name = post.validate()?;
+    if category::Model::from_user_and_name(payload.id, &name, &state.conn)
+        .await?
+        .is_some()
+    {
+        return Err(AppError::Conflict(
+            ErrorCode::CategoryExists,
+            format!("category [{name}] exists"),
+        ));
+    }
+    let c = category::Model::create(payload.id, name, &state.conn).await?;
+    event!(
+        Level::INFO,
+        "user [{}] create category [{}]",
+        payload.id,
+        c.id
+    );
+    let c = CategoryItem::from_model(c, &state.conn).await?;
+    Ok((StatusCode::CREATED, Json(c)).into_response())
+}
+
+/// 获取好友分组列表
+///
+/// 按排列顺序返回
+#[cfg_attr(feature = "dev",
+utoipa::path(
+    get,
+    path = "/contact/category",
+    responses(
+        (status = 200, description = "获取成功", body = Vec<CategoryItem>)
+    ),
+    tag = "contact"
+))]
+#[instrument(skip(state))]
+pub async fn list_category_handler(
+    State(state): State<AppState>,
+    payload: JWTPayload,
+) -> Result<Json<Vec<CategoryItem>>, AppError> {
+    let mut res = Vec::new();
+    for c in category::Model::list(payload.id, &state.conn).await? {
+        res.push(CategoryItem::from_model(c, &state.conn).await?);
+    }
+    Ok(Json(res))
+}
+
+/// 重命名好友分组
+#[cfg_attr(feature = "dev",
+utoipa::path(
+    put,
+    path = "/contact/category/{id}",
+    params(("id" = Uuid, Path, description = "分组 UUID")),
+    request_body = CategoryPost,
+    responses(
+        (status = 200, description = "重命名成功", body = CategoryItem),
+        (status = 409, description = "分组已存在", body = AppErrorResponse),
+    ),
+    tag = "contact"
+))]
+#[instrument(skip(state))]
+pub async fn rename_category_handler(
+    State(state): State<AppState>,
+    payload: JWTPayload,
+    Path(id): Path<Uuid>,
+    Json(post): Json<CategoryPost>,
+) -> Result<Json<CategoryItem>, AppError> {
+    let c = category::Model::from_user_and_id(payload.id, id, &state.conn).await?;
+    let name = post.validate()?;
+    if let Some(other) = category::Model::from_user_and_name(payload.id, &name, &state.conn).await?
+    {
+        if other.id != c.id {
+            return Err(AppError::Conflict(
+                ErrorCode::CategoryExists,
+                format!("category [{name}] exists"),
+            ));
+        }
+    }
+    let mut c: category::ActiveModel = c.into();
+    c.name = ActiveValue::set(name);
+    let c = Category::update(c).exec(&state.conn).await?;
+    Ok(Json(CategoryItem::from_model(c, &state.conn).await?))
+}
+
+/// 删除好友分组
+///
+/// 分组内的好友变为未分组
+#[cfg_attr(feature = "dev",
+utoipa::path(
+    delete,
+    path = "/contact/category/{id}",
+    params(("id" = Uuid, Path, description = "分组 UUID")),
+    responses(
+        (status = 204, description = "删除成功"),
+    ),
+    tag = "contact"
+))]
+#[instrument(skip(state))]
+pub async fn delete_category_handler(
+    State(state): State<AppState>,
+    payload: JWTPayload,
+    Path(id): Path<Uuid>,
+) -> Result<Response, AppError> {
+    let c = category::Model::from_user_and_id(payload.id, id, &state.conn).await?;
+    Category::delete_by_id(c.id).exec(&state.conn).await?;
+    event!(
+        Level::INFO,
+        "user [{}] delete category [{}]",
+        payload.id,
+        id
+    );
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// 调整好友分组顺序
+///
+/// 请求体为当前用户全部分组的 UUID, 按新的顺序排列
+#[cfg_attr(feature = "dev",
+utoipa::path(
+    put,
+    path = "/contact/category/order",
+    request_body = Vec<Uuid>,
+    responses(
+        (status = 200, description = "调整成功", body = Vec<CategoryItem>),
+        (status = 400, description = "未包含全部分组", body = AppErrorResponse),
+    ),
+    tag = "contact"
+))]
+#[instrument(skip(state))]
+pub async fn order_category_handler(
+    State(state): State<AppState>,
+    payload: JWTPayload,
+    Json(order): Json<Vec<Uuid>>,
+) -> Result<Json<Vec<CategoryItem>>, AppError> {
+    let categories = category::Model::list(payload.id, &state.conn).await?;
+    let mut expected: Vec<Uuid> = categories.iter().map(|c| c.id).collect();
+    let mut given = order.clone();
+    expected.sort();
+    given.sort();
+    if expected != given {
+        return Err(AppError::BadRequest(
+            ErrorCode::InvalidCategoryOrder,
+            "order must contain every category exactly once".to_string(),
+        ));
+    }
+    for (position, id) in order.iter().enumerate() {
+        Category::update_many()
+            .col_expr(category::Column::Position, Expr::value(position as i32))
+            .filter(category::Column::Id.eq(*id))
+            .exec(&state.conn)
+            .await?;
+    }
+    let mut res = Vec::new();
+    for c in category::Model::list(payload.id, &state.conn).await? {
+        res.push(CategoryItem::from_model(c, &state.conn).await?);
+    }
+    Ok(Json(res))
+}
+
+/// 批量移动好友至分组
+///
+/// 请求体为好友的用户 UUID 列表, 分组 UUID 为全零时表示移动至未分组
+#[cfg_attr(feature = "dev",
+utoipa::path(
+    put,
+    path = "/contact/category/assign/{id}",
+    params(("id" = Uuid, Path, description = "分组 UUID")),
+    request_body = Vec<Uuid>,
+    responses(
+        (status = 200, description = "移动成功", body = CategoryItem),
+        (status = 404, description = "分组不存在或不是好友", body = AppErrorResponse),
+    ),
+    tag = "contact"
+))]
+#[instrument(skip(state))]
+pub async fn assign_category_handler(
+    State(state): State<AppState>,
+    payload: JWTPayload,
+    Path(id): Path<Uuid>,
+    Json(contacts): Json<Vec<Uuid>>,
+) -> Result<Response, AppError> {
+    let target = if id == *UUID_NIL {
+        None
+    } else {
+        Some(category::Model::from_user_and_id(payload.id, id, &state.conn).await?)
+    };
+    let mut contacts = contacts;
+    contacts.sort();
+    contacts.dedup();
+    let found = Contact::find()
+        .filter(contact::Column::User.eq(payload.id))
+        .filter(contact::Column::RefUser.is_in(contacts.clone()))
+        .count(&state.conn)
+        .await?;
+    if found != contacts.len() as u64 {
+        return Err(AppError::NotFound(
+            ErrorCode::NotContacts,
+            "some users are not contacts".to_string(),
+        ));
+    }
+    Contact::update_many()
+        .col_expr(
+            contact::Column::CategoryId,
+            Expr::value(target.as_ref().map(|c| c.id)),
+        )
+        .filter(contact::Column::User.eq(payload.id))
+        .filter(contact::Column::RefUser.is_in(contacts))
+        .exec(&state.conn)
+        .await?;
+    Ok(match target {
+        Some(c) => Json(CategoryItem::from_model(c, &state.conn).await?).into_response(),
+        None => StatusCode::OK.into_response(),
+    })
+}
@@ -1,17 +1,98 @@
 use super::*;
 use entity::{
-    contact,
-    prelude::{Contact, Session},
+    block, category, contact,
+    prelude::{Block, Contact, Session, User},
     session, user,
 };
 use feed::Notification;
-use utility::UUID_NIL;
+use sea_orm::{ConnectionTrait, DatabaseTransaction, TransactionTrait};
+use sea_query::OnConflict;
+use utility::{CONTACT_EXPIRE, UUID_NIL};
+
+/// 好友申请来源
+#[cfg_attr(feature = "dev", derive(ToSchema))]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ContactSource {
+    /// 搜索
+    Search,
+    /// 群聊
+    Group,
+    /// 二维码
+    QrCode,
+}
+
+impl ContactSource {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ContactSource::Search => "search",
+            ContactSource::Group => "group",
+            ContactSource::QrCode => "qr_code",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "search" => Some(ContactSource::Search),
+            "group" => Some(ContactSource::Group),
+            "qr_code" => Some(ContactSource::QrCode),
+            _ => None,
+        }
+    }
+}
+
+impl Block {
+    /// `user` 是否屏蔽了 `blocked`
+    pub(super) async fn is_blocked(
+        user: Uuid,
+        blocked: Uuid,
+        conn: &impl ConnectionTrait,
+    ) -> Result<bool, AppError> {
+        Ok(Block::find()
+            .filter(block::Column::User.eq(user))
+            .filter(block::Column::Blocked.eq(blocked))
+            .one(conn)
+            .await?
+            .is_some())
+    }
+
+    /// 屏蔽了 `user` 的用户
+    pub(super) async fn get_blockers(
+        user: Uuid,
+        conn: &DatabaseConnection,
+    ) -> Result<Vec<Uuid>, AppError> {
+        Ok(Block::find()
+            .filter(block::Column::Blocked.eq(user))
+            .all(conn)
+            .await?
+            .into_iter()
+            .map(|b| b.user)
+            .collect())
+    }
+}
+
+/// 按主键顺序锁定两名用户, 使双方之间的好友申请与屏蔽依次执行
+async fn lock_pair(a: Uuid, b: Uuid, txn: &DatabaseTransaction) -> Result<(), AppError> {
+    User::find()
+        .filter(user::Column::Id.is_in([a, b]))
+        .order_by_asc(user::Column::Id)
+        .lock_exclusive()
+        .all(txn)
+        .await?;
+    Ok(())
+}
+
+/// 好友申请的过期时间点, 早于该时间创建的申请视为过期
+fn request_expire_cutoff() -> chrono::NaiveDateTime {
+    chrono::Utc::now().naive_utc()
+        - chrono::Duration::seconds(*CONTACT_EXPIRE.get().unwrap() as i64)
+}
 
 impl contact::Model {
     async fn from_user_and_ref_raw(
         user: Uuid,
         ref_user: Uuid,
-        conn: &DatabaseConnection,
+        conn: &impl ConnectionTrait,
     ) -> Result<Option<Self>, AppError> {
         Ok(Contact::find()
             .filter(
@@ -25,26 +106,73 @@ impl contact::Model {
     async fn from_user_and_ref(
         user: Uuid,
         ref_user: Uuid,
-        conn: &DatabaseConnection,
+        conn: &impl ConnectionTrait,
     ) -> Result<Self, AppError> {
         Self::from_user_and_ref_raw(user, ref_user, conn)
             .await?
-            .ok_or(AppError::NotFound(format!(
-                "cannot find contact [{ref_user}] of [{user}]"
-            )))
+            .ok_or(AppError::NotFound(
+                ErrorCode::ContactNotFound,
+                format!("cannot find contact [{ref_user}] of [{user}]"),
+            ))
+    }
+    /// 两名用户是否互为好友
+    pub(super) async fn are_contacts(
+        a: Uuid,
+        b: Uuid,
+        conn: &DatabaseConnection,
+    ) -> Result<bool, AppError> {
+        let rows = Contact::find()
+            .filter(
+                Condition::any()
+                    .add(
+                        Condition::all()
+                            .add(contact::Column::User.eq(a))
+                            .add(contact::Column::RefUser.eq(b)),
+                    )
+                    .add(
+                        Condition::all()
+                            .add(contact::Column::User.eq(b))
+                            .add(contact::Column::RefUser.eq(a)),
+                    ),
+            )
+            .count(conn)
+            .await?;
+        Ok(rows == 2)
+    }
+    /// 删除 `user` 向 `ref_user` 发起的已过期且未被接受的好友申请
+    async fn purge_expired_request(
+        user: Uuid,
+        ref_user: Uuid,
+        conn: &impl ConnectionTrait,
+    ) -> Result<(), AppError> {
+        if let Some(c) = Self::from_user_and_ref_raw(user, ref_user, conn).await? {
+            if c.created_at < request_expire_cutoff()
+                && Self::from_user_and_ref_raw(ref_user, user, conn)
+                    .await?
+                    .is_none()
+            {
+                Session::delete_by_id(c.session).exec(conn).await?;
+                event!(
+                    Level::DEBUG,
+                    "purge expired contact request [{user}:{ref_user}]"
+                );
+            }
+        }
+        Ok(())
     }
     async fn is_user_and_ref_exist(
         user: Uuid,
         ref_user: Uuid,
-        conn: &DatabaseConnection,
+        conn: &impl ConnectionTrait,
     ) -> Result<(), AppError> {
         if Self::from_user_and_ref_raw(user, ref_user, conn)
             .await?
             .is_some()
         {
-            Err(AppError::Conflict(format!(
-                "contact relation exist [{user}:{ref_user}]"
-            )))
+            Err(AppError::Conflict(
+                ErrorCode::ContactExists,
+                format!("contact relation exist [{user}:{ref_user}]"),
+            ))
         } else {
             Ok(())
         }
@@ -60,22 +188,36 @@ impl From<(Uuid, Uuid, Option<String>, Uuid)> for contact::ActiveModel {
             alias: ActiveValue::set(alias),
             session: ActiveValue::set(session),
             created_at: ActiveValue::not_set(),
-            category: ActiveValue::not_set(),
+            category_id: ActiveValue::not_set(),
             pin: ActiveValue::not_set(),
             mute: ActiveValue::not_set(),
+            message: ActiveValue::not_set(),
+            source: ActiveValue::not_set(),
         }
     }
 }
 
+#[cfg_attr(feature = "dev", derive(IntoParams))]
+#[derive(Deserialize, Debug)]
+pub(super) struct AddContactParams {
+    /// 验证消息
+    message: Option<String>,
+    /// 申请来源
+    source: Option<ContactSource>,
+}
+
 /// 发起添加好友
 #[cfg_attr(feature = "dev",
 utoipa::path(
     post,
     path = "/contact/new/{id}",
     params(
-        ("id" = Uuid, Path, description = "要添加的用户主键")),
+        ("id" = Uuid, Path, description = "要添加的用户主键"),
+        AddContactParams
+    ),
     responses(
-        (status = 200, description = "发起成功")
+        (status = 200, description = "发起成功"),
+        (status = 403, description = "已被对方屏蔽, 已屏蔽对方, 或对方不接受好友申请", body = AppErrorResponse),
     ),
     tag = "contact"
 ))]
@@ -84,21 +226,43 @@ pub async fn add_contact_handler(
     State(state): State<AppState>,
     payload: JWTPayload,
     Path(contact): Path<Uuid>,
+    Query(params): Query<AddContactParams>,
 ) -> Result<impl IntoResponse, AppError> {
     let user = payload.to_user(&state.conn).await?;
     let con = user::Model::from_uuid(contact, &state.conn).await?;
     if user.id == con.id {
-        return Err(AppError::BadRequest("cannot add self".to_string()));
+        return Err(AppError::BadRequest(
+            ErrorCode::ContactSelf,
+            "cannot add self".to_string(),
+        ));
+    }
+    let txn = state.conn.begin().await?;
+    lock_pair(user.id, con.id, &txn).await?;
+    if Block::is_blocked(con.id, user.id, &txn).await?
+        || Block::is_blocked(user.id, con.id, &txn).await?
+    {
+        return Err(AppError::Forbidden(
+            ErrorCode::ContactForbidden,
+            format!("cannot add [{}]", con.id),
+        ));
     }
-    contact::Model::is_user_and_ref_exist(user.id, con.id, &state.conn).await?;
-    contact::Model::is_user_and_ref_exist(con.id, user.id, &state.conn).await?;
+    privacy::PrivacySettings::from(&con)
+        .check_contact_request(con.id, user.id, &state.conn)
+        .await?;
+    contact::Model::purge_expired_request(user.id, con.id, &txn).await?;
+    contact::Model::purge_expired_request(con.id, user.id, &txn).await?;
+    contact::Model::is_user_and_ref_exist(user.id, con.id, &txn).await?;
+    contact::Model::is_user_and_ref_exist(con.id, user.id, &txn).await?;
     let s = session::ActiveModel::default();
-    let s = Session::insert(s).exec(&state.conn).await?.last_insert_id;
-    let c = contact::ActiveModel::from((user.id, con.id, con.alias.clone(), s));
-    Contact::insert(c).exec(&state.conn).await?;
+    let s = Session::insert(s).exec(&txn).await?.last_insert_id;
+    let mut c = contact::ActiveModel::from((user.id, con.id, con.alias.clone(), s));
+    c.message = ActiveValue::set(params.message);
+    c.source = ActiveValue::set(params.source.map(|s| s.as_str().to_string()));
+    Contact::insert(c).exec(&txn).await?;
+    txn.commit().await?;
     event!(Level::DEBUG, "create new session [{}]", s);
     event!(Level::DEBUG, "user [{}] add [{}]", user.id, con.id);
-    tokio::task::spawn(async move {
+    trace::spawn(async move {
         if let Ok(data) = ContactList::query_new_contact(con, &state.conn).await {
             let data = Notification::ContactRequests { items: data };
             state
@@ -113,13 +277,25 @@ pub async fn add_contact_handler(
     Ok(StatusCode::OK.into_response())
 }
 
+#[cfg_attr(feature = "dev", derive(IntoParams))]
+#[derive(Deserialize, Debug)]
+pub(super) struct RejectContactParams {
+    /// 是否同时屏蔽对方
+    ///
+    /// 字段为空的时候默认 `false`
+    block: Option<bool>,
+}
+
 /// 拒绝好友申请
 #[cfg_attr(feature = "dev",
 utoipa::path(
     put,
     path = "/contact/reject/{id}",
     params(
-        ("id" = Uuid, Path, description = "要拒绝的用户主键") ), responses(
+        ("id" = Uuid, Path, description = "要拒绝的用户主键"),
+        RejectContactParams
+    ),
+    responses(
         (status = 200, description = "拒绝成功")
     ),
     tag = "contact"
@@ -129,21 +305,153 @@ pub async fn reject_contact_handler(
     State(state): State<AppState>,
     payload: JWTPayload,
     Path(con): Path<Uuid>,
+    Query(params): Query<RejectContactParams>,
 ) -> Result<impl IntoResponse, AppError> {
     let c: contact::ActiveModel = contact::Model::from_user_and_ref(con, payload.id, &state.conn)
         .await?
         .into();
     contact::Model::is_user_and_ref_exist(payload.id, con, &state.conn).await?;
     Contact::delete(c).exec(&state.conn).await?;
+    if params.block.unwrap_or(false) {
+        block_user(payload.id, con, &state.conn).await?;
+    }
+    Ok(StatusCode::OK.into_response())
+}
+
+/// 屏蔽用户, 已屏蔽时返回 `false`
+async fn block_user(
+    user: Uuid,
+    blocked: Uuid,
+    conn: &impl ConnectionTrait,
+) -> Result<bool, AppError> {
+    let b = block::ActiveModel {
+        id: ActiveValue::not_set(),
+        user: ActiveValue::set(user),
+        blocked: ActiveValue::set(blocked),
+        created_at: ActiveValue::not_set(),
+    };
+    let inserted = Block::insert(b)
+        .on_conflict(
+            OnConflict::columns([block::Column::User, block::Column::Blocked])
+                .do_nothing()
+                .to_owned(),
+        )
+        .exec_without_returning(conn)
+        .await?;
+    if inserted > 0 {
+        event!(Level::INFO, "user [{user}] block [{blocked}]");
+    }
+    Ok(inserted > 0)
+}
+
+/// 屏蔽用户
+///
+/// 被屏蔽的用户无法向当前用户发起好友申请, 发送消息, 也无法搜索到当前用户, 对方未处理的好友申请会被删除
+#[cfg_attr(feature = "dev",
+utoipa::path(
+    put,
+    path = "/contact/block/{id}",
+    params(("id" = Uuid, Path, description = "要屏蔽的用户主键")),
+    responses(
+        (status = 200, description = "屏蔽成功"),
+        (status = 409, description = "已屏蔽", body = AppErrorResponse),
+    ),
+    tag = "contact"
+))]
+#[instrument(skip(state))]
+pub async fn block_contact_handler(
+    State(state): State<AppState>,
+    payload: JWTPayload,
+    Path(con): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    let con = user::Model::from_uuid(con, &state.conn).await?;
+    if con.id == payload.id {
+        return Err(AppError::BadRequest(
+            ErrorCode::BlockSelf,
+            "cannot block self".to_string(),
+        ));
+    }
+    let txn = state.conn.begin().await?;
+    lock_pair(payload.id, con.id, &txn).await?;
+    if !block_user(payload.id, con.id, &txn).await? {
+        return Err(AppError::Conflict(
+            ErrorCode::AlreadyBlocked,
+            format!("already blocked [{}]", con.id),
+        ));
+    }
+    if let Some(c) = contact::Model::from_user_and_ref_raw(con.id, payload.id, &txn).await? {
+        if contact::Model::from_user_and_ref_raw(payload.id, con.id, &txn)
+            .await?
+            .is_none()
+        {
+            Session::delete_by_id(c.session).exec(&txn).await?;
+        }
+    }
+    txn.commit().await?;
     Ok(StatusCode::OK.into_response())
 }
 
+/// 解除屏蔽
+#[cfg_attr(feature = "dev",
+utoipa::path(
+    delete,
+    path = "/contact/block/{id}",
+    params(("id" = Uuid, Path, description = "要解除屏蔽的用户主键")),
+    responses(
+        (status = 204, description = "解除成功"),
+    ),
+    tag = "contact"
+))]
+#[instrument(skip(state))]
+pub async fn unblock_contact_handler(
+    State(state): State<AppState>,
+    payload: JWTPayload,
+    Path(con): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    let res = Block::delete_many()
+        .filter(block::Column::User.eq(payload.id))
+        .filter(block::Column::Blocked.eq(con))
+        .exec(&state.conn)
+        .await?;
+    if res.rows_affected == 0 {
+        return Err(AppError::NotFound(
+            ErrorCode::NotBlocked,
+            format!("[{con}] not blocked"),
+        ));
+    }
+    event!(Level::INFO, "user [{}] unblock [{con}]", payload.id);
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// 获取屏蔽列表
+#[cfg_attr(feature = "dev",
+utoipa::path(
+    get,
+    path = "/contact/block",
+    responses(
+        (status = 200, description = "获取成功", body = Vec<Uuid>)
+    ),
+    tag = "contact"
+))]
+#[instrument(skip(state))]
+pub async fn get_blocks_handler(
+    State(state): State<AppState>,
+    payload: JWTPayload,
+) -> Result<Json<Vec<Uuid>>, AppError> {
+    let blocks = Block::find()
+        .filter(block::Column::User.eq(payload.id))
+        .order_by(block::Column::CreatedAt, sea_orm::Order::Desc)
+        .all(&state.conn)
+        .await?;
+    Ok(Json(blocks.into_iter().map(|b| b.blocked).collect()))
+}
+
 #[cfg_attr(feature = "dev", derive(IntoParams))]
 #[derive(Deserialize, Debug)]
 pub(super) struct EditContactParams {
     /// 好友备注
     alias: Option<String>,
-    /// 好友分组
+    /// 好友分组名称, 分组不存在时自动新建
     category: Option<String>,
     /// 置顶
     pin: Option<bool>,
@@ -180,14 +488,21 @@ pub async fn edit_contact_handler(
             .into();
     contact::Model::from_user_and_ref(con, payload.id, &state.conn).await?;
     c.alias = ActiveValue::set(params.alias);
-    c.category = ActiveValue::set(params.category);
+    c.category_id = ActiveValue::set(match params.category {
+        Some(name) => Some(
+            category::Model::from_name_or_create(payload.id, name, &state.conn)
+                .await?
+                .id,
+        ),
+        None => None,
+    });
     c.pin = params
         .pin
-        .map(|b| ActiveValue::set(b))
+        .map(ActiveValue::set)
         .unwrap_or(ActiveValue::not_set());
     c.mute = params
         .mute
-        .map(|b| ActiveValue::set(b))
+        .map(ActiveValue::set)
         .unwrap_or(ActiveValue::not_set());
     Contact::update(c).exec(&state.conn).await?;
     Ok(StatusCode::OK.into_response())
@@ -245,17 +560,21 @@ pub async fn accept_contact_handler(
     let user = payload.to_user(&state.conn).await?;
     let con = user::Model::from_uuid(contact, &state.conn).await?;
     if user.id == con.id {
-        return Err(AppError::BadRequest("cannot accept self".to_string()));
+        return Err(AppError::BadRequest(
+            ErrorCode::ContactSelf,
+            "cannot accept self".to_string(),
+        ));
     }
     if contact::Model::from_user_and_ref_raw(user.id, con.id, &state.conn)
         .await?
         .is_some()
     {
-        return Err(AppError::Conflict(format!(
-            "contact already accepted [{}:{}]",
-            user.id, con.id
-        )));
+        return Err(AppError::Conflict(
+            ErrorCode::ContactAccepted,
+            format!("contact already accepted [{}:{}]", user.id, con.id),
+        ));
     }
+    contact::Model::purge_expired_request(con.id, user.id, &state.conn).await?;
     let entry = contact::Model::from_user_and_ref(con.id, user.id, &state.conn).await?;
     let s = Session::find_by_id(entry.session)
         .one(&state.conn)
@@ -263,7 +582,7 @@ pub async fn accept_contact_handler(
         .ok_or(anyhow::anyhow!("session not found [{}]", entry.session))?;
     let c = contact::ActiveModel::from((user.id, con.id, con.alias, s.id));
     Contact::insert(c).exec(&state.conn).await?;
-    tokio::task::spawn(async move {
+    trace::spawn(async move {
         if let Ok(Some(c)) =
             contact::Model::from_user_and_ref_raw(con.id, user.id, &state.conn).await
         {
@@ -272,6 +591,7 @@ pub async fn accept_contact_handler(
                 items: ContactList {
                     num: 1,
                     items: vec![c],
+                    groups: Vec::new(),
                 },
             };
             state
@@ -303,6 +623,34 @@ pub struct ContactList {
     pub items: Vec<Chat>,
     #[cfg(not(test))]
     items: Vec<Chat>,
+    /// 按好友分组排列的好友 UUID, 仅在获取好友列表时提供
+    ///
+    /// 分组按排列顺序返回, 未分组的好友在最后
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[cfg(test)]
+    pub groups: Vec<ContactGroup>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[cfg(not(test))]
+    groups: Vec<ContactGroup>,
+}
+
+/// 好友分组及其中的好友
+#[derive(Serialize, Debug)]
+#[cfg_attr(test, derive(Deserialize))]
+#[cfg_attr(feature = "dev", derive(ToSchema))]
+pub struct ContactGroup {
+    /// 分组 UUID, 为空表示未分组
+    #[cfg(test)]
+    pub category: Option<Uuid>,
+    #[cfg(not(test))]
+    category: Option<Uuid>,
+    /// 分组名称
+    name: Option<String>,
+    /// 好友的 UUID
+    #[cfg(test)]
+    pub items: Vec<Uuid>,
+    #[cfg(not(test))]
+    items: Vec<Uuid>,
 }
 
 #[derive(Serialize, Debug)]
@@ -321,7 +669,7 @@ pub struct Chat {
     pub session: Uuid,
     #[cfg(not(test))]
     session: Uuid,
-    /// 分类
+    /// 分组名称
     category: Option<String>,
     /// 备注
     alias: Option<String>,
@@ -329,6 +677,16 @@ pub struct Chat {
     pin: bool,
     /// 是否静音
     mute: bool,
+    /// 好友申请的验证消息
+    #[cfg(test)]
+    pub message: Option<String>,
+    #[cfg(not(test))]
+    message: Option<String>,
+    /// 好友申请来源
+    #[cfg(test)]
+    pub source: Option<ContactSource>,
+    #[cfg(not(test))]
+    source: Option<ContactSource>,
 }
 
 impl From<contact::Model> for Chat {
@@ -336,10 +694,12 @@ impl From<contact::Model> for Chat {
         Self {
             id: c.ref_user.unwrap_or(*UUID_NIL),
             session: c.session,
-            category: c.category,
+            category: None,
             alias: c.alias,
             pin: c.pin,
             mute: c.mute,
+            message: c.message,
+            source: c.source.as_deref().and_then(ContactSource::parse),
         }
     }
 }
@@ -348,10 +708,13 @@ impl From<contact::Model> for Chat {
 struct UserUuid {
     user: Uuid,
     session: Uuid,
+    category_id: Option<Uuid>,
     category: Option<String>,
     alias: Option<String>,
     pin: bool,
     mute: bool,
+    message: Option<String>,
+    source: Option<String>,
 }
 
 impl From<UserUuid> for Chat {
@@ -363,17 +726,45 @@ impl From<UserUuid> for Chat {
             alias: u.alias,
             pin: u.pin,
             mute: u.mute,
+            message: u.message,
+            source: u.source.as_deref().and_then(ContactSource::parse),
         }
     }
 }
 
 impl ContactList {
-    async fn query_contact(user: user::Model, db: &DatabaseConnection) -> Result<Self, AppError> {
+    pub(super) async fn query_contact(
+        user: user::Model,
+        db: &DatabaseConnection,
+    ) -> Result<Self, AppError> {
         let contacts:Vec<UserUuid> = UserUuid::find_by_statement(Statement::from_sql_and_values(Postgres,
-                    "SELECT a.ref_user AS user, a.session, a.category, a.alias, a.pin, a.mute FROM contact AS a INNER JOIN contact AS b ON a.user = b.ref_user AND a.ref_user = b.user WHERE a.user = $1",[user.id.into()])).all(db).await?;
+                    "SELECT a.ref_user AS user, a.session, a.category_id, k.name AS category, a.alias, a.pin, a.mute, NULL::VARCHAR AS message, NULL::VARCHAR AS source FROM contact AS a INNER JOIN contact AS b ON a.user = b.ref_user AND a.ref_user = b.user LEFT JOIN category AS k ON a.category_id = k.id WHERE a.user = $1 ORDER BY k.position ASC NULLS LAST, a.created_at ASC",[user.id.into()])).all(db).await?;
+        let mut groups: Vec<ContactGroup> = category::Model::list(user.id, db)
+            .await?
+            .into_iter()
+            .map(|c| ContactGroup {
+                category: Some(c.id),
+                name: Some(c.name),
+                items: Vec::new(),
+            })
+            .collect();
+        let mut uncategorized = ContactGroup {
+            category: None,
+            name: None,
+            items: Vec::new(),
+        };
+        for c in contacts.iter() {
+            match groups.iter_mut().find(|g| g.category == c.category_id) {
+                Some(g) => g.items.push(c.user),
+                None => uncategorized.items.push(c.user),
+            }
+        }
+        if !uncategorized.items.is_empty() {
+            groups.push(uncategorized);
+        }
         let items: Vec<Chat> = contacts.into_iter().map(UserUuid::into).collect();
         let num = items.len() as i32;
-        Ok(Self { num, items })
+        Ok(Self { num, items, groups })
     }
 
     async fn query_new_contact(
@@ -381,17 +772,14 @@ impl ContactList {
         db: &DatabaseConnection,
     ) -> Result<Self, AppError> {
         let contacts:Vec<UserUuid> = UserUuid::find_by_statement(Statement::from_sql_and_values(Postgres,
-            "SELECT c.user, c.session, c.category, c.alias, c.pin, c.mute FROM contact AS c INNER JOIN (SELECT contact.user, contact.session FROM contact WHERE contact.ref_user = $1 EXCEPT SELECT contact.ref_user, contact.session FROM contact WHERE contact.user = $1) AS b ON c.user = b.user AND c.session = b.session",[user.id.into()])).all(db).await?;
-        let items: Vec<Chat> = contacts
-            .into_iter()
-            .map(|c| {
-                let mut c: Chat = c.into();
-                c.category = None;
-                c
-            })
-            .collect();
+            "SELECT c.user, c.session, NULL::UUID AS category_id, NULL::VARCHAR AS category, c.alias, c.pin, c.mute, c.message, c.source FROM contact AS c INNER JOIN (SELECT contact.user, contact.session FROM contact WHERE contact.ref_user = $1 EXCEPT SELECT contact.ref_user, contact.session FROM contact WHERE contact.user = $1) AS b ON c.user = b.user AND c.session = b.session WHERE c.created_at > $2",[user.id.into(), request_expire_cutoff().into()])).all(db).await?;
+        let items: Vec<Chat> = contacts.into_iter().map(UserUuid::into).collect();
         let num = items.len() as i32;
-        Ok(Self { num, items })
+        Ok(Self {
+            num,
+            items,
+            groups: Vec::new(),
+        })
     }
 
     async fn query_pending_contact(
@@ -399,10 +787,14 @@ impl ContactList {
         db: &DatabaseConnection,
     ) -> Result<Self, AppError> {
         let contacts:Vec<UserUuid> = UserUuid::find_by_statement(Statement::from_sql_and_values(Postgres,
-                    "SELECT c.user, c.session, c.category, c.alias, c.pin, c.mute FROM contact AS c INNER JOIN (SELECT contact.ref_user AS user, contact.session FROM contact WHERE contact.user = $1 EXCEPT SELECT contact.user, contact.session FROM contact WHERE contact.ref_user = $1) AS b ON c.ref_user = b.user AND c.session = b.session;",[user.id.into()])).all(db).await?;
+                    "SELECT c.user, c.session, NULL::UUID AS category_id, NULL::VARCHAR AS category, c.alias, c.pin, c.mute, c.message, c.source FROM contact AS c INNER JOIN (SELECT contact.ref_user AS user, contact.session FROM contact WHERE contact.user = $1 EXCEPT SELECT contact.user, contact.session FROM contact WHERE contact.ref_user = $1) AS b ON c.ref_user = b.user AND c.session = b.session WHERE c.created_at > $2;",[user.id.into(), request_expire_cutoff().into()])).all(db).await?;
         let items: Vec<Chat> = contacts.into_iter().map(UserUuid::into).collect();
         let num = items.len() as i32;
-        Ok(Self { num, items })
+        Ok(Self {
+            num,
+            items,
+            groups: Vec::new(),
+        })
     }
 }
 
@@ -468,6 +860,8 @@ pub async fn get_contacts_handler(
             .collect();
         data.items = items;
         data.num = data.items.len() as i32;
+        data.groups
+            .retain(|g| g.name.as_deref() == Some(category.as_str()));
     }
     event!(
         Level::DEBUG,
@@ -503,7 +897,9 @@ pub async fn get_pending_contacts_handler(
     Ok(Json(data))
 }
 
-/// 获取好友分组列表
+/// 获取好友分组名称列表
+///
+/// 按排列顺序返回, 完整的分组信息见 `/contact/category`
 #[cfg_attr(feature = "dev",
 utoipa::path(
     get,
@@ -517,17 +913,10 @@ pub async fn get_categories_handler(
     State(state): State<AppState>,
     payload: JWTPayload,
 ) -> Result<Json<Vec<String>>, AppError> {
-    let user = payload.to_user(&state.conn).await?;
-    let categories = Contact::find()
-        .filter(contact::Column::User.eq(user.id))
-        .all(&state.conn)
-        .await?;
-    let mut categories = categories
+    let categories = category::Model::list(payload.id, &state.conn)
+        .await?
         .into_iter()
-        .filter(|c| c.category.is_some())
-        .map(|c| c.category.unwrap())
-        .collect::<Vec<String>>();
-    categories.sort();
-    categories.dedup();
+        .map(|c| c.name)
+        .collect();
     Ok(Json(categories))
 }
@@ -44,9 +44,15 @@ pub async fn download_handler(
 ) -> Result<impl IntoResponse, AppError> {
     event!(Level::DEBUG, "request resource [{:?}]", &id);
     let file = Upload::find_by_id(id).one(&state.conn).await?;
-    let file = file.ok_or(AppError::NotFound(format!("cannot find file: [{}]", id)))?;
+    let file = file.ok_or(AppError::NotFound(
+        ErrorCode::FileNotFound,
+        format!("cannot find file: [{}]", id),
+    ))?;
     if file.uuid == *UUID_NIL {
-        return Err(AppError::BadRequest("empty content".to_string()));
+        return Err(AppError::BadRequest(
+            ErrorCode::EmptyContent,
+            "empty content".to_string(),
+        ));
     }
     let path = std::path::Path::new(UPLOAD_DIR.get().unwrap()).join(file.uuid.to_string());
     let typ = file.typ;
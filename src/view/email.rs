@@ -0,0 +1,343 @@
+use super::*;
+use crate::mail::{send_mail, MAIL_SETTING};
+use entity::{
+    prelude::{User, UserToken},
+    user, user_token,
+};
+use login::ClientInfo;
+use utility::{gen_hash_and_salt, hash_token, normalize_email};
+
+/// 一次性令牌的用途
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TokenPurpose {
+    /// 验证邮箱
+    VerifyEmail,
+    /// 重置密码
+    ResetPassword,
+}
+
+impl TokenPurpose {
+    fn as_str(&self) -> &'static str {
+        match self {
+            TokenPurpose::VerifyEmail => "verify_email",
+            TokenPurpose::ResetPassword => "reset_password",
+        }
+    }
+}
+
+/// 令牌请求体
+#[cfg_attr(feature = "dev", derive(ToSchema))]
+#[derive(Deserialize, Debug)]
+#[cfg_attr(test, derive(Serialize))]
+pub struct TokenPost {
+    /// 邮件中的令牌
+    #[cfg(test)]
+    pub token: String,
+    #[cfg(not(test))]
+    token: String,
+}
+
+/// 重置密码请求体
+#[cfg_attr(feature = "dev", derive(ToSchema))]
+#[derive(Deserialize, Debug)]
+#[cfg_attr(test, derive(Serialize))]
+pub struct ResetRequest {
+    /// 账号绑定的邮箱
+    #[cfg(test)]
+    pub email: String,
+    #[cfg(not(test))]
+    email: String,
+}
+
+/// 确认重置密码请求体
+#[cfg_attr(feature = "dev", derive(ToSchema))]
+#[derive(Deserialize, Debug)]
+#[cfg_attr(test, derive(Serialize))]
+pub struct ResetConfirm {
+    /// 邮件中的令牌
+    #[cfg(test)]
+    pub token: String,
+    #[cfg(not(test))]
+    token: String,
+    /// 新密码
+    #[cfg(test)]
+    pub password: String,
+    #[cfg(not(test))]
+    password: String,
+}
+
+impl user_token::Model {
+    /// 距离上次签发不足最短间隔时拒绝
+    async fn check_interval(
+        user: Uuid,
+        purpose: TokenPurpose,
+        conn: &DatabaseConnection,
+    ) -> Result<(), AppError> {
+        let interval = MAIL_SETTING.get().unwrap().resend_interval;
+        let recent =
+            UserToken::find()
+                .filter(user_token::Column::User.eq(user))
+                .filter(user_token::Column::Purpose.eq(purpose.as_str()))
+                .filter(
+                    user_token::Column::CreatedAt
+                        .gt(chrono::Utc::now().naive_utc()
+                            - chrono::Duration::seconds(interval as i64)),
+                )
+                .one(conn)
+                .await?;
+        match recent {
+            Some(_) => Err(AppError::TooManyRequests(
+                ErrorCode::MailTooFrequent,
+                format!("mail requested within [{interval}] seconds"),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// 签发令牌, 同一用途的旧令牌失效
+    async fn issue(
+        user: &user::Model,
+        purpose: TokenPurpose,
+        expire: u64,
+        conn: &DatabaseConnection,
+    ) -> Result<String, AppError> {
+        UserToken::delete_many()
+            .filter(user_token::Column::User.eq(user.id))
+            .filter(user_token::Column::Purpose.eq(purpose.as_str()))
+            .exec(conn)
+            .await?;
+        let token = utility::gen_random_code(48);
+        let t = user_token::ActiveModel {
+            id: ActiveValue::not_set(),
+            user: ActiveValue::set(user.id),
+            purpose: ActiveValue::set(purpose.as_str().to_string()),
+            token_hash: ActiveValue::set(hash_token(&token)),
+            email: ActiveValue::set(user.email.clone()),
+            expires_at: ActiveValue::set(
+                chrono::Utc::now().naive_utc() + chrono::Duration::seconds(expire as i64),
+            ),
+            created_at: ActiveValue::not_set(),
+        };
+        UserToken::insert(t).exec(conn).await?;
+        Ok(token)
+    }
+
+    /// 使用令牌并返回令牌所属的用户, 令牌使用后即失效
+    async fn consume(
+        token: &str,
+        purpose: TokenPurpose,
+        conn: &DatabaseConnection,
+    ) -> Result<user::Model, AppError> {
+        let t = UserToken::find()
+            .filter(user_token::Column::TokenHash.eq(hash_token(token)))
+            .filter(user_token::Column::Purpose.eq(purpose.as_str()))
+            .one(conn)
+            .await?
+            .ok_or(AppError::BadRequest(
+                ErrorCode::InvalidMailToken,
+                "invalid token".to_string(),
+            ))?;
+        UserToken::delete_by_id(t.id).exec(conn).await?;
+        if t.expires_at < chrono::Utc::now().naive_utc() {
+            return Err(AppError::BadRequest(
+                ErrorCode::MailTokenExpired,
+                "token expired".to_string(),
+            ));
+        }
+        let user = user::Model::from_uuid(t.user, conn).await?;
+        if user.email != t.email {
+            return Err(AppError::BadRequest(
+                ErrorCode::EmailChanged,
+                "email changed after token issued".to_string(),
+            ));
+        }
+        Ok(user)
+    }
+}
+
+/// 签发验证令牌, 并在后台向用户当前的邮箱发送验证邮件
+pub(super) async fn send_verification(
+    user: &user::Model,
+    conn: &DatabaseConnection,
+) -> Result<(), AppError> {
+    let expire = MAIL_SETTING.get().unwrap().verify_expire;
+    let token = user_token::Model::issue(user, TokenPurpose::VerifyEmail, expire, conn).await?;
+    let (email, name) = (user.email.clone(), user.name.clone());
+    trace::spawn(async move {
+        send_mail(
+            &email,
+            "Veloquent 邮箱验证",
+            &format!(
+                "{name}, 你好:\n\n请使用以下令牌验证你的邮箱, 令牌在 {} 小时内有效.\n\n令牌: {token}\n",
+                expire / 3600
+            ),
+        )
+        .await;
+    });
+    Ok(())
+}
+
+/// 重新发送验证邮件
+///
+/// 注册与修改邮箱时会自动发送验证邮件
+#[cfg_attr(feature = "dev",
+utoipa::path(
+    post,
+    path = "/user/email/verify",
+    responses(
+        (status = 202, description = "已发送"),
+        (status = 409, description = "邮箱已验证", body = AppErrorResponse),
+        (status = 429, description = "请求过于频繁", body = AppErrorResponse),
+    ),
+    tag = "user"
+))]
+#[instrument(skip(state))]
+pub async fn request_verify_handler(
+    State(state): State<AppState>,
+    payload: JWTPayload,
+) -> Result<Response, AppError> {
+    let user = payload.to_user(&state.conn).await?;
+    if user.email.is_empty() {
+        return Err(AppError::BadRequest(
+            ErrorCode::EmailNotSet,
+            "email not set".to_string(),
+        ));
+    }
+    if user.email_verified_at.is_some() {
+        return Err(AppError::Conflict(
+            ErrorCode::EmailAlreadyVerified,
+            "email already verified".to_string(),
+        ));
+    }
+    user_token::Model::check_interval(user.id, TokenPurpose::VerifyEmail, &state.conn).await?;
+    send_verification(&user, &state.conn).await?;
+    Ok(StatusCode::ACCEPTED.into_response())
+}
+
+/// 验证邮箱
+#[cfg_attr(feature = "dev",
+utoipa::path(
+    post,
+    path = "/email/verify",
+    request_body = TokenPost,
+    responses(
+        (status = 200, description = "验证成功"),
+        (status = 400, description = "令牌无效或已过期", body = AppErrorResponse),
+    ),
+    tag = "user"
+))]
+#[instrument(skip(state, post))]
+pub async fn confirm_verify_handler(
+    State(state): State<AppState>,
+    Json(post): Json<TokenPost>,
+) -> Result<Response, AppError> {
+    let user =
+        user_token::Model::consume(&post.token, TokenPurpose::VerifyEmail, &state.conn).await?;
+    let id = user.id;
+    let mut user: user::ActiveModel = user.into();
+    user.email_verified_at = ActiveValue::set(Some(chrono::Utc::now().naive_utc()));
+    User::update(user).exec(&state.conn).await?;
+    event!(Level::INFO, "verify email of user [{}]", id);
+    Ok(StatusCode::OK.into_response())
+}
+
+/// 请求重置密码
+///
+/// 向邮箱发送重置密码的令牌, 邮箱未绑定任何账号或同一账号请求过于频繁时同样返回成功, 但不发送邮件
+#[cfg_attr(feature = "dev",
+utoipa::path(
+    post,
+    path = "/password/reset",
+    request_body = ResetRequest,
+    responses(
+        (status = 202, description = "已受理"),
+        (status = 429, description = "同一 IP 请求过于频繁", body = AppErrorResponse),
+    ),
+    tag = "user"
+))]
+#[instrument(skip(state))]
+pub async fn request_reset_handler(
+    State(state): State<AppState>,
+    client: ClientInfo,
+    Json(req): Json<ResetRequest>,
+) -> Result<Response, AppError> {
+    let setting = MAIL_SETTING.get().unwrap();
+    if setting.reset_ip.hit(&client.ip) > setting.reset_ip_limit {
+        return Err(AppError::TooManyRequests(
+            ErrorCode::MailTooFrequent,
+            "too many password reset requests from this ip".to_string(),
+        ));
+    }
+    let user = if req.email.is_empty() {
+        None
+    } else {
+        User::find()
+            .filter(user::Column::EmailNormalized.eq(normalize_email(&req.email)))
+            .filter(user::Column::DeletedAt.is_null())
+            .one(&state.conn)
+            .await?
+    };
+    let Some(user) = user else {
+        return Ok(StatusCode::ACCEPTED.into_response());
+    };
+    // 请求过于频繁时同样返回成功, 不透露邮箱是否绑定了账号
+    if let Err(e) =
+        user_token::Model::check_interval(user.id, TokenPurpose::ResetPassword, &state.conn).await
+    {
+        event!(Level::DEBUG, "skip password reset mail: {e:?}");
+        return Ok(StatusCode::ACCEPTED.into_response());
+    }
+    let expire = setting.reset_expire;
+    let token =
+        user_token::Model::issue(&user, TokenPurpose::ResetPassword, expire, &state.conn).await?;
+    event!(Level::INFO, "user [{}] request password reset", user.id);
+    // 在后台发送, 响应时间同样不透露邮箱是否绑定了账号
+    trace::spawn(async move {
+        send_mail(
+            &user.email,
+            "Veloquent 重置密码",
+            &format!(
+                "{}, 你好:\n\n请使用以下令牌重置你的密码, 令牌在 {} 分钟内有效. 如果这不是你本人的操作, 请忽略本邮件.\n\n令牌: {token}\n",
+                user.name,
+                expire / 60
+            ),
+        )
+        .await;
+    });
+    Ok(StatusCode::ACCEPTED.into_response())
+}
+
+/// 确认重置密码
+#[cfg_attr(feature = "dev",
+utoipa::path(
+    post,
+    path = "/password/reset/confirm",
+    request_body = ResetConfirm,
+    responses(
+        (status = 200, description = "重置成功"),
+        (status = 400, description = "令牌无效或已过期", body = AppErrorResponse),
+    ),
+    tag = "user"
+))]
+#[instrument(skip(state, post))]
+pub async fn confirm_reset_handler(
+    State(state): State<AppState>,
+    Json(post): Json<ResetConfirm>,
+) -> Result<Response, AppError> {
+    if post.password.is_empty() {
+        return Err(AppError::BadRequest(
+            ErrorCode::EmptyPassword,
+            "password cannot be empty".to_string(),
+        ));
+    }
+    let user =
+        user_token::Model::consume(&post.token, TokenPurpose::ResetPassword, &state.conn).await?;
+    let id = user.id;
+    let (hash, salt) = gen_hash_and_salt(&post.password)?;
+    let mut user: user::ActiveModel = user.into();
+    user.hash = ActiveValue::set(hash);
+    user.salt = ActiveValue::set(salt);
+    User::update(user).exec(&state.conn).await?;
+    event!(Level::INFO, "reset password of user [{}]", id);
+    Ok(StatusCode::OK.into_response())
+}
@@ -0,0 +1,173 @@
+use super::contact::ContactList;
+use super::group::GroupProfile;
+use super::message::Msg;
+use super::privacy::PrivacySettings;
+use super::user::UserProfile;
+use super::*;
+use axum::http::header;
+use entity::{
+    feed, member, message,
+    prelude::{Member, Message, Upload},
+    upload,
+};
+use std::io::Write;
+use utility::UPLOAD_DIR;
+
+/// 导出的用户资料
+#[derive(Serialize, Debug)]
+struct ExportProfile {
+    profile: UserProfile,
+    privacy: PrivacySettings,
+}
+
+/// 导出的上传文件记录
+#[derive(Serialize, Debug)]
+struct ExportUpload {
+    /// 文件 UUID, 文件位于压缩包的 `attachments/{uuid}`
+    uuid: Uuid,
+    /// 扩展名或文件类型
+    typ: String,
+    /// 文件大小, 单位为字节
+    size: i64,
+    /// 上传时间, UTC 毫秒时间戳
+    created_at: i64,
+}
+
+impl From<upload::Model> for ExportUpload {
+    fn from(u: upload::Model) -> Self {
+        Self {
+            uuid: u.uuid,
+            typ: u.typ,
+            size: u.size,
+            created_at: u.created_at.and_utc().timestamp_millis(),
+        }
+    }
+}
+
+/// 压缩包中的一个条目
+enum Entry {
+    Json(&'static str, Vec<u8>),
+    File(String, std::path::PathBuf),
+}
+
+fn to_json<T: Serialize>(name: &'static str, value: &T) -> Result<Entry, AppError> {
+    Ok(Entry::Json(name, serde_json::to_vec_pretty(value)?))
+}
+
+/// 写入 zip 压缩包, 缺失的附件将被跳过
+fn write_archive(entries: Vec<Entry>) -> anyhow::Result<Vec<u8>> {
+    let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let options =
+        zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    for entry in entries {
+        match entry {
+            Entry::Json(name, data) => {
+                zip.start_file(name, options)?;
+                zip.write_all(&data)?;
+            }
+            Entry::File(name, path) => match std::fs::read(&path) {
+                Ok(data) => {
+                    zip.start_file(name, options)?;
+                    zip.write_all(&data)?;
+                }
+                Err(e) => event!(Level::WARN, "skip missing attachment {:?}: [{}]", path, e),
+            },
+        }
+    }
+    Ok(zip.finish()?.into_inner())
+}
+
+/// 导出用户数据
+///
+/// 返回 zip 压缩包, 包含以下文件
+///
+/// | 文件 | 内容 |
+/// | --- | --- |
+/// | `profile.json` | 用户资料与隐私设置 |
+/// | `contacts.json` | 好友列表 |
+/// | `groups.json` | 加入的群聊 |
+/// | `messages.json` | 可见的全部聊天记录 |
+/// | `uploads.json` | 上传的文件记录 |
+/// | `attachments/{uuid}` | 上传的文件 |
+#[cfg_attr(feature = "dev",
+utoipa::path(
+    get,
+    path = "/user/export",
+    responses(
+        (status = 200, description = "导出成功", content_type = "application/zip"),
+    ),
+    tag = "user"
+))]
+#[instrument(skip(state))]
+pub async fn export_user_handler(
+    State(state): State<AppState>,
+    payload: JWTPayload,
+) -> Result<Response, AppError> {
+    let user = payload.to_user(&state.conn).await?;
+    let mut entries = Vec::new();
+    entries.push(to_json(
+        "profile.json",
+        &ExportProfile {
+            privacy: PrivacySettings::from(&user),
+            profile: UserProfile::from(user.clone()),
+        },
+    )?);
+    let contacts = ContactList::query_contact(user, &state.conn).await?;
+    entries.push(to_json("contacts.json", &contacts)?);
+    let mut groups = Vec::new();
+    for m in Member::find()
+        .filter(member::Column::User.eq(payload.id))
+        .all(&state.conn)
+        .await?
+    {
+        groups.push(GroupProfile::from_group_id(m.group, &state.conn, payload.id).await?);
+    }
+    entries.push(to_json("groups.json", &groups)?);
+    let msgs: Vec<Msg> = Message::find()
+        .join_rev(
+            JoinType::InnerJoin,
+            feed::Entity::belongs_to(message::Entity)
+                .from(feed::Column::Message)
+                .to(message::Column::Id)
+                .into(),
+        )
+        .filter(feed::Column::User.eq(payload.id))
+        .order_by(message::Column::CreatedAt, sea_orm::Order::Asc)
+        .all(&state.conn)
+        .await?
+        .into_iter()
+        .map(|m| Msg::from((m, Vec::new())))
+        .collect();
+    entries.push(to_json("messages.json", &msgs)?);
+    let uploads = Upload::find()
+        .filter(upload::Column::Uploader.eq(payload.id))
+        .all(&state.conn)
+        .await?;
+    let dir = std::path::Path::new(UPLOAD_DIR.get().unwrap());
+    for u in uploads.iter() {
+        entries.push(Entry::File(
+            format!("attachments/{}", u.uuid),
+            dir.join(u.uuid.to_string()),
+        ));
+    }
+    let uploads: Vec<ExportUpload> = uploads.into_iter().map(ExportUpload::from).collect();
+    entries.push(to_json("uploads.json", &uploads)?);
+    let data = tokio::task::spawn_blocking(move || write_archive(entries)).await??;
+    event!(
+        Level::INFO,
+        "export data of user [{}]: [{} bytes]",
+        payload.id,
+        data.len()
+    );
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/zip".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"veloquent-{}.zip\"", payload.id),
+            ),
+        ],
+        data,
+    )
+        .into_response())
+}
@@ -21,13 +21,35 @@ pub struct FeedItem {
 }
 
 /// 群聊的用户更新
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 #[cfg_attr(test, derive(Deserialize))]
 pub struct GroupUpdate {
     pub group: Uuid,
     pub user: Uuid,
 }
 
+/// 群聊的管理员更新
+#[derive(Serialize)]
+#[cfg_attr(test, derive(Deserialize))]
+pub struct AdminUpdate {
+    pub group: Uuid,
+    pub user: Uuid,
+    /// `true` 为设置管理员, `false` 为取消管理员
+    pub granted: bool,
+}
+
+/// 会话的置顶消息更新
+#[derive(Serialize)]
+#[cfg_attr(test, derive(Deserialize))]
+pub struct PinUpdate {
+    pub session: Uuid,
+    pub msg: Uuid,
+    /// 操作者
+    pub user: Uuid,
+    /// `true` 为置顶, `false` 为取消置顶
+    pub pinned: bool,
+}
+
 #[cfg_attr(feature = "dev", derive(ToSchema))]
 #[derive(Serialize)]
 #[cfg_attr(test, derive(Deserialize))]
@@ -77,6 +99,30 @@ pub enum Notification {
     GroupAccepts {
         items: Vec<GroupUpdate>,
     },
+    /// 群聊资料被修改, `user` 为修改者
+    GroupEdits {
+        items: Vec<GroupUpdate>,
+    },
+    /// 离开群聊, 包括被移除和主动退出, `user` 为离开的成员
+    GroupRemovals {
+        items: Vec<GroupUpdate>,
+    },
+    /// 群聊被解散, `user` 为解散者
+    GroupDissolved {
+        items: Vec<GroupUpdate>,
+    },
+    /// 群主被转让, `user` 为新群主
+    GroupTransfers {
+        items: Vec<GroupUpdate>,
+    },
+    /// 管理员被设置或取消
+    GroupAdmins {
+        items: Vec<AdminUpdate>,
+    },
+    /// 会话的置顶消息变更
+    Pins {
+        items: Vec<PinUpdate>,
+    },
 }
 
 async fn count_unread_msgs(
@@ -1,18 +1,45 @@
 use super::*;
 use entity::{
     group, member,
-    prelude::{Group, Member, Session, User},
+    prelude::{Group, Member, Message, Session, Upload, User},
     session,
 };
 
-use feed::{GroupUpdate, Notification};
+use feed::{AdminUpdate, GroupUpdate, Notification};
+use message::SystemEvent;
+use role::{GroupAction, PermissionMatrix, Role};
+use sea_orm::TransactionTrait;
+
+/// 群聊设置
+#[cfg_attr(feature = "dev", derive(ToSchema))]
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct GroupSettings {
+    /// 通过邀请链接加入时是否自动通过审批, 默认为 `false`
+    pub auto_approve: bool,
+    /// 全员禁言, 开启时只有管理员和群主可以发言, 默认为 `false`
+    ///
+    /// 通过 `/group/speak/{id}` 修改, 修改群聊资料时忽略该字段
+    pub mute_all: bool,
+    /// 权限设置, 只有群主可以修改
+    pub permissions: PermissionMatrix,
+}
+
+impl From<&group::Model> for GroupSettings {
+    fn from(g: &group::Model) -> Self {
+        serde_json::from_value(g.settings.clone()).unwrap_or_default()
+    }
+}
 
 impl group::Model {
-    async fn from_uuid(id: Uuid, conn: &DatabaseConnection) -> Result<Self, AppError> {
+    pub(super) async fn from_uuid(id: Uuid, conn: &DatabaseConnection) -> Result<Self, AppError> {
         Group::find_by_id(id)
             .one(conn)
             .await?
-            .ok_or(AppError::NotFound(format!("cannot find group [{id}]")))
+            .ok_or(AppError::NotFound(
+                ErrorCode::GroupNotFound,
+                format!("cannot find group [{id}]"),
+            ))
     }
 
     async fn get_pending_members(
@@ -21,28 +48,58 @@ impl group::Model {
     ) -> Result<Vec<Uuid>, AppError> {
         let members = Member::find()
             .filter(member::Column::Group.eq(group))
-            .filter(member::Column::Permission.eq(-1))
+            .filter(member::Column::Permission.eq(Role::Pending))
             .all(conn)
             .await?;
         Ok(members.into_iter().map(|m| m.user).collect())
     }
 
+    /// 查找会话所属的群聊, 双人会话返回 `None`
     pub(super) async fn from_session(
         session: Uuid,
         conn: &DatabaseConnection,
-    ) -> Result<Self, AppError> {
-        Group::find()
+    ) -> Result<Option<Self>, AppError> {
+        Ok(Group::find()
             .filter(group::Column::Session.eq(session))
             .one(conn)
-            .await?
-            .ok_or(AppError::NotFound(format!(
-                "cannot find group with session [{session}]"
-            )))
+            .await?)
     }
 }
 
 impl member::Model {
-    async fn from_group_and_user(
+    /// 检查成员是否可以在群聊中发言
+    ///
+    /// 管理员和群主不受禁言限制
+    pub(super) fn check_speak(&self, g: &group::Model) -> Result<(), AppError> {
+        match self.role() {
+            Role::Pending => Err(AppError::Forbidden(
+                ErrorCode::PendingMember,
+                format!("pending member cannot speak in group [{}]", g.id),
+            )),
+            Role::Admin | Role::Owner => Ok(()),
+            Role::Member => {
+                if GroupSettings::from(g).mute_all {
+                    return Err(AppError::Forbidden(
+                        ErrorCode::GroupMuted,
+                        format!("group [{}] is muted", g.id),
+                    ));
+                }
+                match self.banned_until {
+                    Some(t) if t > chrono::Utc::now().naive_utc() => Err(AppError::Forbidden(
+                        ErrorCode::MemberBanned,
+                        format!(
+                            "banned in group [{}] until {}",
+                            g.id,
+                            t.and_utc().timestamp_millis()
+                        ),
+                    )),
+                    _ => Ok(()),
+                }
+            }
+        }
+    }
+
+    pub(super) async fn from_group_and_user(
         group: Uuid,
         user: Uuid,
         conn: &DatabaseConnection,
@@ -52,52 +109,79 @@ impl member::Model {
             .filter(member::Column::User.eq(user))
             .one(conn)
             .await?
-            .ok_or(AppError::NotFound(format!(
-                "user [{user}] not in group [{group}]"
-            )))
+            .ok_or(AppError::NotFound(
+                ErrorCode::MemberNotFound,
+                format!("user [{user}] not in group [{group}]"),
+            ))
     }
 }
 
 impl Member {
-    pub(super) async fn is_admin(
+    pub(super) async fn get_admins(
         group: Uuid,
-        user: Uuid,
         conn: &DatabaseConnection,
-    ) -> Result<bool, AppError> {
-        let m = Member::find()
+    ) -> Result<Vec<Uuid>, AppError> {
+        let admins = Member::find()
             .filter(member::Column::Group.eq(group))
-            .filter(member::Column::User.eq(user))
-            .one(conn)
-            .await?
-            .ok_or(AppError::NotFound(format!(
-                "member [{user}] not in [{group}]",
-            )))?;
-        Ok(m.permission == 1)
+            .filter(member::Column::Permission.eq(Role::Admin))
+            .all(conn)
+            .await?;
+        Ok(admins.into_iter().map(|m| m.user).collect())
     }
 
-    pub(super) async fn get_admins(
+    /// 已经加入群聊的成员, 不包含待审批的用户
+    pub(super) async fn get_members(
         group: Uuid,
         conn: &DatabaseConnection,
     ) -> Result<Vec<Uuid>, AppError> {
-        let admins = Member::find()
+        let members = Member::find()
             .filter(member::Column::Group.eq(group))
-            .filter(member::Column::Permission.eq(1))
+            .filter(member::Column::Permission.ne(Role::Pending))
             .all(conn)
             .await?;
-        Ok(admins.into_iter().map(|m| m.user).collect())
+        Ok(members.into_iter().map(|m| m.user).collect())
     }
 }
 
+/// 向用户推送通知
+fn push_notification(state: &AppState, receivers: Vec<Uuid>, notification: Notification) {
+    let ws_pool = state.ws_pool.clone();
+    trace::spawn(async move {
+        let notification = serde_json::to_string(&notification).unwrap();
+        for r in receivers {
+            ws_pool
+                .notify(r, WebSocketMessage::Text(notification.clone()))
+                .await;
+        }
+    });
+}
+
+/// 只能管理角色低于自己的成员
+fn check_outranks(actor: &member::Model, target: &member::Model) -> Result<(), AppError> {
+    if target.role() >= actor.role() {
+        return Err(AppError::Forbidden(
+            ErrorCode::RoleOutranked,
+            format!(
+                "cannot manage [{}] with role [{:?}]",
+                target.user,
+                target.role()
+            ),
+        ));
+    }
+    Ok(())
+}
+
 impl From<(Uuid, Uuid)> for member::ActiveModel {
     fn from(value: (Uuid, Uuid)) -> Self {
         member::ActiveModel {
             id: ActiveValue::not_set(),
             group: ActiveValue::set(value.0),
             user: ActiveValue::set(value.1),
-            permission: ActiveValue::set(0),
+            permission: ActiveValue::set(Role::Member.into()),
             created_at: ActiveValue::not_set(),
             pin: ActiveValue::not_set(),
             mute: ActiveValue::not_set(),
+            banned_until: ActiveValue::not_set(),
         }
     }
 }
@@ -112,6 +196,16 @@ pub struct GroupPost {
     pub name: Option<String>,
     #[cfg(not(test))]
     name: Option<String>,
+    /// 群头像, 需要先上传, 提供空 UUID 表示清空
+    #[cfg(test)]
+    pub avatar: Option<Uuid>,
+    #[cfg(not(test))]
+    avatar: Option<Uuid>,
+    /// 群简介
+    #[cfg(test)]
+    pub description: Option<String>,
+    #[cfg(not(test))]
+    description: Option<String>,
     /// 群成员, 自动包含创建者, 此外最少需要一个成员
     #[cfg(test)]
     pub members: Vec<Uuid>,
@@ -125,7 +219,19 @@ pub struct GroupPost {
 #[cfg_attr(test, derive(Deserialize))]
 pub struct GroupProfile {
     /// 群名
+    #[cfg(test)]
+    pub name: Option<String>,
+    #[cfg(not(test))]
     name: Option<String>,
+    /// 群头像
+    avatar: Option<Uuid>,
+    /// 群简介
+    #[cfg(test)]
+    pub description: Option<String>,
+    #[cfg(not(test))]
+    description: Option<String>,
+    /// 群聊设置
+    settings: GroupSettings,
     /// 群主 UUID
     #[cfg(test)]
     pub owner: Uuid,
@@ -154,6 +260,21 @@ pub struct GroupProfile {
     ///
     /// 不包含群主
     admins: Vec<Uuid>,
+    /// 当前用户在群聊中的角色
+    #[cfg(test)]
+    pub role: Option<Role>,
+    #[cfg(not(test))]
+    role: Option<Role>,
+    /// 最新的群公告
+    #[cfg(test)]
+    pub notice: Option<notice::Notice>,
+    #[cfg(not(test))]
+    notice: Option<notice::Notice>,
+    /// 当前用户的禁言截止时间, UTC 毫秒时间戳
+    #[cfg(test)]
+    pub banned_until: Option<i64>,
+    #[cfg(not(test))]
+    banned_until: Option<i64>,
     /// 是否置顶
     pin: bool,
     /// 是否静音
@@ -181,7 +302,7 @@ pub async fn get_group_handler(
 }
 
 impl GroupProfile {
-    async fn from_group_id(
+    pub(super) async fn from_group_id(
         id: Uuid,
         conn: &DatabaseConnection,
         user: Uuid,
@@ -189,30 +310,44 @@ impl GroupProfile {
         let g = group::Model::from_uuid(id, conn).await?;
         let members = Member::find()
             .filter(member::Column::Group.eq(g.id))
-            .filter(member::Column::Permission.ne(-1))
             .all(conn)
             .await?;
         let mut pin = false;
         let mut mute = false;
+        let mut role = None;
+        let mut banned_until = None;
         let members: Vec<Uuid> = members
             .into_iter()
-            .map(|m| {
+            .filter_map(|m| {
                 if m.user == user {
                     pin = m.pin;
                     mute = m.mute;
+                    role = Some(m.role());
+                    banned_until = m
+                        .banned_until
+                        .filter(|t| *t > chrono::Utc::now().naive_utc())
+                        .map(|t| t.and_utc().timestamp_millis());
                 }
-                m.user
+                (m.role() != Role::Pending).then_some(m.user)
             })
             .collect();
         let admins = Member::get_admins(g.id, conn).await?;
+        let settings = GroupSettings::from(&g);
+        let notice = notice::Notice::latest(&g, user, conn).await?;
         Ok(GroupProfile {
             name: g.name,
+            avatar: g.avatar,
+            description: g.description,
+            settings,
             owner: g.owner,
             id: g.id,
             session: g.session,
             created_at: g.created_at.and_utc().timestamp_millis(),
             members,
             admins,
+            role,
+            notice,
+            banned_until,
             pin,
             mute,
         })
@@ -270,8 +405,12 @@ pub async fn create_group_handler(
     members.sort();
     members.dedup();
     if members.len() < 2 {
-        return Err(AppError::BadRequest("at least 2 members".to_string()));
+        return Err(AppError::BadRequest(
+            ErrorCode::GroupTooSmall,
+            "at least 2 members".to_string(),
+        ));
     }
+    check_avatar(req.avatar, &state.conn).await?;
     let s = Session::insert(session::ActiveModel::default())
         .exec(&state.conn)
         .await?
@@ -282,10 +421,16 @@ pub async fn create_group_handler(
         owner: ActiveValue::set(user.id),
         session: ActiveValue::set(s),
         created_at: ActiveValue::not_set(),
+        avatar: ActiveValue::set(req.avatar.filter(|a| !a.is_nil())),
+        description: ActiveValue::set(req.description),
+        settings: ActiveValue::not_set(),
     };
     let g = Group::insert(g).exec(&state.conn).await?.last_insert_id;
     for m in members {
-        let m = member::ActiveModel::from((g, m));
+        let mut m = member::ActiveModel::from((g, m));
+        if m.user.as_ref() == &user.id {
+            m.permission = ActiveValue::set(Role::Owner.into());
+        }
         Member::insert(m).exec(&state.conn).await?;
     }
     let g = GroupProfile::from_group_id(g, &state.conn, payload.id).await?;
@@ -313,14 +458,17 @@ pub async fn invite_group_handler(
 ) -> Result<impl IntoResponse, AppError> {
     let user = payload.to_user(&state.conn).await?;
     let g = group::Model::from_uuid(id, &state.conn).await?;
-    member::Model::from_group_and_user(g.id, user.id, &state.conn).await?;
+    Member::authorize(&g, user.id, GroupAction::Invite, &state.conn).await?;
     let admins = Member::get_admins(g.id, &state.conn).await?;
     let owner = g.owner;
     for u in users.into_iter() {
         let _ = User::find_by_id(u)
             .one(&state.conn)
             .await?
-            .ok_or(AppError::NotFound(format!("cannot find user [{}]", u)))?;
+            .ok_or(AppError::NotFound(
+                ErrorCode::UserNotFound,
+                format!("cannot find user [{}]", u),
+            ))?;
         let m = Member::find()
             .filter(member::Column::Group.eq(g.id))
             .filter(member::Column::User.eq(u))
@@ -330,46 +478,37 @@ pub async fn invite_group_handler(
             continue;
         }
         let mut m = member::ActiveModel::from((g.id, u));
-        m.permission = ActiveValue::set(-1);
+        m.permission = ActiveValue::set(Role::Pending.into());
         Member::insert(m).exec(&state.conn).await?;
-        let admins = admins.clone();
-        let ws_pool = state.ws_pool.clone();
-        tokio::task::spawn(async move {
-            let notification = Notification::GroupRequests {
-                items: vec![GroupUpdate {
-                    group: g.id,
-                    user: u,
-                }],
-            };
-            ws_pool
-                .notify(
-                    owner,
-                    WebSocketMessage::Text(serde_json::to_string(&notification).unwrap()),
-                )
-                .await;
-            ws_pool
-                .notify(
-                    u,
-                    WebSocketMessage::Text(
-                        serde_json::to_string(&Notification::GroupInvites {
-                            items: vec![GroupUpdate {
-                                group: g.id,
-                                user: u,
-                            }],
-                        })
-                        .unwrap(),
-                    ),
-                )
-                .await;
-            for a in &admins {
-                ws_pool
-                    .notify(
-                        a.clone(),
-                        WebSocketMessage::Text(serde_json::to_string(&notification).unwrap()),
-                    )
-                    .await;
-            }
-        });
+        Message::send_system(
+            &state,
+            g.session,
+            SystemEvent::Invite {
+                operator: user.id,
+                user: u,
+            },
+        )
+        .await?;
+        let update = GroupUpdate {
+            group: g.id,
+            user: u,
+        };
+        let mut receivers = vec![owner];
+        receivers.extend(admins.iter().copied());
+        push_notification(
+            &state,
+            receivers,
+            Notification::GroupRequests {
+                items: vec![update.clone()],
+            },
+        );
+        push_notification(
+            &state,
+            vec![u],
+            Notification::GroupInvites {
+                items: vec![update],
+            },
+        );
     }
     Ok(StatusCode::OK.into_response())
 }
@@ -383,9 +522,9 @@ pub(super) struct GroupDeleteParams {
 
 /// 删除群聊或移除群聊中的成员
 ///
-/// 只有群主可以删除群聊
+/// 只有群主可以删除群聊, 所有成员会收到 `GroupDissolved` 通知
 ///
-/// 管理员和群主可以移除群聊中的成员
+/// 移除成员所需角色由权限设置 `kick` 决定, 且只能移除角色低于自己的成员, 被移除的成员会收到 `GroupRemovals` 通知
 #[cfg_attr(feature = "dev",
 utoipa::path(
     delete,
@@ -406,13 +545,9 @@ pub async fn delete_group_handler(
     let user = payload.to_user(&state.conn).await?;
     let g = group::Model::from_uuid(id, &state.conn).await?;
     if let Some(u) = params.user {
-        let is_admin = Member::is_admin(g.id, user.id, &state.conn).await?;
-        if !is_admin && g.owner != user.id {
-            return Err(AppError::Forbidden(
-                "only admin or owner can delete member".to_string(),
-            ));
-        }
+        let actor = Member::authorize(&g, user.id, GroupAction::Kick, &state.conn).await?;
         let m = member::Model::from_group_and_user(g.id, u, &state.conn).await?;
+        check_outranks(&actor, &m)?;
         let res: DeleteResult = Member::delete_by_id(m.id).exec(&state.conn).await?;
         if res.rows_affected == 0 {
             return Err(AppError::Server(anyhow::anyhow!(
@@ -420,12 +555,33 @@ pub async fn delete_group_handler(
             )));
         }
         event!(Level::INFO, "delete member [{u}] from group [{id}]");
-    } else {
-        if g.owner != user.id {
-            return Err(AppError::Forbidden(
-                "only owner can delete group".to_string(),
-            ));
+        push_notification(
+            &state,
+            vec![u],
+            Notification::GroupRemovals {
+                items: vec![GroupUpdate { group: id, user: u }],
+            },
+        );
+        if m.role() != Role::Pending {
+            Message::send_system(
+                &state,
+                g.session,
+                SystemEvent::Remove {
+                    operator: user.id,
+                    user: u,
+                },
+            )
+            .await?;
         }
+    } else {
+        Member::authorize(&g, user.id, GroupAction::Dissolve, &state.conn).await?;
+        let members: Vec<Uuid> = Member::find()
+            .filter(member::Column::Group.eq(id))
+            .all(&state.conn)
+            .await?
+            .into_iter()
+            .map(|m| m.user)
+            .collect();
         let res: DeleteResult = Group::delete_by_id(id).exec(&state.conn).await?;
         if res.rows_affected == 0 {
             return Err(AppError::Server(anyhow::anyhow!(
@@ -433,6 +589,16 @@ pub async fn delete_group_handler(
             )));
         }
         event!(Level::INFO, "delete group [{id}]",);
+        push_notification(
+            &state,
+            members,
+            Notification::GroupDissolved {
+                items: vec![GroupUpdate {
+                    group: id,
+                    user: user.id,
+                }],
+            },
+        );
     }
     Ok(StatusCode::NO_CONTENT.into_response())
 }
@@ -468,19 +634,15 @@ pub async fn approve_group_handler(
 ) -> Result<impl IntoResponse, AppError> {
     let user = payload.to_user(&state.conn).await?;
     let g = group::Model::from_uuid(group, &state.conn).await?;
-    let is_admin = Member::is_admin(group, user.id, &state.conn).await?;
-    if !is_admin && g.owner != user.id {
-        return Err(AppError::Forbidden(
-            "only admin or owner can approve member".to_string(),
-        ));
-    }
+    Member::authorize(&g, user.id, GroupAction::Approve, &state.conn).await?;
     let deny = params.deny.unwrap_or(false);
     if let Some(member) = params.member {
         let m = member::Model::from_group_and_user(g.id, member, &state.conn).await?;
-        return if m.permission != -1 {
-            Err(AppError::BadRequest(format!(
-                "member [{member}] already in group [{group}]"
-            )))
+        return if m.role() != Role::Pending {
+            Err(AppError::BadRequest(
+                ErrorCode::AlreadyMember,
+                format!("member [{member}] already in group [{group}]"),
+            ))
         } else if deny {
             let res: DeleteResult = Member::delete_by_id(m.id).exec(&state.conn).await?;
             if res.rows_affected == 0 {
@@ -492,23 +654,27 @@ pub async fn approve_group_handler(
             }
         } else {
             let mut m = m.into_active_model();
-            m.permission = ActiveValue::set(0);
+            m.permission = ActiveValue::set(Role::Member.into());
             Member::update(m).exec(&state.conn).await?;
-            tokio::task::spawn(async move {
-                let notification = Notification::GroupAccepts {
+            Message::send_system(
+                &state,
+                g.session,
+                SystemEvent::Join {
+                    operator: Some(user.id),
+                    user: member,
+                },
+            )
+            .await?;
+            push_notification(
+                &state,
+                vec![member],
+                Notification::GroupAccepts {
                     items: vec![GroupUpdate {
                         group,
                         user: member,
                     }],
-                };
-                state
-                    .ws_pool
-                    .notify(
-                        member,
-                        WebSocketMessage::Text(serde_json::to_string(&notification).unwrap()),
-                    )
-                    .await;
-            });
+                },
+            );
             Ok(StatusCode::OK.into_response())
         };
     }
@@ -552,12 +718,7 @@ pub async fn monitor_group_handler(
 ) -> Result<Json<Vec<Uuid>>, AppError> {
     let user = payload.to_user(&state.conn).await?;
     let g = group::Model::from_uuid(group, &state.conn).await?;
-    let is_admin = Member::is_admin(group, user.id, &state.conn).await?;
-    if !is_admin && g.owner != user.id {
-        return Err(AppError::Forbidden(
-            "only admin or owner can view applications".to_string(),
-        ));
-    }
+    Member::authorize(&g, user.id, GroupAction::Approve, &state.conn).await?;
     Ok(Json(
         group::Model::get_pending_members(group, &state.conn).await?,
     ))
@@ -566,6 +727,8 @@ pub async fn monitor_group_handler(
 /// 群聊管理
 ///
 /// 转移群主, 添加或移除管理员, 添加或移除群成员
+///
+/// 转移群主和管理员变更会向所有成员推送 `GroupTransfers` 和 `GroupAdmins` 通知, 被移除的成员会收到 `GroupRemovals` 通知
 #[cfg_attr(feature = "dev",
 utoipa::path(
     put,
@@ -586,33 +749,64 @@ pub async fn manage_group_handler(
     let user = payload.to_user(&state.conn).await?;
     let g = group::Model::from_uuid(group, &state.conn).await?;
     if let Some(owner) = params.owner {
-        if g.owner != user.id {
-            return Err(AppError::Forbidden(
-                "only owner can transfer group".to_string(),
-            ));
-        }
+        let actor = Member::authorize(&g, user.id, GroupAction::Manage, &state.conn).await?;
         if user.id == owner {
-            return Err(AppError::BadRequest("cannot transfer to self".to_string()));
+            return Err(AppError::BadRequest(
+                ErrorCode::TransferToSelf,
+                "cannot transfer to self".to_string(),
+            ));
         }
         let m = member::Model::from_group_and_user(g.id, owner, &state.conn).await?;
-        let mut g = g.clone().into_active_model();
-        g.owner = ActiveValue::set(owner);
-        Group::update(g).exec(&state.conn).await?;
-        if m.permission == 1 {
-            let mut m = m.into_active_model();
-            m.permission = ActiveValue::set(0);
-            Member::update(m).exec(&state.conn).await?;
+        if m.role() == Role::Pending {
+            return Err(AppError::BadRequest(
+                ErrorCode::TransferToPending,
+                format!("cannot transfer to pending member [{owner}]"),
+            ));
         }
+        let txn = state.conn.begin().await?;
+        let mut active = g.clone().into_active_model();
+        active.owner = ActiveValue::set(owner);
+        Group::update(active).exec(&txn).await?;
+        let mut m = m.into_active_model();
+        m.permission = ActiveValue::set(Role::Owner.into());
+        Member::update(m).exec(&txn).await?;
+        let mut actor = actor.into_active_model();
+        actor.permission = ActiveValue::set(Role::Member.into());
+        Member::update(actor).exec(&txn).await?;
+        txn.commit().await?;
         event!(Level::INFO, "transfer group [{group}] to [{owner}]");
+        push_notification(
+            &state,
+            Member::get_members(group, &state.conn).await?,
+            Notification::GroupTransfers {
+                items: vec![GroupUpdate { group, user: owner }],
+            },
+        );
+        Message::send_system(
+            &state,
+            g.session,
+            SystemEvent::Transfer {
+                operator: user.id,
+                user: owner,
+            },
+        )
+        .await?;
     }
     let remove = params.remove.unwrap_or(false);
     if let Some(admin) = params.admin {
-        if g.owner != user.id {
-            return Err(AppError::Forbidden("only owner can edit admin".to_string()));
-        }
+        Member::authorize(&g, user.id, GroupAction::Manage, &state.conn).await?;
         let m = member::Model::from_group_and_user(g.id, admin, &state.conn).await?;
+        if !matches!(m.role(), Role::Member | Role::Admin) {
+            return Err(AppError::BadRequest(
+                ErrorCode::InvalidAdminChange,
+                format!(
+                    "[{admin}] cannot be {} admin",
+                    if remove { "removed from" } else { "set as" }
+                ),
+            ));
+        }
         let mut m = m.into_active_model();
-        m.permission = ActiveValue::set(if remove { 0 } else { 1 });
+        m.permission = ActiveValue::set(if remove { Role::Member } else { Role::Admin }.into());
         Member::update(m).exec(&state.conn).await?;
         event!(
             Level::INFO,
@@ -620,14 +814,35 @@ pub async fn manage_group_handler(
             if remove { "remove" } else { "add" },
             if remove { "off" } else { "into" },
         );
+        push_notification(
+            &state,
+            Member::get_members(group, &state.conn).await?,
+            Notification::GroupAdmins {
+                items: vec![AdminUpdate {
+                    group,
+                    user: admin,
+                    granted: !remove,
+                }],
+            },
+        );
+        Message::send_system(
+            &state,
+            g.session,
+            SystemEvent::Admin {
+                operator: user.id,
+                user: admin,
+                granted: !remove,
+            },
+        )
+        .await?;
     }
     if let Some(member) = params.member {
-        let is_admin = Member::is_admin(group, user.id, &state.conn).await?;
-        if !is_admin && g.owner != user.id {
-            return Err(AppError::Forbidden(
-                "only admin or owner can edit member".to_string(),
-            ));
-        }
+        let action = if remove {
+            GroupAction::Kick
+        } else {
+            GroupAction::Approve
+        };
+        let actor = Member::authorize(&g, user.id, action, &state.conn).await?;
         let m = Member::find()
             .filter(member::Column::Group.eq(g.id))
             .filter(member::Column::User.eq(member))
@@ -635,31 +850,58 @@ pub async fn manage_group_handler(
             .await?;
         return match m {
             Some(m) => {
-                if m.permission == 1 && is_admin && remove {
-                    return Err(AppError::Forbidden(
-                        "only owner can remove admin".to_string(),
-                    ));
-                }
                 if remove {
-                    if member == g.owner {
-                        return Err(AppError::BadRequest("cannot remove owner".to_string()));
-                    } else if member == user.id {
-                        return Err(AppError::BadRequest("cannot remove self".to_string()));
+                    if member == user.id {
+                        return Err(AppError::BadRequest(
+                            ErrorCode::RemoveSelf,
+                            "cannot remove self".to_string(),
+                        ));
                     }
+                    check_outranks(&actor, &m)?;
                     Member::delete_by_id(m.id).exec(&state.conn).await?;
                 } else {
-                    return Err(AppError::BadRequest(format!(
-                        "[{member}] already in group [{}]",
-                        g.id
-                    )));
+                    return Err(AppError::BadRequest(
+                        ErrorCode::AlreadyMember,
+                        format!("[{member}] already in group [{}]", g.id),
+                    ));
                 }
                 event!(Level::INFO, "remove member [{member}] from group [{group}]");
+                push_notification(
+                    &state,
+                    vec![member],
+                    Notification::GroupRemovals {
+                        items: vec![GroupUpdate {
+                            group,
+                            user: member,
+                        }],
+                    },
+                );
+                if m.role() != Role::Pending {
+                    Message::send_system(
+                        &state,
+                        g.session,
+                        SystemEvent::Remove {
+                            operator: user.id,
+                            user: member,
+                        },
+                    )
+                    .await?;
+                }
                 Ok(StatusCode::NO_CONTENT.into_response())
             }
             None => {
                 let m = member::ActiveModel::from((g.id, member));
                 Member::insert(m).exec(&state.conn).await?;
                 event!(Level::INFO, "add member [{member}] into group [{group}]");
+                Message::send_system(
+                    &state,
+                    g.session,
+                    SystemEvent::Join {
+                        operator: Some(user.id),
+                        user: member,
+                    },
+                )
+                .await?;
                 Ok(StatusCode::OK.into_response())
             }
         };
@@ -702,11 +944,11 @@ pub async fn pin_group_handler(
     let mut m = m.into_active_model();
     m.pin = params
         .pin
-        .map(|p| ActiveValue::set(p))
+        .map(ActiveValue::set)
         .unwrap_or(ActiveValue::not_set());
     m.mute = params
         .mute
-        .map(|p| ActiveValue::set(p))
+        .map(ActiveValue::set)
         .unwrap_or(ActiveValue::not_set());
     Member::update(m).exec(&state.conn).await?;
     event!(Level::INFO, "edit group [{}]", id);
@@ -714,6 +956,8 @@ pub async fn pin_group_handler(
 }
 
 /// 退出群聊
+///
+/// 退出者的所有客户端会收到 `GroupRemovals` 通知
 #[cfg_attr(feature = "dev",
 utoipa::path(
     delete,
@@ -735,6 +979,7 @@ pub async fn exit_group_handler(
     let m = member::Model::from_group_and_user(g.id, user.id, &state.conn).await?;
     if g.owner == user.id {
         return Err(AppError::Forbidden(
+            ErrorCode::GroupOwnerExit,
             "owner cannot exit group before transferring the group".to_string(),
         ));
     }
@@ -745,5 +990,220 @@ pub async fn exit_group_handler(
         )));
     }
     event!(Level::INFO, "user [{}] exit group [{id}]", user.id);
+    push_notification(
+        &state,
+        vec![user.id],
+        Notification::GroupRemovals {
+            items: vec![GroupUpdate {
+                group: id,
+                user: user.id,
+            }],
+        },
+    );
+    if m.role() != Role::Pending {
+        Message::send_system(&state, g.session, SystemEvent::Exit { user: user.id }).await?;
+    }
     Ok(StatusCode::NO_CONTENT.into_response())
 }
+
+/// 检查头像文件是否已上传, 空 UUID 表示不设置头像
+async fn check_avatar(avatar: Option<Uuid>, conn: &DatabaseConnection) -> Result<(), AppError> {
+    if let Some(avatar) = avatar.filter(|a| !a.is_nil()) {
+        Upload::find_by_id(avatar)
+            .one(conn)
+            .await?
+            .ok_or(AppError::NotFound(
+                ErrorCode::FileNotFound,
+                format!("cannot find file [{avatar}]"),
+            ))?;
+    }
+    Ok(())
+}
+
+/// 群聊资料修改请求
+///
+/// 不提供该字段表示不进行修改, 提供空字符串表示清空
+#[cfg_attr(feature = "dev", derive(ToSchema))]
+#[derive(Deserialize, Debug)]
+#[cfg_attr(test, derive(Serialize))]
+pub struct GroupEdition {
+    /// 群名
+    #[cfg(test)]
+    pub name: Option<String>,
+    #[cfg(not(test))]
+    name: Option<String>,
+    /// 群头像, 需要先上传, 提供空 UUID 表示清空
+    #[cfg(test)]
+    pub avatar: Option<Uuid>,
+    #[cfg(not(test))]
+    avatar: Option<Uuid>,
+    /// 群简介
+    #[cfg(test)]
+    pub description: Option<String>,
+    #[cfg(not(test))]
+    description: Option<String>,
+    /// 群聊设置
+    #[cfg(test)]
+    pub settings: Option<GroupSettings>,
+    #[cfg(not(test))]
+    settings: Option<GroupSettings>,
+}
+
+/// 修改群聊资料
+///
+/// 群名, 头像, 简介与群聊设置, 所需角色由权限设置 `edit` 决定, 修改 `permissions` 需要群主
+///
+/// 修改成功后, 所有群成员会收到 `GroupEdits` 通知
+#[cfg_attr(feature = "dev",
+utoipa::path(
+    put,
+    path = "/group/profile/{id}",
+    params(("id" = Uuid, Path, description = "群聊的唯一主键")),
+    request_body = GroupEdition,
+    responses(
+        (status = 200, description = "修改成功", body = GroupProfile),
+        (status = 403, description = "权限不足", body = AppErrorResponse),
+    ),
+    tag = "group"
+))]
+#[instrument(skip(state))]
+pub async fn edit_group_handler(
+    State(state): State<AppState>,
+    payload: JWTPayload,
+    Path(id): Path<Uuid>,
+    Json(edition): Json<GroupEdition>,
+) -> Result<Json<GroupProfile>, AppError> {
+    let user = payload.to_user(&state.conn).await?;
+    let g = group::Model::from_uuid(id, &state.conn).await?;
+    Member::authorize(&g, user.id, GroupAction::Edit, &state.conn).await?;
+    let current = GroupSettings::from(&g);
+    if let Some(settings) = &edition.settings {
+        if settings.permissions != current.permissions {
+            Member::authorize(&g, user.id, GroupAction::Manage, &state.conn).await?;
+            settings.permissions.validate()?;
+        }
+    }
+    check_avatar(edition.avatar, &state.conn).await?;
+    let mut g = g.into_active_model();
+    if let Some(name) = edition.name {
+        g.name = ActiveValue::set(Some(name).filter(|n| !n.is_empty()));
+    }
+    if let Some(avatar) = edition.avatar {
+        g.avatar = ActiveValue::set(Some(avatar).filter(|a| !a.is_nil()));
+    }
+    if let Some(description) = edition.description {
+        g.description = ActiveValue::set(Some(description).filter(|d| !d.is_empty()));
+    }
+    if let Some(mut settings) = edition.settings {
+        // 全员禁言只能通过 `/group/speak/{id}` 修改
+        settings.mute_all = current.mute_all;
+        g.settings = ActiveValue::set(serde_json::to_value(settings)?);
+    }
+    Group::update(g).exec(&state.conn).await?;
+    event!(Level::INFO, "user [{}] edit group [{id}]", user.id);
+    let members = Member::get_members(id, &state.conn).await?;
+    push_notification(
+        &state,
+        members,
+        Notification::GroupEdits {
+            items: vec![GroupUpdate {
+                group: id,
+                user: user.id,
+            }],
+        },
+    );
+    Ok(Json(
+        GroupProfile::from_group_id(id, &state.conn, payload.id).await?,
+    ))
+}
+
+#[cfg_attr(feature = "dev", derive(IntoParams))]
+#[derive(Deserialize, Debug)]
+pub(super) struct SpeakParams {
+    /// 开启或关闭全员禁言
+    mute_all: Option<bool>,
+    /// 禁言或解除禁言的成员
+    member: Option<Uuid>,
+    /// 禁言时长, 单位为秒
+    ///
+    /// 字段为空或为 `0` 时解除禁言
+    duration: Option<u32>,
+}
+
+/// 群聊发言管理
+///
+/// 开启或关闭全员禁言, 禁言或解除禁言成员, 所需角色由权限设置 `mute` 决定, 且只能禁言角色低于自己的成员
+///
+/// 每项修改都会在群聊中发送系统消息
+#[cfg_attr(feature = "dev",
+utoipa::path(
+    put,
+    path = "/group/speak/{id}",
+    params(("id" = Uuid, Path, description = "群聊的唯一主键"), SpeakParams),
+    responses(
+        (status = 200, description = "修改成功"),
+        (status = 403, description = "权限不足", body = AppErrorResponse),
+    ),
+    tag = "group"
+))]
+#[instrument(skip(state))]
+pub async fn speak_group_handler(
+    State(state): State<AppState>,
+    payload: JWTPayload,
+    Path(id): Path<Uuid>,
+    Query(params): Query<SpeakParams>,
+) -> Result<impl IntoResponse, AppError> {
+    let g = group::Model::from_uuid(id, &state.conn).await?;
+    let actor = Member::authorize(&g, payload.id, GroupAction::Mute, &state.conn).await?;
+    if let Some(enabled) = params.mute_all {
+        let mut settings = GroupSettings::from(&g);
+        if settings.mute_all != enabled {
+            settings.mute_all = enabled;
+            let mut active = g.clone().into_active_model();
+            active.settings = ActiveValue::set(serde_json::to_value(settings)?);
+            Group::update(active).exec(&state.conn).await?;
+            event!(
+                Level::INFO,
+                "user [{}] {} mute all in group [{id}]",
+                payload.id,
+                if enabled { "enable" } else { "disable" }
+            );
+            Message::send_system(
+                &state,
+                g.session,
+                SystemEvent::MuteAll {
+                    operator: payload.id,
+                    enabled,
+                },
+            )
+            .await?;
+        }
+    }
+    if let Some(member) = params.member {
+        let m = member::Model::from_group_and_user(g.id, member, &state.conn).await?;
+        check_outranks(&actor, &m)?;
+        let until = params
+            .duration
+            .filter(|d| *d > 0)
+            .map(|d| chrono::Utc::now().naive_utc() + chrono::Duration::seconds(i64::from(d)));
+        let mut m = m.into_active_model();
+        m.banned_until = ActiveValue::set(until);
+        Member::update(m).exec(&state.conn).await?;
+        event!(
+            Level::INFO,
+            "user [{}] ban [{member}] in group [{id}] until {until:?}",
+            payload.id
+        );
+        Message::send_system(
+            &state,
+            g.session,
+            SystemEvent::Ban {
+                operator: payload.id,
+                user: member,
+                until: until.map(|t| t.and_utc().timestamp_millis()),
+            },
+        )
+        .await?;
+    }
+    Ok(StatusCode::OK.into_response())
+}
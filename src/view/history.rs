@@ -64,7 +64,10 @@ impl History {
         let start = req.start.unwrap_or(0);
         let end = req.end.unwrap_or(50);
         if end <= start {
-            return Err(AppError::BadRequest("end leq start".to_string()));
+            return Err(AppError::BadRequest(
+                ErrorCode::InvalidRange,
+                "end leq start".to_string(),
+            ));
         }
         let condition = Condition::all()
             .add(message::Column::Notice.eq(req.notice.unwrap_or(false)))
@@ -102,7 +105,7 @@ impl History {
             )
             .filter(condition.clone())
             .order_by(message::Column::CreatedAt, sea_orm::Order::Desc)
-            .limit(Some((end - start) as u64))
+            .limit(Some(end - start))
             .all(conn)
             .await?
             .split_off(start as usize);
@@ -133,7 +136,7 @@ impl History {
             .await?;
         let c = conn.clone();
         if ack {
-            tokio::spawn(async move {
+            trace::spawn(async move {
                 Feed::ack_msgs(user, msg_uuids, &c).await.ok();
             });
         }
@@ -169,7 +172,7 @@ pub async fn get_history_handler(
 ) -> Result<Json<History>, AppError> {
     let history = History::find_by_session(params, &state.conn, session, payload.id).await?;
     let h = history.clone();
-    tokio::task::spawn(async move {
+    trace::spawn(async move {
         let mut reads_map: std::collections::HashMap<Uuid, Vec<ReadMsg>> =
             std::collections::HashMap::new();
         for msg in h.msgs {
@@ -0,0 +1,361 @@
+use super::group::GroupSettings;
+use super::*;
+use entity::{
+    group, invite, member,
+    prelude::{Invite, Member, Message},
+};
+use feed::{GroupUpdate, Notification};
+use message::SystemEvent;
+use role::{GroupAction, Role};
+use sea_orm::TransactionTrait;
+use sea_query::Expr;
+use utility::gen_random_code;
+
+/// 邀请码长度
+const INVITE_CODE_LEN: usize = 8;
+
+/// 新建邀请链接请求
+#[cfg_attr(feature = "dev", derive(ToSchema))]
+#[derive(Deserialize, Debug)]
+#[cfg_attr(test, derive(Serialize))]
+pub struct InvitePost {
+    /// 有效时长, 单位为秒, 为空表示永不过期
+    #[cfg(test)]
+    pub expire_after: Option<u32>,
+    #[cfg(not(test))]
+    expire_after: Option<u32>,
+    /// 最大使用次数, 为空表示不限制
+    #[cfg(test)]
+    pub max_uses: Option<i32>,
+    #[cfg(not(test))]
+    max_uses: Option<i32>,
+}
+
+/// 邀请链接
+#[cfg_attr(feature = "dev", derive(ToSchema))]
+#[derive(Serialize, Debug)]
+#[cfg_attr(test, derive(Deserialize))]
+pub struct InviteLink {
+    /// 邀请码, 客户端据此拼接分享链接
+    pub code: String,
+    /// 群聊唯一主键
+    pub group: Uuid,
+    /// 创建者 UUID
+    creator: Option<Uuid>,
+    /// 创建时间, UTC 毫秒时间戳
+    created_at: i64,
+    /// 过期时间, UTC 毫秒时间戳
+    expires_at: Option<i64>,
+    /// 最大使用次数
+    max_uses: Option<i32>,
+    /// 已使用次数
+    pub uses: i32,
+    /// 是否已撤销
+    pub revoked: bool,
+}
+
+impl From<invite::Model> for InviteLink {
+    fn from(i: invite::Model) -> Self {
+        Self {
+            code: i.code,
+            group: i.group,
+            creator: i.creator,
+            created_at: i.created_at.and_utc().timestamp_millis(),
+            expires_at: i.expires_at.map(|t| t.and_utc().timestamp_millis()),
+            max_uses: i.max_uses,
+            uses: i.uses,
+            revoked: i.revoked,
+        }
+    }
+}
+
+impl invite::Model {
+    async fn from_code(code: &str, conn: &DatabaseConnection) -> Result<Self, AppError> {
+        Invite::find()
+            .filter(invite::Column::Code.eq(code))
+            .one(conn)
+            .await?
+            .ok_or(AppError::NotFound(
+                ErrorCode::InviteNotFound,
+                format!("cannot find invite [{code}]"),
+            ))
+    }
+
+    fn validate(&self) -> Result<(), AppError> {
+        if self.revoked {
+            return Err(AppError::Forbidden(
+                ErrorCode::InviteRevoked,
+                format!("invite [{}] has been revoked", self.code),
+            ));
+        }
+        if self
+            .expires_at
+            .is_some_and(|t| t <= chrono::Utc::now().naive_utc())
+        {
+            return Err(AppError::Forbidden(
+                ErrorCode::InviteExpired,
+                format!("invite [{}] has expired", self.code),
+            ));
+        }
+        if self.max_uses.is_some_and(|m| self.uses >= m) {
+            return Err(AppError::Forbidden(
+                ErrorCode::InviteUsedUp,
+                format!("invite [{}] has been used up", self.code),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// 创建邀请链接
+///
+/// 与直接邀请的权限相同
+#[cfg_attr(feature = "dev",
+utoipa::path(
+    post,
+    path = "/invite/group/{id}",
+    params(("id" = Uuid, Path, description = "群聊的唯一主键")),
+    request_body = InvitePost,
+    responses(
+        (status = 201, description = "创建成功", body = InviteLink),
+        (status = 403, description = "没有邀请权限", body = AppErrorResponse),
+    ),
+    tag = "group"
+))]
+#[instrument(skip(state))]
+pub async fn create_invite_handler(
+    State(state): State<AppState>,
+    payload: JWTPayload,
+    Path(id): Path<Uuid>,
+    Json(req): Json<InvitePost>,
+) -> Result<Response, AppError> {
+    let g = group::Model::from_uuid(id, &state.conn).await?;
+    Member::authorize(&g, payload.id, GroupAction::Invite, &state.conn).await?;
+    if req.max_uses.is_some_and(|m| m <= 0) {
+        return Err(AppError::BadRequest(
+            ErrorCode::InvalidMaxUses,
+            "max uses must be positive".to_string(),
+        ));
+    }
+    let expires_at = req
+        .expire_after
+        .map(|s| chrono::Utc::now().naive_utc() + chrono::Duration::seconds(i64::from(s)));
+    let i = invite::ActiveModel {
+        id: ActiveValue::not_set(),
+        code: ActiveValue::set(gen_random_code(INVITE_CODE_LEN)),
+        group: ActiveValue::set(g.id),
+        creator: ActiveValue::set(Some(payload.id)),
+        created_at: ActiveValue::not_set(),
+        expires_at: ActiveValue::set(expires_at),
+        max_uses: ActiveValue::set(req.max_uses),
+        uses: ActiveValue::not_set(),
+        revoked: ActiveValue::not_set(),
+    };
+    let i = Invite::insert(i).exec_with_returning(&state.conn).await?;
+    event!(
+        Level::INFO,
+        "user [{}] create invite [{}] of group [{id}]",
+        payload.id,
+        i.code
+    );
+    Ok((StatusCode::CREATED, Json(InviteLink::from(i))).into_response())
+}
+
+/// 列出群聊的邀请链接
+///
+/// 只有管理员和群主可以查看
+#[cfg_attr(feature = "dev",
+utoipa::path(
+    get,
+    path = "/invite/group/{id}",
+    params(("id" = Uuid, Path, description = "群聊的唯一主键")),
+    responses(
+        (status = 200, description = "获取成功", body = Vec<InviteLink>),
+        (status = 403, description = "非管理员或群主", body = AppErrorResponse),
+    ),
+    tag = "group"
+))]
+#[instrument(skip(state))]
+pub async fn list_invite_handler(
+    State(state): State<AppState>,
+    payload: JWTPayload,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<InviteLink>>, AppError> {
+    let g = group::Model::from_uuid(id, &state.conn).await?;
+    Member::authorize(&g, payload.id, GroupAction::Approve, &state.conn).await?;
+    let invites = Invite::find()
+        .filter(invite::Column::Group.eq(g.id))
+        .order_by(invite::Column::CreatedAt, sea_orm::Order::Desc)
+        .all(&state.conn)
+        .await?;
+    Ok(Json(invites.into_iter().map(InviteLink::from).collect()))
+}
+
+/// 撤销邀请链接
+///
+/// 创建者, 管理员和群主可以撤销
+#[cfg_attr(feature = "dev",
+utoipa::path(
+    delete,
+    path = "/invite/{code}",
+    params(("code" = String, Path, description = "邀请码")),
+    responses(
+        (status = 204, description = "撤销成功"),
+        (status = 403, description = "没有撤销权限", body = AppErrorResponse),
+    ),
+    tag = "group"
+))]
+#[instrument(skip(state))]
+pub async fn revoke_invite_handler(
+    State(state): State<AppState>,
+    payload: JWTPayload,
+    Path(code): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let i = invite::Model::from_code(&code, &state.conn).await?;
+    if i.creator != Some(payload.id) {
+        let g = group::Model::from_uuid(i.group, &state.conn).await?;
+        Member::authorize(&g, payload.id, GroupAction::Approve, &state.conn).await?;
+    }
+    let mut i = i.into_active_model();
+    i.revoked = ActiveValue::set(true);
+    Invite::update(i).exec(&state.conn).await?;
+    event!(Level::INFO, "user [{}] revoke invite [{code}]", payload.id);
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// 通过邀请链接加入群聊的结果
+#[cfg_attr(feature = "dev", derive(ToSchema))]
+#[derive(Serialize, Debug)]
+#[cfg_attr(test, derive(Deserialize))]
+pub struct InviteJoin {
+    /// 群聊唯一主键
+    pub group: Uuid,
+    /// 是否等待审批
+    pub pending: bool,
+}
+
+/// 通过邀请链接加入群聊
+///
+/// 群聊设置 `auto_approve` 时直接加入, 否则进入待审批列表, 由管理员或群主通过 `/group/approve/{id}` 审批
+#[cfg_attr(feature = "dev",
+utoipa::path(
+    post,
+    path = "/invite/{code}",
+    params(("code" = String, Path, description = "邀请码")),
+    responses(
+        (status = 200, description = "已加入群聊", body = InviteJoin),
+        (status = 202, description = "已提交申请, 等待审批", body = InviteJoin),
+        (status = 403, description = "邀请码已失效", body = AppErrorResponse),
+        (status = 409, description = "已在群聊中或已提交申请", body = AppErrorResponse),
+    ),
+    tag = "group"
+))]
+#[instrument(skip(state))]
+pub async fn redeem_invite_handler(
+    State(state): State<AppState>,
+    payload: JWTPayload,
+    Path(code): Path<String>,
+) -> Result<Response, AppError> {
+    let user = payload.to_user(&state.conn).await?;
+    let i = invite::Model::from_code(&code, &state.conn).await?;
+    i.validate()?;
+    let g = group::Model::from_uuid(i.group, &state.conn).await?;
+    if let Some(m) = Member::find()
+        .filter(member::Column::Group.eq(g.id))
+        .filter(member::Column::User.eq(user.id))
+        .one(&state.conn)
+        .await?
+    {
+        return Err(if m.role() == Role::Pending {
+            AppError::Conflict(
+                ErrorCode::JoinPending,
+                format!("request to join group [{}] is pending", g.id),
+            )
+        } else {
+            AppError::Conflict(
+                ErrorCode::AlreadyMember,
+                format!("already in group [{}]", g.id),
+            )
+        });
+    }
+    // 使用次数与成员记录在同一事务中写入, 加入失败时不消耗邀请
+    let txn = state.conn.begin().await?;
+    let res = Invite::update_many()
+        .col_expr(invite::Column::Uses, Expr::col(invite::Column::Uses).add(1))
+        .filter(invite::Column::Id.eq(i.id))
+        .filter(
+            Condition::any()
+                .add(invite::Column::MaxUses.is_null())
+                .add(Expr::col(invite::Column::Uses).lt(Expr::col(invite::Column::MaxUses))),
+        )
+        .exec(&txn)
+        .await?;
+    if res.rows_affected == 0 {
+        return Err(AppError::Forbidden(
+            ErrorCode::InviteUsedUp,
+            format!("invite [{code}] has been used up"),
+        ));
+    }
+    let pending = !GroupSettings::from(&g).auto_approve;
+    let mut m = member::ActiveModel::from((g.id, user.id));
+    if pending {
+        m.permission = ActiveValue::set(Role::Pending.into());
+    }
+    Member::insert(m).exec(&txn).await?;
+    txn.commit().await?;
+    event!(
+        Level::INFO,
+        "user [{}] join group [{}] by invite [{code}]",
+        user.id,
+        g.id
+    );
+    if !pending {
+        Message::send_system(
+            &state,
+            g.session,
+            SystemEvent::Join {
+                operator: None,
+                user: user.id,
+            },
+        )
+        .await?;
+    }
+    let mut receivers = if pending {
+        Member::get_admins(g.id, &state.conn).await?
+    } else {
+        Vec::new()
+    };
+    let group = g.id;
+    let owner = g.owner;
+    trace::spawn(async move {
+        let items = vec![GroupUpdate {
+            group,
+            user: user.id,
+        }];
+        let notification = if pending {
+            receivers.push(owner);
+            Notification::GroupRequests { items }
+        } else {
+            receivers.push(user.id);
+            Notification::GroupAccepts { items }
+        };
+        for r in receivers {
+            state
+                .ws_pool
+                .notify(
+                    r,
+                    WebSocketMessage::Text(serde_json::to_string(&notification).unwrap()),
+                )
+                .await;
+        }
+    });
+    Ok((
+        if pending {
+            StatusCode::ACCEPTED
+        } else {
+            StatusCode::OK
+        },
+        Json(InviteJoin { group, pending }),
+    )
+        .into_response())
+}
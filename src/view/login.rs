@@ -1,18 +1,36 @@
 use super::*;
-use entity::{prelude::User, user};
-use utility::validate_passwd;
+use crate::jwt::ChallengePayload;
+use crate::limit::LOGIN_LIMIT;
+use axum::{
+    extract::{ConnectInfo, FromRequestParts},
+    http::{header, request::Parts},
+};
+use entity::{
+    login_audit,
+    prelude::{LoginAudit, User},
+    user,
+};
+use std::net::SocketAddr;
+use totp::TotpChallenge;
+use utility::{good_email, good_phone, normalize_email, validate_passwd, DELETION_GRACE};
 
 /// 登录请求体
 #[derive(Deserialize, Debug)]
-#[cfg_attr(test, derive(Serialize))]
+#[cfg_attr(test, derive(Serialize, Clone))]
 #[cfg_attr(feature = "dev", derive(ToSchema))]
 pub struct LoginRequest {
-    /// 用户名
+    /// 用户名, 邮箱或电话
+    ///
+    /// 符合邮箱格式时按邮箱匹配且不区分大小写, 符合电话格式时按电话匹配, 否则按用户名匹配
+    ///
+    /// 兼容旧字段名 `name`
     #[cfg_attr(feature = "dev", schema(example = "yangzheh"))]
+    #[serde(alias = "name")]
     #[cfg(test)]
-    pub name: String,
+    pub identifier: String,
+    #[serde(alias = "name")]
     #[cfg(not(test))]
-    name: String,
+    identifier: String,
     /// 密码
     #[cfg_attr(feature = "dev", schema(example = "123456"))]
     #[cfg(test)]
@@ -22,25 +40,125 @@ pub struct LoginRequest {
 }
 
 impl LoginRequest {
-    async fn validate(&self, conn: &DatabaseConnection) -> Result<JWTPayload, AppError> {
-        let user: Option<user::Model> = User::find()
-            .filter(user::Column::Name.eq(&self.name))
-            .one(conn)
-            .await?;
-        let user = user.ok_or(AppError::NotFound(format!(
-            "user not exist: [{}]",
-            &self.name
-        )))?;
-        if validate_passwd(&self.password, &user.salt, &user.hash)? {
-            event!(Level::INFO, "successfully validate user {:?}", user.name);
-            Ok(user.id.into())
+    /// 查找可以登录的用户, 超过保留期的注销账号视为不存在
+    async fn find_user(&self, conn: &DatabaseConnection) -> Result<Option<user::Model>, AppError> {
+        let email = normalize_email(&self.identifier);
+        let column = if good_email(&email) {
+            user::Column::EmailNormalized.eq(email)
+        } else if good_phone(&self.identifier) {
+            user::Column::Phone.eq(&self.identifier)
         } else {
-            event!(Level::INFO, "fail to validate user {:?}", user.name);
-            Err(AppError::Unauthorized("wrong password".to_string()))
+            user::Column::Name.eq(&self.identifier)
+        };
+        let user = User::find().filter(column).one(conn).await?;
+        Ok(user.filter(user::Model::restorable))
+    }
+}
+
+impl user::Model {
+    /// 账号未注销, 或仍在注销保留期内
+    pub(super) fn restorable(&self) -> bool {
+        self.deleted_at.is_none_or(|deleted_at| {
+            deleted_at >= user::Model::deletion_cutoff(*DELETION_GRACE.get().unwrap())
+        })
+    }
+
+    /// 恢复保留期内的注销账号
+    pub(super) async fn restore(self, conn: &DatabaseConnection) -> Result<Self, AppError> {
+        if self.deleted_at.is_none() {
+            return Ok(self);
         }
+        let mut user: user::ActiveModel = self.into();
+        user.deleted_at = ActiveValue::set(None);
+        let user = User::update(user).exec(conn).await?;
+        event!(Level::INFO, "restore deleted user [{}]", user.id);
+        Ok(user)
+    }
+}
+
+/// 客户端信息, 用于频率限制与登录审计
+#[derive(Debug)]
+pub(super) struct ClientInfo {
+    pub(super) ip: String,
+    user_agent: Option<String>,
+}
+
+#[axum::async_trait]
+impl<S> FromRequestParts<S> for ClientInfo
+where
+    S: Send + Sync,
+{
+    type Rejection = std::convert::Infallible;
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let forwarded = match LOGIN_LIMIT.get().unwrap().forwarded_for {
+            true => parts
+                .headers
+                .get("x-forwarded-for")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.split(',').next())
+                .map(|v| v.trim().to_string()),
+            false => None,
+        };
+        let ip = forwarded
+            .or_else(|| {
+                parts
+                    .extensions
+                    .get::<ConnectInfo<SocketAddr>>()
+                    .map(|c| c.0.ip().to_string())
+            })
+            .unwrap_or_else(|| "unknown".to_string());
+        let user_agent = parts
+            .headers
+            .get(header::USER_AGENT)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        Ok(Self { ip, user_agent })
+    }
+}
+
+impl ClientInfo {
+    /// 记录登录审计日志, `reason` 为空表示登录成功
+    pub(super) async fn audit(
+        &self,
+        name: &str,
+        user: Option<Uuid>,
+        reason: Option<&str>,
+        conn: &DatabaseConnection,
+    ) -> Result<(), AppError> {
+        let record = login_audit::ActiveModel {
+            id: ActiveValue::not_set(),
+            user: ActiveValue::set(user),
+            name: ActiveValue::set(name.to_string()),
+            ip: ActiveValue::set(self.ip.clone()),
+            user_agent: ActiveValue::set(self.user_agent.clone()),
+            success: ActiveValue::set(reason.is_none()),
+            reason: ActiveValue::set(reason.map(str::to_string)),
+            created_at: ActiveValue::not_set(),
+        };
+        LoginAudit::insert(record).exec(conn).await?;
+        Ok(())
+    }
+}
+
+/// 账号仍处于锁定状态时拒绝登录
+pub(super) fn check_locked(name: &str) -> Result<(), AppError> {
+    match LOGIN_LIMIT.get().unwrap().locked_for(name) {
+        Some(left) => Err(AppError::TooManyRequests(
+            ErrorCode::AccountLocked,
+            format!("account locked for [{}] seconds", left.as_secs() + 1),
+        )),
+        None => Ok(()),
     }
 }
 
+/// 统一的登录失败响应, 不区分用户不存在与密码错误
+pub(super) fn invalid_credentials() -> AppError {
+    AppError::Unauthorized(
+        ErrorCode::InvalidCredentials,
+        "invalid identifier or password".to_string(),
+    )
+}
+
 /// 登录响应体
 #[cfg_attr(feature = "dev", derive(ToSchema))]
 #[cfg_attr(test, derive(Deserialize))]
@@ -57,6 +175,13 @@ pub struct LoginResponse {
 }
 
 /// 登录
+///
+/// 可以使用用户名, 邮箱或电话登录
+///
+/// 开启两步验证的用户返回挑战令牌, 需要通过 `/login/totp` 完成登录,
+/// 此时账号的失败计数不会清除, 保留期内的注销账号也不会恢复
+///
+/// 同一 IP 的登录频率受限, 同一账号连续失败多次后将被临时锁定
 #[cfg_attr(feature = "dev",
 utoipa::path(
     post,
@@ -64,20 +189,90 @@ utoipa::path(
     request_body = LoginRequest,
     responses(
         (status = 200, description = "登录成功", body = LoginResponse),
-        (status = 401, description = "登录失败", body = AppErrorResponse, example = json!({"msg":"wrong password","ver": "0.1.1"})),
+        (status = 202, description = "需要两步验证", body = TotpChallenge),
+        (status = 429, description = "请求过于频繁或账号已锁定", body = AppErrorResponse),
+        (status = 401, description = "账号或密码错误", body = AppErrorResponse, example = json!({"code":"INVALID_CREDENTIALS","msg":"invalid identifier or password","details":{"reason":"invalid identifier or password"},"ver": "0.1.1"})),
     ),
     tag = "user"
 ))]
-#[instrument(skip(state))]
+#[instrument(skip(state, user))]
 pub async fn login_handler(
     State(state): State<AppState>,
+    client: ClientInfo,
     Json(user): Json<LoginRequest>,
 ) -> Result<Response, AppError> {
-    if user.name.is_empty() || user.password.is_empty() {
-        return Err(AppError::BadRequest("name or passwd is empty".to_string()));
+    if user.identifier.is_empty() || user.password.is_empty() {
+        return Err(AppError::BadRequest(
+            ErrorCode::CredentialsRequired,
+            "identifier or passwd is empty".to_string(),
+        ));
+    }
+    let limit = LOGIN_LIMIT.get().unwrap();
+    if limit.ip.hit(&client.ip) > limit.ip_limit {
+        event!(Level::WARN, "too many login attempts from [{}]", client.ip);
+        return Err(AppError::TooManyRequests(
+            ErrorCode::LoginRateLimited,
+            "too many login attempts".to_string(),
+        ));
     }
-    let payload = user.validate(&state.conn).await?;
-    event!(Level::INFO, "user login [{}]", user.name);
+    let model = user.find_user(&state.conn).await?;
+    // 锁定按账号计算, 避免通过切换用户名, 邮箱与电话绕过
+    let account = model
+        .as_ref()
+        .map_or(user.identifier.as_str(), |m| m.name.as_str())
+        .to_string();
+    if let Err(e) = check_locked(&account) {
+        client
+            .audit(
+                &user.identifier,
+                model.map(|m| m.id),
+                Some("locked"),
+                &state.conn,
+            )
+            .await?;
+        return Err(e);
+    }
+    let model = match model {
+        Some(m) if validate_passwd(&user.password, &m.salt, &m.hash)? => m,
+        other => {
+            let reason = match other {
+                Some(_) => "wrong_password",
+                None => "unknown_user",
+            };
+            event!(Level::INFO, "fail to validate user {:?}", user.identifier);
+            client
+                .audit(
+                    &user.identifier,
+                    other.map(|m| m.id),
+                    Some(reason),
+                    &state.conn,
+                )
+                .await?;
+            if limit.fail(&account) {
+                event!(Level::WARN, "lock account [{}]", account);
+            }
+            return Err(invalid_credentials());
+        }
+    };
+    // 开启两步验证时, 失败计数与注销恢复推迟到验证码通过之后
+    if model.totp_enabled() {
+        event!(Level::INFO, "user [{}] requires totp", account);
+        let challenge: ChallengePayload = model.id.into();
+        return Ok((
+            StatusCode::ACCEPTED,
+            Json(TotpChallenge {
+                challenge: challenge.into(),
+            }),
+        )
+            .into_response());
+    }
+    limit.succeed(&account);
+    let model = model.restore(&state.conn).await?;
+    client
+        .audit(&user.identifier, Some(model.id), None, &state.conn)
+        .await?;
+    event!(Level::INFO, "user login [{}]", account);
+    let payload: JWTPayload = model.id.into();
     Ok((
         StatusCode::OK,
         Json(LoginResponse {
@@ -87,6 +282,69 @@ pub async fn login_handler(
         .into_response())
 }
 
+/// 登录记录
+#[cfg_attr(feature = "dev", derive(ToSchema))]
+#[derive(Serialize, Debug)]
+#[cfg_attr(test, derive(Deserialize))]
+pub struct LoginRecord {
+    /// 客户端 IP
+    pub ip: String,
+    /// 客户端 User-Agent
+    pub user_agent: Option<String>,
+    /// 是否成功
+    pub success: bool,
+    /// 失败原因
+    ///
+    /// | 取值 | 说明 |
+    /// | --- | --- |
+    /// | `wrong_password` | 密码错误 |
+    /// | `wrong_totp` | 两步验证码错误 |
+    /// | `locked` | 账号已锁定 |
+    pub reason: Option<String>,
+    /// 登录时间
+    ///
+    /// UTC 毫秒时间戳
+    pub created_at: i64,
+}
+
+impl From<login_audit::Model> for LoginRecord {
+    fn from(r: login_audit::Model) -> Self {
+        Self {
+            ip: r.ip,
+            user_agent: r.user_agent,
+            success: r.success,
+            reason: r.reason,
+            created_at: r.created_at.and_utc().timestamp_millis(),
+        }
+    }
+}
+
+/// 获取最近的登录记录
+///
+/// 按时间倒序返回最近 50 条
+#[cfg_attr(feature = "dev",
+utoipa::path(
+    get,
+    path = "/user/logins",
+    responses(
+        (status = 200, description = "获取成功", body = Vec<LoginRecord>),
+    ),
+    tag = "user"
+))]
+#[instrument(skip(state))]
+pub async fn list_login_handler(
+    State(state): State<AppState>,
+    payload: JWTPayload,
+) -> Result<Json<Vec<LoginRecord>>, AppError> {
+    let records = LoginAudit::find()
+        .filter(login_audit::Column::User.eq(payload.id))
+        .order_by(login_audit::Column::CreatedAt, sea_orm::Order::Desc)
+        .limit(50)
+        .all(&state.conn)
+        .await?;
+    Ok(Json(records.into_iter().map(LoginRecord::from).collect()))
+}
+
 /// 登出
 ///
 /// 用于通知服务端注销 websocket
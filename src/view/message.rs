@@ -1,11 +1,13 @@
 use entity::{
     contact, feed, group, member, message,
-    prelude::{Contact, Feed, Member, Message},
+    prelude::{Block, Contact, Feed, Member, Message},
 };
 use utility::UUID_NIL;
 
 use super::feed::{FeedItem, Notification};
+use super::role::GroupAction;
 use super::*;
+use crate::metrics::METRICS;
 
 #[derive(Deserialize, Debug)]
 #[cfg_attr(feature = "dev", derive(ToSchema))]
@@ -18,6 +20,8 @@ pub struct MsgPost {
     /// | 0 | 文本 |
     /// | 1 | 图片 |
     /// | 2 | 文件 |
+    ///
+    /// 系统消息 `3` 只能由服务器生成
     #[cfg(test)]
     pub typ: i32,
     #[cfg(not(test))]
@@ -54,6 +58,12 @@ pub struct MsgPost {
 impl TryFrom<(MsgPost, Uuid, Uuid)> for message::ActiveModel {
     type Error = AppError;
     fn try_from(value: (MsgPost, Uuid, Uuid)) -> Result<Self, Self::Error> {
+        if value.0.typ == SYSTEM_MSG_TYP {
+            return Err(AppError::BadRequest(
+                ErrorCode::SystemMessage,
+                "cannot send system message".to_string(),
+            ));
+        }
         let file = value
             .0
             .file
@@ -67,7 +77,7 @@ impl TryFrom<(MsgPost, Uuid, Uuid)> for message::ActiveModel {
         let notice = value
             .0
             .notice
-            .map(|b| ActiveValue::set(b))
+            .map(ActiveValue::set)
             .unwrap_or(ActiveValue::not_set());
         let mut m = message::ActiveModel {
             id: ActiveValue::not_set(),
@@ -111,8 +121,13 @@ pub struct Msg {
     /// 引用消息的 UUID
     cite: Option<Uuid>,
     /// 消息类型
+    #[cfg(test)]
+    pub typ: i32,
+    #[cfg(not(test))]
     typ: i32,
     /// 消息内容
+    ///
+    /// 系统消息的内容为 JSON 序列化的 [`SystemEvent`]
     #[cfg(test)]
     pub content: Option<String>,
     #[cfg(not(test))]
@@ -138,10 +153,7 @@ impl From<(message::Model, Vec<ReadAt>)> for Msg {
         Msg {
             id: value.0.id,
             created_at: value.0.created_at.and_utc().timestamp_millis(),
-            edited_at: value
-                .0
-                .edited_at
-                .and_then(|t| Some(t.and_utc().timestamp_millis())),
+            edited_at: value.0.edited_at.map(|t| t.and_utc().timestamp_millis()),
             typ: value.0.typ,
             content: value.0.content,
             file: value.0.file,
@@ -166,6 +178,7 @@ pub struct MsgRes {
     /// | 0 | 文本 |
     /// | 1 | 图片 |
     /// | 2 | 文件 |
+    /// | 3 | 系统消息 |
     pub typ: i32,
     /// 消息 UUID
     pub id: Uuid,
@@ -183,6 +196,72 @@ impl From<message::Model> for MsgRes {
     }
 }
 
+/// 系统消息类型
+pub(super) const SYSTEM_MSG_TYP: i32 = 3;
+
+/// 系统消息内容
+///
+/// 由服务器在群聊中发送, 发送者为空
+#[cfg_attr(feature = "dev", derive(ToSchema))]
+#[derive(Serialize, Debug)]
+#[cfg_attr(test, derive(Deserialize))]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum SystemEvent {
+    /// 开启或关闭全员禁言
+    MuteAll { operator: Uuid, enabled: bool },
+    /// 禁言或解除禁言成员, `until` 为空表示解除, 否则为 UTC 毫秒时间戳
+    Ban {
+        operator: Uuid,
+        user: Uuid,
+        until: Option<i64>,
+    },
+    /// 邀请新成员, 被邀请者等待审批
+    Invite { operator: Uuid, user: Uuid },
+    /// 成员加入群聊, `operator` 为审批者, 通过邀请链接自动加入时为空
+    Join { operator: Option<Uuid>, user: Uuid },
+    /// 移除成员
+    Remove { operator: Uuid, user: Uuid },
+    /// 成员退出群聊
+    Exit { user: Uuid },
+    /// 转让群主
+    Transfer { operator: Uuid, user: Uuid },
+    /// 设置或取消管理员
+    Admin {
+        operator: Uuid,
+        user: Uuid,
+        granted: bool,
+    },
+}
+
+impl Message {
+    /// 在会话中发送系统消息, 并推送给会话成员
+    pub(super) async fn send_system(
+        state: &AppState,
+        session: Uuid,
+        event: SystemEvent,
+    ) -> Result<message::Model, AppError> {
+        let msg = message::ActiveModel {
+            id: ActiveValue::not_set(),
+            created_at: ActiveValue::not_set(),
+            edited_at: ActiveValue::not_set(),
+            sender: ActiveValue::set(None),
+            session: ActiveValue::set(session),
+            content: ActiveValue::set(Some(serde_json::to_string(&event)?)),
+            typ: ActiveValue::set(SYSTEM_MSG_TYP),
+            file: ActiveValue::not_set(),
+            cite: ActiveValue::not_set(),
+            fwd_von: ActiveValue::not_set(),
+            notice: ActiveValue::not_set(),
+        };
+        let msg = Message::insert(msg)
+            .exec_with_returning(&state.conn)
+            .await?;
+        trace::spawn(dispatch_msg(state.clone(), msg.clone(), false));
+        event!(Level::DEBUG, "new system message [{}]", msg.id);
+        Ok(msg)
+    }
+}
+
 #[derive(Debug, FromQueryResult)]
 pub(super) struct Reader {
     reader: Uuid,
@@ -235,10 +314,14 @@ pub async fn get_msg_handler(
     payload: JWTPayload,
     Path(id): Path<Uuid>,
 ) -> Result<Json<Msg>, AppError> {
-    let msg: message::Model = Message::find_by_id(id)
-        .one(&state.conn)
-        .await?
-        .ok_or(AppError::NotFound(format!("cannot find message [{}]", id)))?;
+    let msg: message::Model =
+        Message::find_by_id(id)
+            .one(&state.conn)
+            .await?
+            .ok_or(AppError::NotFound(
+                ErrorCode::MessageNotFound,
+                format!("cannot find message [{}]", id),
+            ))?;
     let read_ats: Vec<ReadAt> = Reader::fetch_from_db(id, &state.conn)
         .await?
         .into_iter()
@@ -271,10 +354,16 @@ pub async fn delete_msg_handler(
     let _ = Message::find_by_id(id)
         .one(&state.conn)
         .await?
-        .ok_or(AppError::NotFound(format!("cannot find message [{}]", id)))?;
+        .ok_or(AppError::NotFound(
+            ErrorCode::MessageNotFound,
+            format!("cannot find message [{}]", id),
+        ))?;
     let res = Message::delete_by_id(id).exec(&state.conn).await?;
     if res.rows_affected == 0 {
-        Err(AppError::NotFound(format!("cannot find message [{}]", id)))
+        Err(AppError::NotFound(
+            ErrorCode::MessageNotFound,
+            format!("cannot find message [{}]", id),
+        ))
     } else {
         event!(Level::DEBUG, "delete message [{}]", id);
         Ok(StatusCode::NO_CONTENT.into_response())
@@ -304,11 +393,30 @@ pub async fn send_msg_handler(
 ) -> Result<Json<MsgRes>, AppError> {
     let msg: message::ActiveModel = (msg, payload.id, session).try_into()?;
     let notice = msg.notice == ActiveValue::set(true);
-    if notice {
-        let g = group::Model::from_session(session, &state.conn).await?;
-        let is_admin = Member::is_admin(g.id, payload.id, &state.conn).await?;
-        if g.owner != payload.id && !is_admin {
-            return Err(AppError::Forbidden("cannot send notice message".into()));
+    if let Some(g) = group::Model::from_session(session, &state.conn).await? {
+        let m = member::Model::from_group_and_user(g.id, payload.id, &state.conn).await?;
+        m.check_speak(&g)?;
+        if notice {
+            Member::authorize(&g, payload.id, GroupAction::Notice, &state.conn).await?;
+        }
+    } else if notice {
+        return Err(AppError::NotFound(
+            ErrorCode::GroupNotFound,
+            format!("cannot find group with session [{session}]"),
+        ));
+    } else if let Some(c) = Contact::find()
+        .filter(contact::Column::Session.eq(session))
+        .filter(contact::Column::User.eq(payload.id))
+        .one(&state.conn)
+        .await?
+    {
+        if let Some(peer) = c.ref_user {
+            if Block::is_blocked(peer, payload.id, &state.conn).await? {
+                return Err(AppError::Forbidden(
+                    ErrorCode::BlockedByUser,
+                    format!("blocked by user [{peer}]"),
+                ));
+            }
         }
     }
     let res = Message::insert(msg).exec(&state.conn).await?;
@@ -316,109 +424,119 @@ pub async fn send_msg_handler(
         .one(&state.conn)
         .await?
         .ok_or(AppError::Server(anyhow::anyhow!("cannot store message")))?;
-    tokio::task::spawn(async move {
-        match Contact::find()
-            .filter(contact::Column::Session.eq(session))
-            .all(&state.conn)
-            .await
-        {
-            Ok(contacts) => {
-                for c in contacts {
-                    match Feed::insert(feed::ActiveModel::from((c.user, msg.id)))
-                        .exec(&state.conn)
+    trace::spawn(dispatch_msg(state, msg.clone(), notice));
+    event!(
+        Level::DEBUG,
+        "new message [{}] by user [{}]",
+        msg.id,
+        payload.id
+    );
+    Ok(Json(msg.into()))
+}
+
+/// 为会话中的用户生成消息记录, 并通过 WebSocket 推送
+pub(super) async fn dispatch_msg(state: AppState, msg: message::Model, notice: bool) {
+    METRICS.message(if msg.typ == SYSTEM_MSG_TYP {
+        "system"
+    } else if notice {
+        "notice"
+    } else {
+        "chat"
+    });
+    match Contact::find()
+        .filter(contact::Column::Session.eq(msg.session))
+        .all(&state.conn)
+        .await
+    {
+        Ok(contacts) => {
+            for c in contacts {
+                match Feed::insert(feed::ActiveModel::from((c.user, msg.id)))
+                    .exec(&state.conn)
+                    .await
+                {
+                    Ok(_) => {
+                        if let Ok(feed) = FeedItem::from_chat(
+                            c.ref_user.unwrap_or(*UUID_NIL),
+                            msg.session,
+                            c.user,
+                            &state.conn,
+                        )
                         .await
-                    {
-                        Ok(_) => {
-                            if let Ok(feed) = FeedItem::from_chat(
-                                c.ref_user.unwrap_or(*UUID_NIL),
-                                session,
-                                c.user,
-                                &state.conn,
-                            )
-                            .await
-                            {
-                                let notification = Notification::Chats { feeds: vec![feed] };
-                                state
-                                    .ws_pool
-                                    .notify(
-                                        c.user,
-                                        WebSocketMessage::Text(
-                                            serde_json::to_string(&notification).unwrap(),
-                                        ),
-                                    )
-                                    .await;
-                            }
-                        }
-                        Err(e) => {
-                            event!(Level::ERROR, "cannot store contact feed: {}", e);
+                        {
+                            let notification = Notification::Chats { feeds: vec![feed] };
+                            state
+                                .ws_pool
+                                .notify(
+                                    c.user,
+                                    WebSocketMessage::Text(
+                                        serde_json::to_string(&notification).unwrap(),
+                                    ),
+                                )
+                                .await;
                         }
                     }
+                    Err(e) => {
+                        event!(Level::ERROR, "cannot store contact feed: {}", e);
+                    }
                 }
             }
-            Err(e) => {
-                event!(Level::ERROR, "cannot find contacts: {}", e);
-            }
         }
-        match Member::find()
-            .join_rev(
-                JoinType::InnerJoin,
-                group::Entity::belongs_to(member::Entity)
-                    .from(group::Column::Id)
-                    .to(member::Column::Group)
-                    .into(),
-            )
-            .filter(group::Column::Session.eq(session))
-            .all(&state.conn)
-            .await
-        {
-            Ok(mut groups) => {
-                groups.sort_by_key(|g| g.user);
-                groups.dedup_by_key(|g| g.user);
-                for g in groups {
-                    match Feed::insert(feed::ActiveModel::from((g.user, msg.id)))
-                        .exec(&state.conn)
-                        .await
-                    {
-                        Ok(_) => {
-                            if let Ok(feed) = if notice {
-                                FeedItem::from_notice(g.group, session, g.user, &state.conn).await
+        Err(e) => {
+            event!(Level::ERROR, "cannot find contacts: {}", e);
+        }
+    }
+    match Member::find()
+        .join_rev(
+            JoinType::InnerJoin,
+            group::Entity::belongs_to(member::Entity)
+                .from(group::Column::Id)
+                .to(member::Column::Group)
+                .into(),
+        )
+        .filter(group::Column::Session.eq(msg.session))
+        .all(&state.conn)
+        .await
+    {
+        Ok(mut groups) => {
+            groups.sort_by_key(|g| g.user);
+            groups.dedup_by_key(|g| g.user);
+            for g in groups {
+                match Feed::insert(feed::ActiveModel::from((g.user, msg.id)))
+                    .exec(&state.conn)
+                    .await
+                {
+                    Ok(_) => {
+                        if let Ok(feed) = if notice {
+                            FeedItem::from_notice(g.group, msg.session, g.user, &state.conn).await
+                        } else {
+                            FeedItem::from_group(g.group, msg.session, g.user, &state.conn).await
+                        } {
+                            let notification = if notice {
+                                Notification::Notices { feeds: vec![feed] }
                             } else {
-                                FeedItem::from_group(g.group, session, g.user, &state.conn).await
-                            } {
-                                let notification = if notice {
-                                    Notification::Notices { feeds: vec![feed] }
-                                } else {
-                                    Notification::Groups { feeds: vec![feed] }
-                                };
-                                state
-                                    .ws_pool
-                                    .notify(
-                                        g.user,
-                                        WebSocketMessage::Text(
-                                            serde_json::to_string(&notification).unwrap(),
-                                        ),
-                                    )
-                                    .await;
-                            }
-                        }
-                        Err(e) => {
-                            event!(Level::ERROR, "cannot store group feed: {}", e);
+                                Notification::Groups { feeds: vec![feed] }
+                            };
+                            state
+                                .ws_pool
+                                .notify(
+                                    g.user,
+                                    WebSocketMessage::Text(
+                                        serde_json::to_string(&notification).unwrap(),
+                                    ),
+                                )
+                                .await;
                         }
                     }
+                    Err(e) => {
+                        event!(Level::ERROR, "cannot store group feed: {}", e);
+                    }
                 }
             }
-            Err(e) => {
-                event!(Level::ERROR, "cannot find group users: {}", e);
-            }
         }
-    });
-    event!(
-        Level::DEBUG,
-        "new message [{}] by user [{}]",
-        msg.id,
-        payload.id
-    );
-    Ok(Json(msg.into()))
+        Err(e) => {
+            event!(Level::ERROR, "cannot find group users: {}", e);
+        }
+    }
 }
 
 impl From<(Uuid, Uuid)> for feed::ActiveModel {
@@ -428,6 +546,7 @@ impl From<(Uuid, Uuid)> for feed::ActiveModel {
             user: ActiveValue::set(value.0),
             message: ActiveValue::set(value.1),
             read_at: ActiveValue::not_set(),
+            confirmed_at: ActiveValue::not_set(),
         }
     }
 }
@@ -456,7 +575,10 @@ pub async fn mask_msg_handler(
         .filter(feed::Column::User.eq(payload.id))
         .one(&state.conn)
         .await?
-        .ok_or(AppError::NotFound(format!("cannot find message [{}]", msg)))?;
+        .ok_or(AppError::NotFound(
+            ErrorCode::MessageNotFound,
+            format!("cannot find message [{}]", msg),
+        ))?;
     let res = Feed::delete_by_id(msg.id).exec(&state.conn).await?;
     if res.rows_affected == 0 {
         return Err(AppError::Server(anyhow::anyhow!(
@@ -0,0 +1,151 @@
+use super::*;
+use crate::metrics::{Gauges, METRICS};
+use axum::http::header;
+use migration::MigratorTrait;
+use std::sync::atomic::Ordering;
+use utility::{SHUTTING_DOWN, UPLOAD_DIR};
+
+/// 单项检查结果
+#[cfg_attr(feature = "dev", derive(ToSchema))]
+#[derive(Serialize)]
+#[cfg_attr(test, derive(Deserialize))]
+pub struct Check {
+    /// 是否通过
+    pub ok: bool,
+    /// 未通过时的原因
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl<E: std::fmt::Display> From<Result<(), E>> for Check {
+    fn from(r: Result<(), E>) -> Self {
+        match r {
+            Ok(()) => Self {
+                ok: true,
+                detail: None,
+            },
+            Err(e) => Self {
+                ok: false,
+                detail: Some(e.to_string()),
+            },
+        }
+    }
+}
+
+/// 就绪检查结果
+#[cfg_attr(feature = "dev", derive(ToSchema))]
+#[derive(Serialize)]
+#[cfg_attr(test, derive(Deserialize))]
+pub struct Readiness {
+    /// 所有检查均通过
+    pub ready: bool,
+    /// 数据库可以连接
+    pub database: Check,
+    /// 上传目录可写
+    pub upload_dir: Check,
+    /// 数据库迁移已全部应用
+    pub migrations: Check,
+    /// 服务没有在停止中
+    pub serving: Check,
+}
+
+/// 存活检查
+///
+/// 进程可以处理请求即返回成功, 不检查依赖
+#[cfg_attr(feature = "dev",
+utoipa::path(
+    get,
+    path = "/healthz",
+    responses(
+        (status = 200, description = "进程存活"),
+    ),
+    tag = "monitor"
+))]
+pub async fn health_handler() -> impl IntoResponse {
+    Json(serde_json::json!({ "alive": true }))
+}
+
+async fn check_upload_dir() -> std::io::Result<()> {
+    // 每次检查使用不同的文件, 避免并发的检查互相删除对方的文件
+    let path =
+        std::path::Path::new(UPLOAD_DIR.get().unwrap()).join(format!(".readyz-{}", Uuid::new_v4()));
+    tokio::fs::write(&path, b"").await?;
+    tokio::fs::remove_file(&path).await
+}
+
+async fn check_migrations(conn: &DatabaseConnection) -> Result<(), String> {
+    let pending = migration::Migrator::get_pending_migrations(conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    match pending.len() {
+        0 => Ok(()),
+        n => Err(format!("{n} pending migrations")),
+    }
+}
+
+/// 就绪检查
+///
+/// 检查数据库连接, 上传目录与数据库迁移; 收到停止信号后总是返回失败,
+/// 以便负载均衡在服务退出前摘除流量
+#[cfg_attr(feature = "dev",
+utoipa::path(
+    get,
+    path = "/readyz",
+    responses(
+        (status = 200, description = "服务就绪", body = Readiness),
+        (status = 503, description = "服务未就绪", body = Readiness),
+    ),
+    tag = "monitor"
+))]
+#[instrument(skip(state))]
+pub async fn ready_handler(State(state): State<AppState>) -> impl IntoResponse {
+    let database: Check = state.conn.ping().await.into();
+    let upload_dir: Check = check_upload_dir().await.into();
+    let migrations: Check = if database.ok {
+        check_migrations(&state.conn).await.into()
+    } else {
+        Err("database unavailable").into()
+    };
+    let serving: Check = if SHUTTING_DOWN.load(Ordering::Relaxed) {
+        Err("shutting down").into()
+    } else {
+        Ok::<(), &str>(()).into()
+    };
+    let ready = database.ok && upload_dir.ok && migrations.ok && serving.ok;
+    if !ready {
+        event!(Level::WARN, "not ready");
+    }
+    (
+        if ready {
+            StatusCode::OK
+        } else {
+            StatusCode::SERVICE_UNAVAILABLE
+        },
+        Json(Readiness {
+            ready,
+            database,
+            upload_dir,
+            migrations,
+            serving,
+        }),
+    )
+}
+
+/// Prometheus 指标
+///
+/// 文本格式见 [Prometheus exposition formats](https://prometheus.io/docs/instrumenting/exposition_formats/)
+#[instrument(skip(state))]
+pub async fn metrics_handler(State(state): State<AppState>) -> Response {
+    let pool = state.conn.get_postgres_connection_pool();
+    let gauges = Gauges {
+        websockets: state.ws_pool.connections(),
+        db_connections: pool.size(),
+        db_idle: pool.num_idle(),
+        db_max: pool.options().get_max_connections(),
+    };
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        METRICS.render(&gauges),
+    )
+        .into_response()
+}
@@ -0,0 +1,463 @@
+use super::message::dispatch_msg;
+use super::*;
+use entity::{
+    announcement, feed, group, member, message,
+    prelude::{Announcement, Feed, Member, Message, Upload},
+};
+use role::{GroupAction, Role};
+
+/// 新建群公告请求
+#[cfg_attr(feature = "dev", derive(ToSchema))]
+#[derive(Deserialize, Debug)]
+#[cfg_attr(test, derive(Serialize))]
+pub struct NoticePost {
+    /// 标题
+    #[cfg(test)]
+    pub title: String,
+    #[cfg(not(test))]
+    title: String,
+    /// 正文
+    #[cfg(test)]
+    pub content: String,
+    #[cfg(not(test))]
+    content: String,
+    /// 附件 UUID, 需要先上传
+    #[cfg(test)]
+    pub file: Option<Uuid>,
+    #[cfg(not(test))]
+    file: Option<Uuid>,
+}
+
+/// 群公告修改请求
+///
+/// 不提供该字段表示不进行修改
+#[cfg_attr(feature = "dev", derive(ToSchema))]
+#[derive(Deserialize, Debug)]
+#[cfg_attr(test, derive(Serialize))]
+pub struct NoticeEdition {
+    /// 标题
+    #[cfg(test)]
+    pub title: Option<String>,
+    #[cfg(not(test))]
+    title: Option<String>,
+    /// 正文
+    #[cfg(test)]
+    pub content: Option<String>,
+    #[cfg(not(test))]
+    content: Option<String>,
+    /// 附件 UUID, 需要先上传, 提供空 UUID 表示移除附件
+    #[cfg(test)]
+    pub file: Option<Uuid>,
+    #[cfg(not(test))]
+    file: Option<Uuid>,
+}
+
+/// 群公告
+#[cfg_attr(feature = "dev", derive(ToSchema))]
+#[derive(Serialize, Debug)]
+#[cfg_attr(test, derive(Deserialize))]
+pub struct Notice {
+    /// 公告的消息 UUID
+    pub id: Uuid,
+    /// 标题, 通过 `/msg/session/{id}` 发送的公告没有标题
+    pub title: Option<String>,
+    /// 正文
+    pub content: Option<String>,
+    /// 附件 UUID
+    pub file: Option<Uuid>,
+    /// 发布者 UUID
+    author: Option<Uuid>,
+    /// 发布时间, UTC 毫秒时间戳
+    created_at: i64,
+    /// 修改时间, UTC 毫秒时间戳
+    edited_at: Option<i64>,
+    /// 当前用户是否已确认阅读
+    pub confirmed: bool,
+}
+
+/// 群公告的确认阅读情况
+#[cfg_attr(feature = "dev", derive(ToSchema))]
+#[derive(Serialize, Debug)]
+#[cfg_attr(test, derive(Deserialize))]
+pub struct NoticeReads {
+    /// 已确认阅读的成员
+    pub confirmed: Vec<Uuid>,
+    /// 未确认阅读的成员
+    pub unconfirmed: Vec<Uuid>,
+}
+
+impl Notice {
+    async fn from_model(
+        msg: message::Model,
+        title: Option<announcement::Model>,
+        user: Uuid,
+        conn: &DatabaseConnection,
+    ) -> Result<Self, AppError> {
+        let confirmed = Feed::find()
+            .filter(feed::Column::Message.eq(msg.id))
+            .filter(feed::Column::User.eq(user))
+            .filter(feed::Column::ConfirmedAt.is_not_null())
+            .one(conn)
+            .await?
+            .is_some();
+        Ok(Self {
+            id: msg.id,
+            title: title.map(|t| t.title),
+            content: msg.content,
+            file: msg.file,
+            author: msg.sender,
+            created_at: msg.created_at.and_utc().timestamp_millis(),
+            edited_at: msg.edited_at.map(|t| t.and_utc().timestamp_millis()),
+            confirmed,
+        })
+    }
+
+    /// 群聊最新的公告
+    pub(super) async fn latest(
+        g: &group::Model,
+        user: Uuid,
+        conn: &DatabaseConnection,
+    ) -> Result<Option<Self>, AppError> {
+        let notice = Message::find()
+            .filter(message::Column::Session.eq(g.session))
+            .filter(message::Column::Notice.eq(true))
+            .order_by(message::Column::CreatedAt, sea_orm::Order::Desc)
+            .find_also_related(Announcement)
+            .one(conn)
+            .await?;
+        Ok(match notice {
+            Some((msg, title)) => Some(Self::from_model(msg, title, user, conn).await?),
+            None => None,
+        })
+    }
+}
+
+impl message::Model {
+    /// 查找公告及其所属群聊
+    async fn notice_from_uuid(
+        id: Uuid,
+        conn: &DatabaseConnection,
+    ) -> Result<(Self, group::Model), AppError> {
+        let msg = Message::find_by_id(id)
+            .filter(message::Column::Notice.eq(true))
+            .one(conn)
+            .await?
+            .ok_or(AppError::NotFound(
+                ErrorCode::NoticeNotFound,
+                format!("cannot find notice [{id}]"),
+            ))?;
+        let g = group::Model::from_session(msg.session, conn)
+            .await?
+            .ok_or(AppError::NotFound(
+                ErrorCode::GroupNotFound,
+                format!("cannot find group with session [{}]", msg.session),
+            ))?;
+        Ok((msg, g))
+    }
+}
+
+/// 检查用户是否为群聊的正式成员
+async fn check_member(
+    g: &group::Model,
+    user: Uuid,
+    conn: &DatabaseConnection,
+) -> Result<(), AppError> {
+    let m = member::Model::from_group_and_user(g.id, user, conn).await?;
+    if m.role() == Role::Pending {
+        return Err(AppError::Forbidden(
+            ErrorCode::PendingMember,
+            format!("pending member cannot view notices of group [{}]", g.id),
+        ));
+    }
+    Ok(())
+}
+
+async fn check_file(file: Option<Uuid>, conn: &DatabaseConnection) -> Result<(), AppError> {
+    if let Some(file) = file.filter(|f| !f.is_nil()) {
+        Upload::find_by_id(file)
+            .one(conn)
+            .await?
+            .ok_or(AppError::NotFound(
+                ErrorCode::FileNotFound,
+                format!("cannot find file [{file}]"),
+            ))?;
+    }
+    Ok(())
+}
+
+/// 发布群公告
+///
+/// 所需角色由权限设置 `notice` 决定, 群成员会收到 `Notices` 通知
+#[cfg_attr(feature = "dev",
+utoipa::path(
+    post,
+    path = "/notice/group/{id}",
+    params(("id" = Uuid, Path, description = "群聊的唯一主键")),
+    request_body = NoticePost,
+    responses(
+        (status = 201, description = "发布成功", body = Notice),
+        (status = 403, description = "权限不足", body = AppErrorResponse),
+    ),
+    tag = "group"
+))]
+#[instrument(skip(state))]
+pub async fn create_notice_handler(
+    State(state): State<AppState>,
+    payload: JWTPayload,
+    Path(id): Path<Uuid>,
+    Json(req): Json<NoticePost>,
+) -> Result<Response, AppError> {
+    let g = group::Model::from_uuid(id, &state.conn).await?;
+    Member::authorize(&g, payload.id, GroupAction::Notice, &state.conn).await?;
+    if req.title.is_empty() {
+        return Err(AppError::BadRequest(
+            ErrorCode::EmptyTitle,
+            "title cannot be empty".to_string(),
+        ));
+    }
+    check_file(req.file, &state.conn).await?;
+    let file = req.file.filter(|f| !f.is_nil());
+    let msg = message::ActiveModel {
+        id: ActiveValue::not_set(),
+        created_at: ActiveValue::not_set(),
+        edited_at: ActiveValue::not_set(),
+        sender: ActiveValue::set(Some(payload.id)),
+        session: ActiveValue::set(g.session),
+        content: ActiveValue::set(Some(req.content)),
+        typ: ActiveValue::set(if file.is_some() { 2 } else { 0 }),
+        file: ActiveValue::set(file),
+        cite: ActiveValue::not_set(),
+        fwd_von: ActiveValue::not_set(),
+        notice: ActiveValue::set(true),
+    };
+    let msg = Message::insert(msg)
+        .exec_with_returning(&state.conn)
+        .await?;
+    let title = announcement::ActiveModel {
+        message: ActiveValue::set(msg.id),
+        title: ActiveValue::set(req.title),
+    };
+    let title = Announcement::insert(title)
+        .exec_with_returning(&state.conn)
+        .await?;
+    event!(
+        Level::INFO,
+        "user [{}] create notice [{}] in group [{id}]",
+        payload.id,
+        msg.id
+    );
+    trace::spawn(dispatch_msg(state.clone(), msg.clone(), true));
+    let notice = Notice::from_model(msg, Some(title), payload.id, &state.conn).await?;
+    Ok((StatusCode::CREATED, Json(notice)).into_response())
+}
+
+/// 列出群公告
+///
+/// 按发布时间倒序排列
+#[cfg_attr(feature = "dev",
+utoipa::path(
+    get,
+    path = "/notice/group/{id}",
+    params(("id" = Uuid, Path, description = "群聊的唯一主键")),
+    responses(
+        (status = 200, description = "获取成功", body = Vec<Notice>),
+    ),
+    tag = "group"
+))]
+#[instrument(skip(state))]
+pub async fn list_notice_handler(
+    State(state): State<AppState>,
+    payload: JWTPayload,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<Notice>>, AppError> {
+    let g = group::Model::from_uuid(id, &state.conn).await?;
+    check_member(&g, payload.id, &state.conn).await?;
+    let notices = Message::find()
+        .filter(message::Column::Session.eq(g.session))
+        .filter(message::Column::Notice.eq(true))
+        .order_by(message::Column::CreatedAt, sea_orm::Order::Desc)
+        .find_also_related(Announcement)
+        .all(&state.conn)
+        .await?;
+    let mut res = Vec::new();
+    for (msg, title) in notices {
+        res.push(Notice::from_model(msg, title, payload.id, &state.conn).await?);
+    }
+    Ok(Json(res))
+}
+
+/// 修改群公告
+///
+/// 所需角色由权限设置 `notice` 决定
+#[cfg_attr(feature = "dev",
+utoipa::path(
+    put,
+    path = "/notice/{id}",
+    params(("id" = Uuid, Path, description = "公告的消息 UUID")),
+    request_body = NoticeEdition,
+    responses(
+        (status = 200, description = "修改成功", body = Notice),
+        (status = 403, description = "权限不足", body = AppErrorResponse),
+    ),
+    tag = "group"
+))]
+#[instrument(skip(state))]
+pub async fn edit_notice_handler(
+    State(state): State<AppState>,
+    payload: JWTPayload,
+    Path(id): Path<Uuid>,
+    Json(edition): Json<NoticeEdition>,
+) -> Result<Json<Notice>, AppError> {
+    let (msg, g) = message::Model::notice_from_uuid(id, &state.conn).await?;
+    Member::authorize(&g, payload.id, GroupAction::Notice, &state.conn).await?;
+    check_file(edition.file, &state.conn).await?;
+    if let Some(title) = edition.title {
+        if title.is_empty() {
+            return Err(AppError::BadRequest(
+                ErrorCode::EmptyTitle,
+                "title cannot be empty".to_string(),
+            ));
+        }
+        let t = announcement::ActiveModel {
+            message: ActiveValue::set(id),
+            title: ActiveValue::set(title),
+        };
+        Announcement::insert(t)
+            .on_conflict(
+                sea_query::OnConflict::column(announcement::Column::Message)
+                    .update_column(announcement::Column::Title)
+                    .to_owned(),
+            )
+            .exec(&state.conn)
+            .await?;
+    }
+    let mut msg = msg.into_active_model();
+    if let Some(content) = edition.content {
+        msg.content = ActiveValue::set(Some(content));
+    }
+    if let Some(file) = edition.file {
+        // 移除附件后恢复为文本公告
+        let file = Some(file).filter(|f| !f.is_nil());
+        msg.typ = ActiveValue::set(if file.is_some() { 2 } else { 0 });
+        msg.file = ActiveValue::set(file);
+    }
+    msg.edited_at = ActiveValue::set(Some(chrono::Utc::now().naive_utc()));
+    let msg = Message::update(msg).exec(&state.conn).await?;
+    event!(Level::INFO, "user [{}] edit notice [{id}]", payload.id);
+    let title = Announcement::find_by_id(id).one(&state.conn).await?;
+    Ok(Json(
+        Notice::from_model(msg, title, payload.id, &state.conn).await?,
+    ))
+}
+
+/// 删除群公告
+///
+/// 所需角色由权限设置 `notice` 决定
+#[cfg_attr(feature = "dev",
+utoipa::path(
+    delete,
+    path = "/notice/{id}",
+    params(("id" = Uuid, Path, description = "公告的消息 UUID")),
+    responses(
+        (status = 204, description = "删除成功"),
+        (status = 403, description = "权限不足", body = AppErrorResponse),
+    ),
+    tag = "group"
+))]
+#[instrument(skip(state))]
+pub async fn delete_notice_handler(
+    State(state): State<AppState>,
+    payload: JWTPayload,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    let (_, g) = message::Model::notice_from_uuid(id, &state.conn).await?;
+    Member::authorize(&g, payload.id, GroupAction::Notice, &state.conn).await?;
+    Message::delete_by_id(id).exec(&state.conn).await?;
+    event!(Level::INFO, "user [{}] delete notice [{id}]", payload.id);
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// 确认阅读群公告
+#[cfg_attr(feature = "dev",
+utoipa::path(
+    put,
+    path = "/notice/confirm/{id}",
+    params(("id" = Uuid, Path, description = "公告的消息 UUID")),
+    responses(
+        (status = 200, description = "确认成功"),
+    ),
+    tag = "group"
+))]
+#[instrument(skip(state))]
+pub async fn confirm_notice_handler(
+    State(state): State<AppState>,
+    payload: JWTPayload,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    let (_, g) = message::Model::notice_from_uuid(id, &state.conn).await?;
+    check_member(&g, payload.id, &state.conn).await?;
+    let now = chrono::Utc::now().naive_utc();
+    let res = Feed::update_many()
+        .col_expr(feed::Column::ConfirmedAt, now.into())
+        .col_expr(
+            feed::Column::ReadAt,
+            sea_query::Func::coalesce([
+                sea_query::Expr::col(feed::Column::ReadAt).into(),
+                now.into(),
+            ])
+            .into(),
+        )
+        .filter(feed::Column::User.eq(payload.id))
+        .filter(feed::Column::Message.eq(id))
+        .exec(&state.conn)
+        .await?;
+    if res.rows_affected == 0 {
+        // 公告发布后才加入群聊的成员没有消息记录
+        let mut f = feed::ActiveModel::from((payload.id, id));
+        f.read_at = ActiveValue::set(Some(now));
+        f.confirmed_at = ActiveValue::set(Some(now));
+        Feed::insert(f).exec(&state.conn).await?;
+    }
+    event!(Level::DEBUG, "user [{}] confirm notice [{id}]", payload.id);
+    Ok(StatusCode::OK.into_response())
+}
+
+/// 查看群公告的确认阅读情况
+///
+/// 所需角色由权限设置 `notice` 决定
+#[cfg_attr(feature = "dev",
+utoipa::path(
+    get,
+    path = "/notice/reads/{id}",
+    params(("id" = Uuid, Path, description = "公告的消息 UUID")),
+    responses(
+        (status = 200, description = "获取成功", body = NoticeReads),
+        (status = 403, description = "权限不足", body = AppErrorResponse),
+    ),
+    tag = "group"
+))]
+#[instrument(skip(state))]
+pub async fn reads_notice_handler(
+    State(state): State<AppState>,
+    payload: JWTPayload,
+    Path(id): Path<Uuid>,
+) -> Result<Json<NoticeReads>, AppError> {
+    let (_, g) = message::Model::notice_from_uuid(id, &state.conn).await?;
+    Member::authorize(&g, payload.id, GroupAction::Notice, &state.conn).await?;
+    let confirmed: Vec<Uuid> = Feed::find()
+        .filter(feed::Column::Message.eq(id))
+        .filter(feed::Column::ConfirmedAt.is_not_null())
+        .all(&state.conn)
+        .await?
+        .into_iter()
+        .map(|f| f.user)
+        .collect();
+    let (confirmed, unconfirmed) = Member::get_members(g.id, &state.conn)
+        .await?
+        .into_iter()
+        .partition(|m| confirmed.contains(m));
+    Ok(Json(NoticeReads {
+        confirmed,
+        unconfirmed,
+    }))
+}
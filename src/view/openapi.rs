@@ -7,36 +7,67 @@ use utoipa::OpenApi;
 #[openapi(
     paths(
         login::login_handler, login::renew_handler, login::logout_handler,
+        login::list_login_handler,
+        totp::login_totp_handler, totp::enroll_totp_handler, totp::confirm_totp_handler,
+        totp::disable_totp_handler, totp::regenerate_recovery_handler,
         user::register_handler,
+        email::request_verify_handler, email::confirm_verify_handler,
+        email::request_reset_handler, email::confirm_reset_handler,
         user::get_profile_handler, user::update_profile_handler,
-        user::delete_user_handler,
-        user::find_user_handler,
+        user::delete_user_handler, export::export_user_handler,
+        user::find_user_handler, search::search_user_handler,
+        privacy::get_privacy_handler, privacy::update_privacy_handler,
         contact::add_contact_handler, contact::get_contacts_handler,
         contact::get_pending_contacts_handler, contact::get_new_contacts_handler,
         contact::delete_contact_handler, contact::get_categories_handler,
         contact::accept_contact_handler, contact::reject_contact_handler,
-        contact::edit_contact_handler,
+        contact::edit_contact_handler, contact::block_contact_handler,
+        contact::unblock_contact_handler, contact::get_blocks_handler,
+        category::create_category_handler, category::list_category_handler,
+        category::rename_category_handler, category::delete_category_handler,
+        category::order_category_handler, category::assign_category_handler,
         message::send_msg_handler, message::get_msg_handler,
         message::delete_msg_handler, message::mask_msg_handler,
+        pin::pin_msg_handler, pin::unpin_msg_handler, pin::list_pin_handler,
         group::get_group_handler, group::create_group_handler,
         group::delete_group_handler, group::list_group_handler,
         group::manage_group_handler, group::exit_group_handler,
         group::pin_group_handler, group::invite_group_handler,
         group::approve_group_handler, group::monitor_group_handler,
+        group::edit_group_handler,
+        group::speak_group_handler,
+        invite::create_invite_handler, invite::list_invite_handler,
+        invite::revoke_invite_handler, invite::redeem_invite_handler,
+        notice::create_notice_handler, notice::list_notice_handler,
+        notice::edit_notice_handler, notice::delete_notice_handler,
+        notice::confirm_notice_handler, notice::reads_notice_handler,
         history::get_history_handler,
         avatar::upload_handler, avatar::upload_avatar_handler,
+        avatar::get_usage_handler,
         download::download_handler,
+        monitor::health_handler, monitor::ready_handler,
     ),
     components(
         schemas(
-            error::AppErrorResponse,
+            error::AppErrorResponse, error::ErrorCode,
+            monitor::Readiness, monitor::Check,
             user::RegisterProfile, user::UserList,
             user::UserProfile, user::UserProfileEdition,
-            login::LoginRequest, login::LoginResponse,
-            download::Resource, avatar::UploadRes,
-            contact::ContactList, contact::Chat,
+            privacy::PrivacySettings, privacy::Visibility, privacy::RequestPolicy,
+            search::SearchResult, search::SearchItem, search::Relation,
+            login::LoginRequest, login::LoginResponse, login::LoginRecord,
+            totp::TotpEnrollment, totp::RecoveryCodes, totp::TotpCode,
+            totp::PasswordConfirm, totp::TotpLogin, totp::TotpChallenge,
+            email::TokenPost, email::ResetRequest, email::ResetConfirm,
+            download::Resource, avatar::UploadRes, avatar::UploadUsage,
+            contact::ContactList, contact::Chat, contact::ContactSource,
+            contact::ContactGroup, category::CategoryPost, category::CategoryItem,
             message::MsgPost, message::MsgRes, message::Msg, message::ReadAt,
-            group::GroupPost, group::GroupProfile, 
+            group::GroupPost, group::GroupProfile,
+            group::GroupEdition, group::GroupSettings, role::Role, role::PermissionMatrix,
+            message::SystemEvent,
+            invite::InvitePost, invite::InviteLink, invite::InviteJoin,
+            notice::NoticePost, notice::NoticeEdition, notice::Notice, notice::NoticeReads,
             history::History
         )
     ),
@@ -45,7 +76,8 @@ use utoipa::OpenApi;
         (name = "contact", description = "好友管理"),
         (name = "msg", description = "消息发送"),
         (name = "group", description = "群聊管理"),
-        (name = "static", description = "静态资源")
+        (name = "static", description = "静态资源"),
+        (name = "monitor", description = "运行监控")
     )
 )]
 pub(super) struct ApiDoc;
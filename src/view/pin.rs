@@ -0,0 +1,229 @@
+use super::message::{Msg, ReadAt, Reader};
+use super::*;
+use entity::{
+    contact, group, pinned_message,
+    prelude::{Contact, Member, Message, PinnedMessage},
+};
+use feed::{Notification, PinUpdate};
+use role::GroupAction;
+
+/// 获取会话中的用户
+///
+/// 群聊会话返回除待审批成员外的所有成员, 双人会话返回双方
+async fn get_session_users(
+    session: Uuid,
+    conn: &DatabaseConnection,
+) -> Result<Vec<Uuid>, AppError> {
+    Ok(match group::Model::from_session(session, conn).await? {
+        Some(g) => Member::get_members(g.id, conn).await?,
+        None => Contact::find()
+            .filter(contact::Column::Session.eq(session))
+            .all(conn)
+            .await?
+            .into_iter()
+            .map(|c| c.user)
+            .collect(),
+    })
+}
+
+/// 检查用户是否可以置顶会话中的消息
+///
+/// 群聊中所需角色由权限设置 `pin` 决定, 双人会话中双方都可以置顶
+async fn check_pin_permission(
+    session: Uuid,
+    user: Uuid,
+    conn: &DatabaseConnection,
+) -> Result<(), AppError> {
+    match group::Model::from_session(session, conn).await? {
+        Some(g) => {
+            Member::authorize(&g, user, GroupAction::Pin, conn).await?;
+        }
+        None => {
+            Contact::find()
+                .filter(contact::Column::Session.eq(session))
+                .filter(contact::Column::User.eq(user))
+                .one(conn)
+                .await?
+                .ok_or(AppError::Forbidden(
+                    ErrorCode::SessionNotMember,
+                    format!("user [{user}] not in session [{session}]"),
+                ))?;
+        }
+    }
+    Ok(())
+}
+
+async fn push_pin_update(state: AppState, session: Uuid, update: PinUpdate) {
+    match get_session_users(session, &state.conn).await {
+        Ok(users) => {
+            let notification = Notification::Pins {
+                items: vec![update],
+            };
+            for u in users {
+                state
+                    .ws_pool
+                    .notify(
+                        u,
+                        WebSocketMessage::Text(serde_json::to_string(&notification).unwrap()),
+                    )
+                    .await;
+            }
+        }
+        Err(e) => {
+            event!(Level::ERROR, "cannot find session users: {:?}", e);
+        }
+    }
+}
+
+/// 置顶消息
+///
+/// 群聊中所需角色由权限设置 `pin` 决定, 会话中的所有用户会收到 `Pins` 通知
+#[cfg_attr(feature = "dev",
+utoipa::path(
+    put,
+    path = "/msg/pin/{id}",
+    params(("id" = Uuid, Path, description = "消息的唯一主键")),
+    responses(
+        (status = 200, description = "置顶成功"),
+        (status = 403, description = "权限不足", body = AppErrorResponse),
+        (status = 409, description = "消息已置顶", body = AppErrorResponse),
+    ),
+    tag = "msg"
+))]
+#[instrument(skip(state))]
+pub async fn pin_msg_handler(
+    State(state): State<AppState>,
+    payload: JWTPayload,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    let msg = Message::find_by_id(id)
+        .one(&state.conn)
+        .await?
+        .ok_or(AppError::NotFound(
+            ErrorCode::MessageNotFound,
+            format!("cannot find message [{id}]"),
+        ))?;
+    check_pin_permission(msg.session, payload.id, &state.conn).await?;
+    if PinnedMessage::find()
+        .filter(pinned_message::Column::Message.eq(id))
+        .one(&state.conn)
+        .await?
+        .is_some()
+    {
+        return Err(AppError::Conflict(
+            ErrorCode::MessagePinned,
+            format!("message [{id}] already pinned"),
+        ));
+    }
+    let p = pinned_message::ActiveModel {
+        id: ActiveValue::not_set(),
+        session: ActiveValue::set(msg.session),
+        message: ActiveValue::set(id),
+        pinned_by: ActiveValue::set(Some(payload.id)),
+        created_at: ActiveValue::not_set(),
+    };
+    PinnedMessage::insert(p).exec(&state.conn).await?;
+    event!(Level::INFO, "user [{}] pin message [{id}]", payload.id);
+    trace::spawn(push_pin_update(
+        state,
+        msg.session,
+        PinUpdate {
+            session: msg.session,
+            msg: id,
+            user: payload.id,
+            pinned: true,
+        },
+    ));
+    Ok(StatusCode::OK.into_response())
+}
+
+/// 取消置顶消息
+#[cfg_attr(feature = "dev",
+utoipa::path(
+    delete,
+    path = "/msg/pin/{id}",
+    params(("id" = Uuid, Path, description = "消息的唯一主键")),
+    responses(
+        (status = 204, description = "取消成功"),
+        (status = 403, description = "权限不足", body = AppErrorResponse),
+    ),
+    tag = "msg"
+))]
+#[instrument(skip(state))]
+pub async fn unpin_msg_handler(
+    State(state): State<AppState>,
+    payload: JWTPayload,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    let p = PinnedMessage::find()
+        .filter(pinned_message::Column::Message.eq(id))
+        .one(&state.conn)
+        .await?
+        .ok_or(AppError::NotFound(
+            ErrorCode::MessageNotPinned,
+            format!("message [{id}] not pinned"),
+        ))?;
+    check_pin_permission(p.session, payload.id, &state.conn).await?;
+    PinnedMessage::delete_by_id(p.id).exec(&state.conn).await?;
+    event!(Level::INFO, "user [{}] unpin message [{id}]", payload.id);
+    trace::spawn(push_pin_update(
+        state,
+        p.session,
+        PinUpdate {
+            session: p.session,
+            msg: id,
+            user: payload.id,
+            pinned: false,
+        },
+    ));
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// 列出会话中的置顶消息
+///
+/// 按置顶时间倒序排列
+#[cfg_attr(feature = "dev",
+utoipa::path(
+    get,
+    path = "/msg/pin/session/{id}",
+    params(("id" = Uuid, Path, description = "会话的唯一主键")),
+    responses(
+        (status = 200, description = "获取成功", body = Vec<Msg>),
+        (status = 403, description = "不在会话中", body = AppErrorResponse),
+    ),
+    tag = "msg"
+))]
+#[instrument(skip(state))]
+pub async fn list_pin_handler(
+    State(state): State<AppState>,
+    payload: JWTPayload,
+    Path(session): Path<Uuid>,
+) -> Result<Json<Vec<Msg>>, AppError> {
+    if !get_session_users(session, &state.conn)
+        .await?
+        .contains(&payload.id)
+    {
+        return Err(AppError::Forbidden(
+            ErrorCode::SessionNotMember,
+            format!("user [{}] not in session [{session}]", payload.id),
+        ));
+    }
+    let pins = PinnedMessage::find()
+        .filter(pinned_message::Column::Session.eq(session))
+        .order_by(pinned_message::Column::CreatedAt, sea_orm::Order::Desc)
+        .find_also_related(Message)
+        .all(&state.conn)
+        .await?;
+    let mut msgs = Vec::new();
+    for (_, msg) in pins {
+        if let Some(msg) = msg {
+            let read_ats: Vec<ReadAt> = Reader::fetch_from_db(msg.id, &state.conn)
+                .await?
+                .into_iter()
+                .map(ReadAt::from)
+                .collect();
+            msgs.push(Msg::from((msg, read_ats)));
+        }
+    }
+    Ok(Json(msgs))
+}
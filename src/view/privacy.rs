@@ -0,0 +1,206 @@
+use super::user::UserProfile;
+use super::*;
+use entity::{
+    member,
+    prelude::{Member, User},
+    user,
+};
+use role::Role;
+use sea_query::{Expr, SimpleExpr};
+
+/// 资料的可见范围
+#[cfg_attr(feature = "dev", derive(ToSchema))]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Visibility {
+    /// 所有用户
+    Everyone,
+    /// 仅好友
+    Contacts,
+    /// 仅自己
+    Nobody,
+}
+
+impl Visibility {
+    fn allows(&self, is_contact: bool) -> bool {
+        match self {
+            Visibility::Everyone => true,
+            Visibility::Contacts => is_contact,
+            Visibility::Nobody => false,
+        }
+    }
+}
+
+/// 可以发起好友申请的用户
+#[cfg_attr(feature = "dev", derive(ToSchema))]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RequestPolicy {
+    /// 所有用户
+    Everyone,
+    /// 同在至少一个群聊中的用户
+    GroupMembers,
+    /// 不接受好友申请
+    Nobody,
+}
+
+/// 用户隐私设置
+///
+/// 存储于 `user.privacy`, 不提供的字段取默认值
+#[cfg_attr(feature = "dev", derive(ToSchema))]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct PrivacySettings {
+    /// 能否通过用户名或别名搜索到, 默认为 `true`
+    pub searchable_by_name: bool,
+    /// 能否通过邮箱搜索到, 默认为 `true`
+    pub searchable_by_email: bool,
+    /// 能否通过电话搜索到, 默认为 `true`
+    pub searchable_by_phone: bool,
+    /// 邮箱的可见范围, 默认为 `contacts`
+    pub email: Visibility,
+    /// 电话的可见范围, 默认为 `contacts`
+    pub phone: Visibility,
+    /// 性别, 个性简介与个人链接的可见范围, 默认为 `everyone`
+    pub profile: Visibility,
+    /// 可以发起好友申请的用户, 默认为 `everyone`
+    pub contact_request: RequestPolicy,
+}
+
+impl Default for PrivacySettings {
+    fn default() -> Self {
+        Self {
+            searchable_by_name: true,
+            searchable_by_email: true,
+            searchable_by_phone: true,
+            email: Visibility::Contacts,
+            phone: Visibility::Contacts,
+            profile: Visibility::Everyone,
+            contact_request: RequestPolicy::Everyone,
+        }
+    }
+}
+
+impl From<&user::Model> for PrivacySettings {
+    fn from(u: &user::Model) -> Self {
+        serde_json::from_value(u.privacy.clone()).unwrap_or_default()
+    }
+}
+
+impl PrivacySettings {
+    /// 搜索条件: `user.privacy` 中的开关未关闭
+    pub(super) fn searchable(key: &str) -> SimpleExpr {
+        Expr::cust(format!(
+            r#"COALESCE(("user".privacy->>'searchable_by_{key}')::BOOLEAN, TRUE)"#
+        ))
+    }
+
+    /// 检查 `requester` 能否向设置的所有者 `owner` 发起好友申请
+    pub(super) async fn check_contact_request(
+        &self,
+        owner: Uuid,
+        requester: Uuid,
+        conn: &DatabaseConnection,
+    ) -> Result<(), AppError> {
+        let allowed = match self.contact_request {
+            RequestPolicy::Everyone => true,
+            RequestPolicy::GroupMembers => share_group(owner, requester, conn).await?,
+            RequestPolicy::Nobody => false,
+        };
+        if allowed {
+            Ok(())
+        } else {
+            Err(AppError::Forbidden(
+                ErrorCode::ContactRequestRefused,
+                format!("user [{owner}] does not accept contact requests from [{requester}]"),
+            ))
+        }
+    }
+}
+
+/// 两名用户是否同在至少一个群聊中
+async fn share_group(a: Uuid, b: Uuid, conn: &DatabaseConnection) -> Result<bool, AppError> {
+    let groups: Vec<Uuid> = Member::find()
+        .filter(member::Column::User.eq(a))
+        .filter(member::Column::Permission.ne(Role::Pending))
+        .all(conn)
+        .await?
+        .into_iter()
+        .map(|m| m.group)
+        .collect();
+    Ok(Member::find()
+        .filter(member::Column::User.eq(b))
+        .filter(member::Column::Permission.ne(Role::Pending))
+        .filter(member::Column::Group.is_in(groups))
+        .one(conn)
+        .await?
+        .is_some())
+}
+
+impl UserProfile {
+    /// 隐藏隐私设置不允许查看的字段
+    pub(super) fn redact(&mut self, privacy: &PrivacySettings, is_contact: bool) {
+        if !privacy.email.allows(is_contact) {
+            self.email = String::default();
+            self.email_verified = false;
+        }
+        if !privacy.phone.allows(is_contact) {
+            self.phone = String::default();
+        }
+        if !privacy.profile.allows(is_contact) {
+            self.gender = 0;
+            self.bio = String::default();
+            self.link = String::default();
+        }
+    }
+}
+
+/// 获取隐私设置
+#[cfg_attr(feature = "dev",
+utoipa::path(
+    get,
+    path = "/user/privacy",
+    responses(
+        (status = 200, description = "获取成功", body = PrivacySettings),
+    ),
+    tag = "user"
+))]
+#[instrument(skip(state))]
+pub async fn get_privacy_handler(
+    State(state): State<AppState>,
+    payload: JWTPayload,
+) -> Result<Json<PrivacySettings>, AppError> {
+    let user = payload.to_user(&state.conn).await?;
+    Ok(Json(PrivacySettings::from(&user)))
+}
+
+/// 修改隐私设置
+///
+/// 不提供的字段恢复为默认值
+#[cfg_attr(feature = "dev",
+utoipa::path(
+    put,
+    path = "/user/privacy",
+    request_body = PrivacySettings,
+    responses(
+        (status = 200, description = "修改成功", body = PrivacySettings),
+    ),
+    tag = "user"
+))]
+#[instrument(skip(state))]
+pub async fn update_privacy_handler(
+    State(state): State<AppState>,
+    payload: JWTPayload,
+    Json(settings): Json<PrivacySettings>,
+) -> Result<Json<PrivacySettings>, AppError> {
+    let user = payload.to_user(&state.conn).await?;
+    let mut user: user::ActiveModel = user.into();
+    user.privacy = ActiveValue::set(serde_json::to_value(&settings)?);
+    User::update(user).exec(&state.conn).await?;
+    event!(
+        Level::INFO,
+        "update privacy settings of user [{}]",
+        payload.id
+    );
+    Ok(Json(settings))
+}
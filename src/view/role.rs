@@ -0,0 +1,200 @@
+use super::*;
+use entity::{group, member, prelude::Member};
+
+use super::group::GroupSettings;
+
+/// 群成员角色
+///
+/// 存储于 `member.permission`
+///
+/// | 值 | 角色 |
+/// |----|----|
+/// | -1 | 待审批 |
+/// | 0 | 普通成员 |
+/// | 1 | 管理员 |
+/// | 2 | 群主 |
+#[cfg_attr(feature = "dev", derive(ToSchema))]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    Pending,
+    Member,
+    Admin,
+    Owner,
+}
+
+impl From<Role> for i32 {
+    fn from(role: Role) -> Self {
+        match role {
+            Role::Pending => -1,
+            Role::Member => 0,
+            Role::Admin => 1,
+            Role::Owner => 2,
+        }
+    }
+}
+
+impl From<i32> for Role {
+    /// 未知的值按最低权限处理
+    fn from(value: i32) -> Self {
+        match value {
+            0 => Role::Member,
+            1 => Role::Admin,
+            2 => Role::Owner,
+            _ => Role::Pending,
+        }
+    }
+}
+
+impl From<Role> for sea_orm::Value {
+    fn from(role: Role) -> Self {
+        i32::from(role).into()
+    }
+}
+
+/// 群聊中需要授权的操作
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GroupAction {
+    /// 邀请新成员, 创建邀请链接
+    Invite,
+    /// 移除成员
+    Kick,
+    /// 发布群公告
+    Notice,
+    /// 禁言成员, 开启全员禁言
+    Mute,
+    /// 置顶消息
+    Pin,
+    /// 修改群资料
+    Edit,
+    /// 审批加入请求, 查看邀请链接
+    Approve,
+    /// 设置管理员, 转让群主, 修改权限设置
+    Manage,
+    /// 解散群聊
+    Dissolve,
+}
+
+/// 群聊权限设置
+///
+/// 每项为执行该操作所需的最低角色
+#[cfg_attr(feature = "dev", derive(ToSchema))]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct PermissionMatrix {
+    /// 邀请新成员, 默认为 `member`
+    pub invite: Role,
+    /// 移除成员, 默认为 `admin`
+    pub kick: Role,
+    /// 发布群公告, 默认为 `admin`
+    pub notice: Role,
+    /// 修改群资料, 默认为 `admin`
+    pub edit: Role,
+    /// 置顶消息, 默认为 `admin`
+    pub pin: Role,
+    /// 禁言成员与全员禁言, 默认为 `admin`
+    pub mute: Role,
+}
+
+impl Default for PermissionMatrix {
+    fn default() -> Self {
+        Self {
+            invite: Role::Member,
+            kick: Role::Admin,
+            notice: Role::Admin,
+            edit: Role::Admin,
+            pin: Role::Admin,
+            mute: Role::Admin,
+        }
+    }
+}
+
+impl PermissionMatrix {
+    /// 执行操作所需的最低角色
+    ///
+    /// 审批, 管理和解散不可配置
+    pub fn required(&self, action: GroupAction) -> Role {
+        match action {
+            GroupAction::Invite => self.invite,
+            GroupAction::Kick => self.kick,
+            GroupAction::Notice => self.notice,
+            GroupAction::Edit => self.edit,
+            GroupAction::Mute => self.mute,
+            GroupAction::Pin => self.pin,
+            GroupAction::Approve => Role::Admin,
+            GroupAction::Manage | GroupAction::Dissolve => Role::Owner,
+        }
+    }
+
+    pub(super) fn validate(&self) -> Result<(), AppError> {
+        if [
+            self.invite,
+            self.kick,
+            self.notice,
+            self.edit,
+            self.pin,
+            self.mute,
+        ]
+        .contains(&Role::Pending)
+        {
+            Err(AppError::BadRequest(
+                ErrorCode::InvalidRole,
+                "pending is not a valid permission".to_string(),
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl member::Model {
+    pub(super) fn role(&self) -> Role {
+        Role::from(self.permission)
+    }
+}
+
+impl Member {
+    /// 检查用户在群聊中是否有权限执行操作, 返回其成员记录
+    pub(super) async fn authorize(
+        g: &group::Model,
+        user: Uuid,
+        action: GroupAction,
+        conn: &DatabaseConnection,
+    ) -> Result<member::Model, AppError> {
+        let m = member::Model::from_group_and_user(g.id, user, conn).await?;
+        let required = GroupSettings::from(g).permissions.required(action);
+        if m.role() < required {
+            return Err(AppError::Forbidden(
+                ErrorCode::PermissionDenied,
+                format!(
+                    "[{action:?}] in group [{}] requires role [{required:?}]",
+                    g.id
+                ),
+            ));
+        }
+        Ok(m)
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convert_role_from_permission() {
+        for role in [Role::Pending, Role::Member, Role::Admin, Role::Owner] {
+            assert_eq!(Role::from(i32::from(role)), role);
+        }
+        assert_eq!(Role::from(42), Role::Pending);
+        assert!(Role::Owner > Role::Admin && Role::Admin > Role::Member);
+    }
+
+    #[test]
+    fn parse_partial_permission_matrix() {
+        let matrix: PermissionMatrix = serde_json::from_str(r#"{"invite":"admin"}"#).unwrap();
+        assert_eq!(matrix.required(GroupAction::Invite), Role::Admin);
+        assert_eq!(matrix.required(GroupAction::Kick), Role::Admin);
+        assert_eq!(matrix.required(GroupAction::Dissolve), Role::Owner);
+    }
+}
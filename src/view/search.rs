@@ -0,0 +1,268 @@
+use super::*;
+use entity::{
+    block, contact,
+    prelude::{Block, Contact},
+};
+
+/// 搜索结果中的用户与当前用户的关系
+#[cfg_attr(feature = "dev", derive(ToSchema))]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Relation {
+    /// 无关系
+    None,
+    /// 互为好友
+    Contact,
+    /// 当前用户已发起好友申请, 等待对方通过
+    Pending,
+    /// 对方已发起好友申请, 等待当前用户通过
+    Requested,
+    /// 当前用户已屏蔽对方
+    Blocked,
+}
+
+/// 用户搜索结果
+#[cfg_attr(feature = "dev", derive(ToSchema))]
+#[derive(Serialize, Debug)]
+#[cfg_attr(test, derive(Deserialize))]
+pub struct SearchItem {
+    /// 用户主键
+    pub id: Uuid,
+    /// 用户名
+    pub name: String,
+    /// 别名
+    pub alias: Option<String>,
+    /// 头像
+    pub avatar: Option<Uuid>,
+    /// 与当前用户的关系
+    pub relation: Relation,
+}
+
+/// 用户搜索结果列表
+#[cfg_attr(feature = "dev", derive(ToSchema))]
+#[derive(Serialize, Debug)]
+#[cfg_attr(test, derive(Deserialize))]
+pub struct SearchResult {
+    /// 按匹配程度排列的用户
+    pub items: Vec<SearchItem>,
+    /// 起始位置
+    pub start: u64,
+    /// 结束位置
+    pub end: u64,
+    /// 匹配的用户总数
+    pub cnt: u64,
+}
+
+/// 用户搜索条件
+#[cfg_attr(feature = "dev", derive(IntoParams))]
+#[derive(Deserialize, Debug)]
+pub(super) struct SearchParams {
+    /// 搜索内容
+    ///
+    /// 用户名与别名按部分匹配, 邮箱与电话需要完全匹配
+    q: String,
+    /// 起始位置, 默认为 `0`
+    start: Option<u64>,
+    /// 结束位置, 默认为 `20`, 最多一次获取 `100` 条
+    end: Option<u64>,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct SearchRow {
+    id: Uuid,
+    name: String,
+    alias: Option<String>,
+    avatar: Option<Uuid>,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct SearchCount {
+    cnt: i64,
+}
+
+/// 按匹配程度排序: 邮箱或电话完全匹配与用户名完全匹配为 `0`, 用户名前缀匹配或别名完全匹配为 `1`, 其余部分匹配为 `2`
+///
+/// 排除当前用户, 已注销的用户, 屏蔽了当前用户的用户, 以及按隐私设置不可被搜索的用户
+const RANKED_USERS: &str = r#"SELECT * FROM (SELECT u.id, u.name, u.alias, u.avatar, CASE
+    WHEN COALESCE((u.privacy->>'searchable_by_email')::BOOLEAN, TRUE) AND u.email_normalized = LOWER(TRIM($2)) THEN 0
+    WHEN COALESCE((u.privacy->>'searchable_by_phone')::BOOLEAN, TRUE) AND u.phone = $2 THEN 0
+    WHEN NOT COALESCE((u.privacy->>'searchable_by_name')::BOOLEAN, TRUE) THEN NULL
+    WHEN LOWER(u.name) = LOWER($2) THEN 0
+    WHEN u.name ILIKE $3 OR LOWER(u.alias) = LOWER($2) THEN 1
+    WHEN u.name ILIKE $4 OR u.alias ILIKE $4 THEN 2
+    END AS rank FROM "user" AS u
+    WHERE u.id <> $1 AND u.deleted_at IS NULL AND u.id NOT IN (SELECT b."user" FROM block AS b WHERE b.blocked = $1)) AS r
+    WHERE r.rank IS NOT NULL"#;
+
+/// 转义 `LIKE` 模式中的通配符
+fn escape_like(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+impl Relation {
+    async fn of_users(
+        caller: Uuid,
+        users: &[Uuid],
+        conn: &DatabaseConnection,
+    ) -> Result<Vec<Self>, AppError> {
+        let outgoing: Vec<Uuid> = Contact::find()
+            .filter(contact::Column::User.eq(caller))
+            .filter(contact::Column::RefUser.is_in(users.to_vec()))
+            .all(conn)
+            .await?
+            .into_iter()
+            .filter_map(|c| c.ref_user)
+            .collect();
+        let incoming: Vec<Uuid> = Contact::find()
+            .filter(contact::Column::User.is_in(users.to_vec()))
+            .filter(contact::Column::RefUser.eq(caller))
+            .all(conn)
+            .await?
+            .into_iter()
+            .map(|c| c.user)
+            .collect();
+        let blocked: Vec<Uuid> = Block::find()
+            .filter(block::Column::User.eq(caller))
+            .filter(block::Column::Blocked.is_in(users.to_vec()))
+            .all(conn)
+            .await?
+            .into_iter()
+            .map(|b| b.blocked)
+            .collect();
+        Ok(users
+            .iter()
+            .map(|u| {
+                if blocked.contains(u) {
+                    Relation::Blocked
+                } else {
+                    match (outgoing.contains(u), incoming.contains(u)) {
+                        (true, true) => Relation::Contact,
+                        (true, false) => Relation::Pending,
+                        (false, true) => Relation::Requested,
+                        (false, false) => Relation::None,
+                    }
+                }
+            })
+            .collect())
+    }
+}
+
+impl SearchResult {
+    async fn search(
+        params: SearchParams,
+        caller: Uuid,
+        conn: &DatabaseConnection,
+    ) -> Result<Self, AppError> {
+        let q = params.q.trim();
+        if q.is_empty() {
+            return Err(AppError::BadRequest(
+                ErrorCode::EmptySearchQuery,
+                "empty search query".to_string(),
+            ));
+        }
+        let start = params.start.unwrap_or(0);
+        let end = params.end.unwrap_or(20);
+        if end <= start {
+            return Err(AppError::BadRequest(
+                ErrorCode::InvalidRange,
+                "end leq start".to_string(),
+            ));
+        }
+        if end - start > 100 {
+            return Err(AppError::BadRequest(
+                ErrorCode::RangeTooLarge,
+                "too many results requested".to_string(),
+            ));
+        }
+        let pattern = escape_like(q);
+        let values: Vec<sea_orm::Value> = vec![
+            caller.into(),
+            q.into(),
+            format!("{pattern}%").into(),
+            format!("%{pattern}%").into(),
+        ];
+        let cnt = SearchCount::find_by_statement(Statement::from_sql_and_values(
+            Postgres,
+            format!("SELECT COUNT(*) AS cnt FROM ({RANKED_USERS}) AS c"),
+            values.clone(),
+        ))
+        .one(conn)
+        .await?
+        .map(|c| c.cnt as u64)
+        .unwrap_or(0);
+        let mut values = values;
+        values.push(((end - start) as i64).into());
+        values.push((start as i64).into());
+        let rows = SearchRow::find_by_statement(Statement::from_sql_and_values(
+            Postgres,
+            format!("{RANKED_USERS} ORDER BY r.rank, r.name LIMIT $5 OFFSET $6"),
+            values,
+        ))
+        .all(conn)
+        .await?;
+        let ids: Vec<Uuid> = rows.iter().map(|r| r.id).collect();
+        let relations = Relation::of_users(caller, &ids, conn).await?;
+        let items: Vec<SearchItem> = rows
+            .into_iter()
+            .zip(relations)
+            .map(|(r, relation)| SearchItem {
+                id: r.id,
+                name: r.name,
+                alias: r.alias,
+                avatar: r.avatar,
+                relation,
+            })
+            .collect();
+        Ok(Self {
+            start,
+            end: start + items.len() as u64,
+            cnt,
+            items,
+        })
+    }
+}
+
+/// 搜索用户
+///
+/// 结果按匹配程度排序, 并附带与当前用户的关系
+#[cfg_attr(feature = "dev",
+utoipa::path(
+    get,
+    path = "/user/search",
+    params(SearchParams),
+    responses(
+        (status = 200, description = "搜索成功", body = SearchResult),
+        (status = 400, description = "搜索内容为空或分页参数无效", body = AppErrorResponse),
+    ),
+    tag = "user"
+))]
+#[instrument(skip(state))]
+pub async fn search_user_handler(
+    State(state): State<AppState>,
+    payload: JWTPayload,
+    Query(params): Query<SearchParams>,
+) -> Result<Json<SearchResult>, AppError> {
+    let res = SearchResult::search(params, payload.id, &state.conn).await?;
+    event!(
+        Level::DEBUG,
+        "user [{}] search users: [{}/{}]",
+        payload.id,
+        res.items.len(),
+        res.cnt
+    );
+    Ok(Json(res))
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_like_wildcards() {
+        assert_eq!(escape_like("a_b%c\\d"), "a\\_b\\%c\\\\d");
+        assert_eq!(escape_like("monika"), "monika");
+    }
+}
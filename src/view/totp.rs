@@ -0,0 +1,462 @@
+use super::*;
+use crate::jwt::ChallengePayload;
+use crate::limit::LOGIN_LIMIT;
+use entity::{
+    prelude::{RecoveryCode, User},
+    recovery_code, user,
+};
+use hmac::{Hmac, Mac};
+use login::{check_locked, ClientInfo, LoginResponse};
+use utility::{gen_random_code, hash_token, validate_passwd};
+
+/// 时间步长, 单位为秒
+const TOTP_STEP: u64 = 30;
+/// 验证码位数
+const TOTP_DIGITS: u32 = 6;
+/// 允许的时钟偏差, 单位为时间步长
+const TOTP_SKEW: u64 = 1;
+/// 每次生成的恢复码数量
+const RECOVERY_CODES: usize = 10;
+
+/// 两步验证的绑定信息
+#[cfg_attr(feature = "dev", derive(ToSchema))]
+#[derive(Serialize, Debug)]
+#[cfg_attr(test, derive(Deserialize))]
+pub struct TotpEnrollment {
+    /// Base32 编码的密钥
+    pub secret: String,
+    /// `otpauth://` 链接, 可生成二维码供验证器扫描
+    pub uri: String,
+}
+
+/// 恢复码列表
+///
+/// 仅在生成时返回一次, 每个恢复码只能使用一次
+#[cfg_attr(feature = "dev", derive(ToSchema))]
+#[derive(Serialize, Debug)]
+#[cfg_attr(test, derive(Deserialize))]
+pub struct RecoveryCodes {
+    /// 恢复码
+    pub codes: Vec<String>,
+}
+
+/// 验证码请求体
+#[cfg_attr(feature = "dev", derive(ToSchema))]
+#[derive(Deserialize, Debug)]
+#[cfg_attr(test, derive(Serialize))]
+pub struct TotpCode {
+    /// 验证器生成的 6 位验证码
+    #[cfg(test)]
+    pub code: String,
+    #[cfg(not(test))]
+    code: String,
+}
+
+/// 密码确认请求体
+#[cfg_attr(feature = "dev", derive(ToSchema))]
+#[derive(Deserialize, Debug)]
+#[cfg_attr(test, derive(Serialize))]
+pub struct PasswordConfirm {
+    /// 当前密码
+    #[cfg(test)]
+    pub password: String,
+    #[cfg(not(test))]
+    password: String,
+}
+
+/// 两步验证登录请求体
+#[cfg_attr(feature = "dev", derive(ToSchema))]
+#[derive(Deserialize, Debug)]
+#[cfg_attr(test, derive(Serialize))]
+pub struct TotpLogin {
+    /// 登录时返回的挑战令牌
+    #[cfg(test)]
+    pub challenge: String,
+    #[cfg(not(test))]
+    challenge: String,
+    /// 验证码或恢复码
+    #[cfg(test)]
+    pub code: String,
+    #[cfg(not(test))]
+    code: String,
+}
+
+/// 登录需要两步验证时的响应体
+#[cfg_attr(feature = "dev", derive(ToSchema))]
+#[derive(Serialize, Debug)]
+#[cfg_attr(test, derive(Deserialize))]
+pub struct TotpChallenge {
+    /// 挑战令牌, 5 分钟内有效
+    pub challenge: String,
+}
+
+/// RFC 4226 HOTP
+fn hotp(secret: &[u8], counter: u64) -> u32 {
+    let mut mac = Hmac::<sha1::Sha1>::new_from_slice(secret).unwrap();
+    mac.update(&counter.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+    let offset = (digest[19] & 0x0f) as usize;
+    let bin = u32::from_be_bytes(digest[offset..offset + 4].try_into().unwrap()) & 0x7fff_ffff;
+    bin % 10u32.pow(TOTP_DIGITS)
+}
+
+/// RFC 6238 TOTP, `time` 为 Unix 时间戳
+pub(super) fn totp_code(secret: &str, time: u64) -> Option<String> {
+    let secret = data_encoding::BASE32_NOPAD.decode(secret.as_bytes()).ok()?;
+    Some(format!(
+        "{:0width$}",
+        hotp(&secret, time / TOTP_STEP),
+        width = TOTP_DIGITS as usize
+    ))
+}
+
+/// 检查验证码, 允许前后各 [`TOTP_SKEW`] 个时间步长的偏差, 返回匹配的时间步
+fn verify_totp(secret: &str, code: &str, time: u64) -> Option<u64> {
+    let step = time / TOTP_STEP;
+    (step.saturating_sub(TOTP_SKEW)..=step + TOTP_SKEW)
+        .find(|s| totp_code(secret, s * TOTP_STEP).as_deref() == Some(code))
+}
+
+/// 百分号编码 `otpauth://` 链接中的标签
+fn encode_label(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+fn now() -> u64 {
+    jsonwebtoken::get_current_timestamp()
+}
+
+fn check_password(user: &user::Model, password: &str) -> Result<(), AppError> {
+    if validate_passwd(password, &user.salt, &user.hash)? {
+        Ok(())
+    } else {
+        Err(AppError::Unauthorized(
+            ErrorCode::WrongPassword,
+            "wrong password".to_string(),
+        ))
+    }
+}
+
+impl recovery_code::Model {
+    /// 重新生成恢复码, 旧恢复码全部失效
+    async fn regenerate(user: Uuid, conn: &DatabaseConnection) -> Result<Vec<String>, AppError> {
+        RecoveryCode::delete_many()
+            .filter(recovery_code::Column::User.eq(user))
+            .exec(conn)
+            .await?;
+        let codes: Vec<String> = (0..RECOVERY_CODES)
+            .map(|_| gen_random_code(10).to_lowercase())
+            .collect();
+        RecoveryCode::insert_many(codes.iter().map(|c| recovery_code::ActiveModel {
+            id: ActiveValue::not_set(),
+            user: ActiveValue::set(user),
+            code_hash: ActiveValue::set(hash_token(c)),
+            created_at: ActiveValue::not_set(),
+        }))
+        .exec(conn)
+        .await?;
+        Ok(codes)
+    }
+
+    /// 使用恢复码, 成功时该恢复码失效
+    async fn consume(user: Uuid, code: &str, conn: &DatabaseConnection) -> Result<bool, AppError> {
+        let res = RecoveryCode::delete_many()
+            .filter(recovery_code::Column::User.eq(user))
+            .filter(recovery_code::Column::CodeHash.eq(hash_token(&code.to_lowercase())))
+            .exec(conn)
+            .await?;
+        Ok(res.rows_affected > 0)
+    }
+}
+
+impl user::Model {
+    /// 是否已开启两步验证
+    pub(super) fn totp_enabled(&self) -> bool {
+        self.totp_enabled_at.is_some()
+    }
+
+    /// 记录通过验证的时间步
+    ///
+    /// 时间步不晚于上次记录时返回 `false`, 即同一验证码只能使用一次
+    async fn accept_totp_step(
+        &self,
+        step: u64,
+        conn: &DatabaseConnection,
+    ) -> Result<bool, AppError> {
+        let step = step as i64;
+        let res = User::update_many()
+            .col_expr(user::Column::TotpLastStep, step.into())
+            .filter(user::Column::Id.eq(self.id))
+            .filter(
+                Condition::any()
+                    .add(user::Column::TotpLastStep.is_null())
+                    .add(user::Column::TotpLastStep.lt(step)),
+            )
+            .exec(conn)
+            .await?;
+        Ok(res.rows_affected > 0)
+    }
+}
+
+/// 开始绑定两步验证
+///
+/// 生成新的密钥, 需要通过 `/user/totp/confirm` 确认后才会生效
+#[cfg_attr(feature = "dev",
+utoipa::path(
+    post,
+    path = "/user/totp",
+    responses(
+        (status = 200, description = "生成成功", body = TotpEnrollment),
+        (status = 409, description = "已开启两步验证", body = AppErrorResponse),
+    ),
+    tag = "user"
+))]
+#[instrument(skip(state))]
+pub async fn enroll_totp_handler(
+    State(state): State<AppState>,
+    payload: JWTPayload,
+) -> Result<Json<TotpEnrollment>, AppError> {
+    let user = payload.to_user(&state.conn).await?;
+    if user.totp_enabled() {
+        return Err(AppError::Conflict(
+            ErrorCode::TotpAlreadyEnabled,
+            "totp already enabled".to_string(),
+        ));
+    }
+    let secret: [u8; 20] = rand::random();
+    let secret = data_encoding::BASE32_NOPAD.encode(&secret);
+    let uri = format!(
+        "otpauth://totp/Veloquent:{}?secret={secret}&issuer=Veloquent&algorithm=SHA1&digits={TOTP_DIGITS}&period={TOTP_STEP}",
+        encode_label(&user.name)
+    );
+    let mut user: user::ActiveModel = user.into();
+    user.totp_secret = ActiveValue::set(Some(secret.clone()));
+    User::update(user).exec(&state.conn).await?;
+    Ok(Json(TotpEnrollment { secret, uri }))
+}
+
+/// 确认绑定两步验证
+///
+/// 验证码正确时开启两步验证, 并返回恢复码
+#[cfg_attr(feature = "dev",
+utoipa::path(
+    post,
+    path = "/user/totp/confirm",
+    request_body = TotpCode,
+    responses(
+        (status = 200, description = "开启成功", body = RecoveryCodes),
+        (status = 400, description = "未开始绑定", body = AppErrorResponse),
+        (status = 401, description = "验证码错误", body = AppErrorResponse),
+        (status = 409, description = "已开启两步验证", body = AppErrorResponse),
+    ),
+    tag = "user"
+))]
+#[instrument(skip(state, post))]
+pub async fn confirm_totp_handler(
+    State(state): State<AppState>,
+    payload: JWTPayload,
+    Json(post): Json<TotpCode>,
+) -> Result<Json<RecoveryCodes>, AppError> {
+    let user = payload.to_user(&state.conn).await?;
+    if user.totp_enabled() {
+        return Err(AppError::Conflict(
+            ErrorCode::TotpAlreadyEnabled,
+            "totp already enabled".to_string(),
+        ));
+    }
+    let secret = user.totp_secret.as_deref().ok_or(AppError::BadRequest(
+        ErrorCode::TotpNotStarted,
+        "totp enrollment not started".to_string(),
+    ))?;
+    let Some(step) = verify_totp(secret, post.code.trim(), now()) else {
+        return Err(AppError::Unauthorized(
+            ErrorCode::WrongTotpCode,
+            "wrong totp code".to_string(),
+        ));
+    };
+    let mut user: user::ActiveModel = user.into();
+    user.totp_enabled_at = ActiveValue::set(Some(chrono::Utc::now().naive_utc()));
+    user.totp_last_step = ActiveValue::set(Some(step as i64));
+    User::update(user).exec(&state.conn).await?;
+    let codes = recovery_code::Model::regenerate(payload.id, &state.conn).await?;
+    event!(Level::INFO, "user [{}] enable totp", payload.id);
+    Ok(Json(RecoveryCodes { codes }))
+}
+
+/// 关闭两步验证
+#[cfg_attr(feature = "dev",
+utoipa::path(
+    delete,
+    path = "/user/totp",
+    request_body = PasswordConfirm,
+    responses(
+        (status = 204, description = "关闭成功"),
+        (status = 401, description = "密码错误", body = AppErrorResponse),
+    ),
+    tag = "user"
+))]
+#[instrument(skip(state, post))]
+pub async fn disable_totp_handler(
+    State(state): State<AppState>,
+    payload: JWTPayload,
+    Json(post): Json<PasswordConfirm>,
+) -> Result<Response, AppError> {
+    let user = payload.to_user(&state.conn).await?;
+    check_password(&user, &post.password)?;
+    let mut user: user::ActiveModel = user.into();
+    user.totp_secret = ActiveValue::set(None);
+    user.totp_enabled_at = ActiveValue::set(None);
+    user.totp_last_step = ActiveValue::set(None);
+    User::update(user).exec(&state.conn).await?;
+    RecoveryCode::delete_many()
+        .filter(recovery_code::Column::User.eq(payload.id))
+        .exec(&state.conn)
+        .await?;
+    event!(Level::INFO, "user [{}] disable totp", payload.id);
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// 重新生成恢复码
+///
+/// 旧恢复码全部失效
+#[cfg_attr(feature = "dev",
+utoipa::path(
+    post,
+    path = "/user/totp/recovery",
+    request_body = PasswordConfirm,
+    responses(
+        (status = 200, description = "生成成功", body = RecoveryCodes),
+        (status = 400, description = "未开启两步验证", body = AppErrorResponse),
+        (status = 401, description = "密码错误", body = AppErrorResponse),
+    ),
+    tag = "user"
+))]
+#[instrument(skip(state, post))]
+pub async fn regenerate_recovery_handler(
+    State(state): State<AppState>,
+    payload: JWTPayload,
+    Json(post): Json<PasswordConfirm>,
+) -> Result<Json<RecoveryCodes>, AppError> {
+    let user = payload.to_user(&state.conn).await?;
+    check_password(&user, &post.password)?;
+    if !user.totp_enabled() {
+        return Err(AppError::BadRequest(
+            ErrorCode::TotpNotEnabled,
+            "totp not enabled".to_string(),
+        ));
+    }
+    let codes = recovery_code::Model::regenerate(payload.id, &state.conn).await?;
+    event!(
+        Level::INFO,
+        "user [{}] regenerate recovery codes",
+        payload.id
+    );
+    Ok(Json(RecoveryCodes { codes }))
+}
+
+/// 完成两步验证登录
+///
+/// 可以使用验证码或恢复码, 每个验证码只能使用一次
+///
+/// 验证通过后清除账号的失败计数, 并恢复保留期内的注销账号
+#[cfg_attr(feature = "dev",
+utoipa::path(
+    post,
+    path = "/login/totp",
+    request_body = TotpLogin,
+    responses(
+        (status = 200, description = "登录成功", body = LoginResponse),
+        (status = 401, description = "挑战令牌无效或验证码错误", body = AppErrorResponse),
+        (status = 429, description = "账号已锁定", body = AppErrorResponse),
+    ),
+    tag = "user"
+))]
+#[instrument(skip(state, post))]
+pub async fn login_totp_handler(
+    State(state): State<AppState>,
+    client: ClientInfo,
+    Json(post): Json<TotpLogin>,
+) -> Result<Json<LoginResponse>, AppError> {
+    let challenge = ChallengePayload::try_from(post.challenge.as_str())?;
+    let user = User::find_by_id(challenge.sub)
+        .one(&state.conn)
+        .await?
+        .filter(user::Model::restorable)
+        .ok_or(AppError::NotFound(
+            ErrorCode::UserNotFound,
+            format!("cannot find user [{}]", challenge.sub),
+        ))?;
+    let secret = match (user.totp_enabled(), user.totp_secret.as_deref()) {
+        (true, Some(secret)) => secret,
+        _ => {
+            return Err(AppError::Unauthorized(
+                ErrorCode::TotpNotEnabled,
+                "totp not enabled".to_string(),
+            ))
+        }
+    };
+    if let Err(e) = check_locked(&user.name) {
+        client
+            .audit(&user.name, Some(user.id), Some("locked"), &state.conn)
+            .await?;
+        return Err(e);
+    }
+    let limit = LOGIN_LIMIT.get().unwrap();
+    let code = post.code.trim();
+    let verified = match verify_totp(secret, code, now()) {
+        Some(step) => user.accept_totp_step(step, &state.conn).await?,
+        None => recovery_code::Model::consume(user.id, code, &state.conn).await?,
+    };
+    if !verified {
+        event!(Level::INFO, "user [{}] fail totp verification", user.id);
+        client
+            .audit(&user.name, Some(user.id), Some("wrong_totp"), &state.conn)
+            .await?;
+        if limit.fail(&user.name) {
+            event!(Level::WARN, "lock account [{}]", user.name);
+        }
+        return Err(AppError::Unauthorized(
+            ErrorCode::WrongTotpCode,
+            "wrong totp code".to_string(),
+        ));
+    }
+    limit.succeed(&user.name);
+    let user = user.restore(&state.conn).await?;
+    client
+        .audit(&user.name, Some(user.id), None, &state.conn)
+        .await?;
+    event!(Level::INFO, "user login [{}] with totp", user.name);
+    Ok(Json(LoginResponse {
+        token: JWTPayload::from(user.id).into(),
+    }))
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rfc6238_sha1_vectors() {
+        let secret = data_encoding::BASE32_NOPAD.encode(b"12345678901234567890");
+        assert_eq!(totp_code(&secret, 59).unwrap(), "287082");
+        assert_eq!(totp_code(&secret, 1111111109).unwrap(), "081804");
+        assert_eq!(totp_code(&secret, 2000000000).unwrap(), "279037");
+        assert_eq!(verify_totp(&secret, "287082", 59 + TOTP_STEP), Some(1));
+        assert_eq!(verify_totp(&secret, "287082", 59 + 2 * TOTP_STEP), None);
+        assert!(totp_code("not base32!", 59).is_none());
+    }
+
+    #[test]
+    fn encode_otpauth_label() {
+        assert_eq!(encode_label("monika"), "monika");
+        assert_eq!(encode_label("a b:c"), "a%20b%3Ac");
+    }
+}
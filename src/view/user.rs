@@ -1,7 +1,12 @@
 use super::*;
-use entity::{prelude::User, user};
+use entity::{
+    contact,
+    prelude::{Block, User},
+    user,
+};
 use login::LoginResponse;
-use utility::{gen_hash_and_salt, good_email, good_phone};
+use privacy::PrivacySettings;
+use utility::{gen_hash_and_salt, good_email, good_phone, normalize_email, DELETION_GRACE};
 
 /// 用户创建请求体
 ///
@@ -64,11 +69,94 @@ pub struct RegisterProfile {
 }
 
 impl user::Model {
+    /// 查找用户, 已注销的用户视为不存在
     pub(crate) async fn from_uuid(id: Uuid, conn: &DatabaseConnection) -> Result<Self, AppError> {
         User::find_by_id(id)
+            .filter(user::Column::DeletedAt.is_null())
             .one(conn)
             .await?
-            .ok_or(AppError::NotFound(format!("cannot find user [{id}]")))
+            .ok_or(AppError::NotFound(
+                ErrorCode::UserNotFound,
+                format!("cannot find user [{id}]"),
+            ))
+    }
+
+    /// 检查用户名, 邮箱与电话是否已被其他用户占用
+    ///
+    /// 保留期内的注销账号同样占用, 避免插入时触发数据库唯一约束
+    pub(super) async fn check_unique(
+        user: &user::ActiveModel,
+        conn: &DatabaseConnection,
+    ) -> Result<(), AppError> {
+        let name = user.name.try_as_ref();
+        let email = user.email.try_as_ref();
+        let email_normalized = user.email_normalized.try_as_ref().and_then(Option::as_ref);
+        let phone = user.phone.try_as_ref();
+        let mut cond = Condition::any();
+        if let Some(name) = name {
+            cond = cond.add(user::Column::Name.eq(name));
+        }
+        if let Some(email) = email {
+            cond = cond.add(user::Column::Email.eq(email));
+        }
+        if let Some(email) = email_normalized {
+            cond = cond.add(user::Column::EmailNormalized.eq(email));
+        }
+        if let Some(phone) = phone {
+            cond = cond.add(user::Column::Phone.eq(phone));
+        }
+        if cond.is_empty() {
+            return Ok(());
+        }
+        let mut query = User::find().filter(cond);
+        if let Some(id) = user.id.try_as_ref() {
+            query = query.filter(user::Column::Id.ne(*id));
+        }
+        match query.one(conn).await? {
+            Some(other) if name == Some(&other.name) => Err(AppError::Conflict(
+                ErrorCode::NameTaken,
+                format!("name [{}] already taken", other.name),
+            )),
+            Some(other) if phone == Some(&other.phone) => Err(AppError::Conflict(
+                ErrorCode::PhoneTaken,
+                "phone already registered".to_string(),
+            )),
+            Some(_) => Err(AppError::Conflict(
+                ErrorCode::EmailTaken,
+                "email already registered".to_string(),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// 注销账号的保留期截止时间点, 早于该时间注销的账号将被清除
+    pub(super) fn deletion_cutoff(grace: u64) -> chrono::NaiveDateTime {
+        chrono::Utc::now().naive_utc() - chrono::Duration::seconds(grace as i64)
+    }
+
+    /// 清除保留期已过的注销账号
+    pub(super) async fn purge_deleted(
+        grace: u64,
+        conn: &DatabaseConnection,
+    ) -> Result<u64, AppError> {
+        let res = User::delete_many()
+            .filter(user::Column::DeletedAt.lt(Self::deletion_cutoff(grace)))
+            .exec(conn)
+            .await?;
+        Ok(res.rows_affected)
+    }
+}
+
+/// 定期清除保留期已过的注销账号
+pub async fn purge_deleted_users(conn: DatabaseConnection, interval: u64) {
+    let mut ticker = tokio::time::interval(std::time::Duration::from_secs(interval.max(1)));
+    loop {
+        ticker.tick().await;
+        match user::Model::purge_deleted(*DELETION_GRACE.get().unwrap(), &conn).await {
+            Ok(0) => {}
+            Ok(n) => event!(Level::INFO, "purge [{n}] deleted users"),
+            Err(e) => event!(Level::ERROR, "fail to purge deleted users: [{:?}]", e),
+        }
     }
 }
 
@@ -76,12 +164,27 @@ impl TryFrom<RegisterProfile> for user::ActiveModel {
     type Error = AppError;
     fn try_from(p: RegisterProfile) -> Result<Self, Self::Error> {
         if !p.name.is_empty() && !p.password.is_empty() {
+            let email = normalize_email(&p.email);
             if p.gender.is_some_and(|g| g.is_negative()) {
-                Err(AppError::BadRequest("gender not valid".to_string()))
-            } else if !p.email.is_empty() && !good_email(&p.email) {
-                Err(AppError::BadRequest("invalid email".to_string()))
+                Err(AppError::BadRequest(
+                    ErrorCode::InvalidGender,
+                    "gender not valid".to_string(),
+                ))
+            } else if good_email(&normalize_email(&p.name)) || good_phone(&p.name) {
+                Err(AppError::BadRequest(
+                    ErrorCode::InvalidName,
+                    "name cannot be an email or phone".to_string(),
+                ))
+            } else if !email.is_empty() && !good_email(&email) {
+                Err(AppError::BadRequest(
+                    ErrorCode::InvalidEmail,
+                    "invalid email".to_string(),
+                ))
             } else if !p.email.is_empty() && !good_phone(&p.phone) {
-                Err(AppError::BadRequest("invalid phone".to_string()))
+                Err(AppError::BadRequest(
+                    ErrorCode::InvalidPhone,
+                    "invalid phone".to_string(),
+                ))
             } else {
                 let (hash, salt) = gen_hash_and_salt(&p.password)?;
                 Ok(user::ActiveModel {
@@ -97,10 +200,18 @@ impl TryFrom<RegisterProfile> for user::ActiveModel {
                     bio: ActiveValue::Set(p.bio),
                     avatar: ActiveValue::not_set(),
                     link: ActiveValue::Set(p.link),
+                    privacy: ActiveValue::not_set(),
+                    deleted_at: ActiveValue::not_set(),
+                    email_verified_at: ActiveValue::not_set(),
+                    totp_secret: ActiveValue::not_set(),
+                    totp_enabled_at: ActiveValue::not_set(),
+                    totp_last_step: ActiveValue::not_set(),
+                    email_normalized: ActiveValue::set((!email.is_empty()).then_some(email)),
                 })
             }
         } else {
             Err(AppError::BadRequest(
+                ErrorCode::CredentialsRequired,
                 "name and password must be provided".to_string(),
             ))
         }
@@ -108,6 +219,8 @@ impl TryFrom<RegisterProfile> for user::ActiveModel {
 }
 
 /// 注册新用户
+///
+/// 提供邮箱时向邮箱发送验证邮件
 #[cfg_attr(feature = "dev",
 utoipa::path(
     post,
@@ -115,18 +228,31 @@ utoipa::path(
     request_body = RegisterProfile,
     responses(
         (status = 201, description = "注册成功", body = LoginResponse),
+        (status = 409, description = "用户名, 邮箱或电话已被占用", body = AppErrorResponse),
     ),
     tag = "user"
 ))]
-#[instrument(skip(state))]
+#[instrument(skip(state, profile))]
 pub async fn register_handler(
     State(state): State<AppState>,
     Json(profile): Json<RegisterProfile>,
 ) -> Result<Response, AppError> {
     let user = user::ActiveModel::try_from(profile)?;
-    let res = User::insert(user).exec(&state.conn).await?;
-    event!(Level::INFO, "create user {:?}", res);
-    let res: JWTPayload = res.last_insert_id.into();
+    user::Model::check_unique(&user, &state.conn).await?;
+    let user = User::insert(user).exec_with_returning(&state.conn).await?;
+    event!(Level::INFO, "create user [{}]", user.id);
+    // 账号已经创建, 签发失败时用户可以稍后重新请求验证邮件
+    if !user.email.is_empty() {
+        if let Err(e) = email::send_verification(&user, &state.conn).await {
+            event!(
+                Level::WARN,
+                "fail to send verification to user [{}]: [{:?}]",
+                user.id,
+                e
+            );
+        }
+    }
+    let res: JWTPayload = user.id.into();
     Ok((
         StatusCode::CREATED,
         Json(LoginResponse { token: res.into() }),
@@ -137,6 +263,7 @@ pub async fn register_handler(
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[cfg_attr(feature = "dev", derive(ToSchema))]
 #[derive(Clone, PartialEq, Serialize, Debug)]
+#[cfg_attr(test, derive(Deserialize))]
 pub struct UserProfile {
     /// 主键
     pub id: Uuid,
@@ -146,6 +273,8 @@ pub struct UserProfile {
     pub alias: String,
     /// 邮箱
     pub email: String,
+    /// 邮箱是否已验证
+    pub email_verified: bool,
     /// 电话
     pub phone: String,
     /// 个人链接
@@ -165,11 +294,12 @@ pub struct UserProfile {
 impl From<user::Model> for UserProfile {
     fn from(user: user::Model) -> Self {
         Self {
-            id: user.id.into(),
+            id: user.id,
             name: user.name,
             gender: user.gender,
             alias: user.alias.unwrap_or_default(),
             email: user.email,
+            email_verified: user.email_verified_at.is_some(),
             phone: user.phone,
             created_at: user.created_at.and_utc().timestamp_millis(),
             avatar: user.avatar.unwrap_or_default(),
@@ -201,6 +331,13 @@ mod tests {
             gender: 0,
             salt: "KxlaYxELSZSGYCEsm5dE00BTTxnZ10".to_string(),
             hash: "74491363c6cc8c851ed7e1ea3279741795cf4e1f9534b125562ff7030f295eb7".to_string(),
+            privacy: serde_json::json!({}),
+            deleted_at: None,
+            email_verified_at: None,
+            totp_secret: None,
+            totp_enabled_at: None,
+            totp_last_step: None,
+            email_normalized: Some("adamanteye@example.com".to_string()),
         };
         assert_eq!(
             UserProfile::from(user),
@@ -208,6 +345,7 @@ mod tests {
                 id: Uuid::from_str("264107cf-8559-41b0-a8fe-074531695bf6").unwrap(),
                 name: "test".to_string(),
                 email: "adamanteye@example.com".to_string(),
+                email_verified: false,
                 phone: "1234567890".to_string(),
                 created_at: created_at.and_utc().timestamp_millis(),
                 gender: 0,
@@ -228,6 +366,8 @@ pub struct UserProfileParams {
 }
 
 /// 获取用户信息
+///
+/// 获取其他用户的信息时, 按对方的隐私设置隐藏部分字段
 #[cfg_attr(feature = "dev",
 utoipa::path(
     get,
@@ -235,8 +375,8 @@ utoipa::path(
     params(UserProfileParams),
     responses(
         (status = 200, description = "获取成功", body = UserProfile),
-        (status = 400, description = "提取 Authorization Bearer 失败", body = AppErrorResponse, example = json!({"msg":"token not found: [invalid HTTP header (authorization)]","ver": "0.1.1"})),
-        (status = 401, description = "验证用户失败", body = AppErrorResponse, example = json!({"msg":"invalid JWT: [InvalidSignature]","ver": "0.1.1"}))
+        (status = 400, description = "提取 Authorization Bearer 失败", body = AppErrorResponse, example = json!({"code":"TOKEN_MISSING","msg":"authorization token not found","details":{"reason":"token not found: [invalid HTTP header (authorization)]"},"ver": "0.1.1"})),
+        (status = 401, description = "验证用户失败", body = AppErrorResponse, example = json!({"code":"INVALID_TOKEN","msg":"invalid or expired token","details":{"reason":"invalid JWT: [InvalidSignature]"},"ver": "0.1.1"}))
     ),
     tag = "user"
 ))]
@@ -247,14 +387,21 @@ pub async fn get_profile_handler(
     Query(params): Query<UserProfileParams>,
 ) -> Result<Json<UserProfile>, AppError> {
     let user: Option<user::Model> = User::find_by_id(params.id.unwrap_or(payload.id))
+        .filter(user::Column::DeletedAt.is_null())
         .one(&state.conn)
         .await?;
-    let user = user.ok_or(AppError::NotFound(format!(
-        "cannot find user [{}]",
-        payload.id
-    )))?;
+    let user = user.ok_or(AppError::NotFound(
+        ErrorCode::UserNotFound,
+        format!("cannot find user [{}]", payload.id),
+    ))?;
     event!(Level::DEBUG, "get user profile: [{:?}]", user);
-    Ok(Json(UserProfile::from(user)))
+    let privacy = PrivacySettings::from(&user);
+    let mut profile = UserProfile::from(user);
+    if profile.id != payload.id {
+        let is_contact = contact::Model::are_contacts(profile.id, payload.id, &state.conn).await?;
+        profile.redact(&privacy, is_contact);
+    }
+    Ok(Json(profile))
 }
 
 #[cfg_attr(feature = "dev", derive(ToSchema))]
@@ -310,7 +457,15 @@ impl TryFrom<UserProfileEdition> for user::ActiveModel {
         let mut user = user::ActiveModel {
             id: ActiveValue::not_set(),
             name: match value.name {
-                Some(n) => ActiveValue::set(n),
+                Some(n) => {
+                    if good_email(&normalize_email(&n)) || good_phone(&n) {
+                        return Err(AppError::BadRequest(
+                            ErrorCode::InvalidName,
+                            "name cannot be an email or phone".to_string(),
+                        ));
+                    }
+                    ActiveValue::set(n)
+                }
                 None => ActiveValue::not_set(),
             },
             salt: ActiveValue::not_set(),
@@ -323,29 +478,48 @@ impl TryFrom<UserProfileEdition> for user::ActiveModel {
             },
             bio: ActiveValue::set(value.bio),
             alias: ActiveValue::set(value.alias),
-            email: match value.email {
+            email: match value.email.as_ref() {
                 Some(e) => {
-                    if !good_email(&e) {
-                        return Err(AppError::BadRequest("invalid email".to_string()));
+                    if !good_email(&normalize_email(e)) {
+                        return Err(AppError::BadRequest(
+                            ErrorCode::InvalidEmail,
+                            "invalid email".to_string(),
+                        ));
                     }
-                    ActiveValue::set(e)
+                    ActiveValue::set(e.clone())
                 }
                 None => ActiveValue::not_set(),
             },
             phone: match value.phone {
                 Some(p) => {
                     if !good_phone(&p) {
-                        return Err(AppError::BadRequest("invalid phone".to_string()));
+                        return Err(AppError::BadRequest(
+                            ErrorCode::InvalidPhone,
+                            "invalid phone".to_string(),
+                        ));
                     }
                     ActiveValue::set(p)
                 }
                 None => ActiveValue::not_set(),
             },
             link: ActiveValue::set(value.link),
+            privacy: ActiveValue::not_set(),
+            deleted_at: ActiveValue::not_set(),
+            email_verified_at: ActiveValue::not_set(),
+            totp_secret: ActiveValue::not_set(),
+            totp_enabled_at: ActiveValue::not_set(),
+            totp_last_step: ActiveValue::not_set(),
+            email_normalized: match value.email {
+                Some(e) => ActiveValue::set(Some(normalize_email(&e))),
+                None => ActiveValue::not_set(),
+            },
         };
         if let Some(p) = value.password.as_ref() {
             if p.is_empty() {
-                return Err(AppError::BadRequest("password cannot be empty".to_string()));
+                return Err(AppError::BadRequest(
+                    ErrorCode::EmptyPassword,
+                    "password cannot be empty".to_string(),
+                ));
             }
             use utility::gen_hash_and_salt;
             let (hash, salt) = gen_hash_and_salt(p)?;
@@ -357,6 +531,8 @@ impl TryFrom<UserProfileEdition> for user::ActiveModel {
 }
 
 /// 修改用户信息
+///
+/// 修改邮箱后需要重新验证, 并向新邮箱发送验证邮件
 #[cfg_attr(feature = "dev",
 utoipa::path(
     put,
@@ -364,49 +540,59 @@ utoipa::path(
     request_body = UserProfileEdition,
     responses(
         (status = 200, description = "更新成功"),
+        (status = 409, description = "用户名, 邮箱或电话已被占用", body = AppErrorResponse),
     ),
     tag = "user"
 ))]
-#[instrument(skip(state))]
+#[instrument(skip(state, edition))]
 pub async fn update_profile_handler(
     State(state): State<AppState>,
     payload: JWTPayload,
     Json(edition): Json<UserProfileEdition>,
 ) -> Result<Response, AppError> {
-    User::find_by_id(payload.id)
-        .one(&state.conn)
-        .await?
-        .ok_or(AppError::NotFound(format!(
-            "cannot find user: [{}]",
-            payload.id
-        )))?;
+    let old = user::Model::from_uuid(payload.id, &state.conn).await?;
+    let email_changed = edition.email.as_ref().is_some_and(|e| *e != old.email);
     let mut user = user::ActiveModel::try_from(edition)?;
     user.id = ActiveValue::set(payload.id);
-    User::update(user).exec(&state.conn).await?;
+    user::Model::check_unique(&user, &state.conn).await?;
+    if email_changed {
+        user.email_verified_at = ActiveValue::set(None);
+    }
+    let user = User::update(user).exec(&state.conn).await?;
     event!(Level::INFO, "update user [{}]", payload.id);
+    if email_changed {
+        if let Err(e) = email::send_verification(&user, &state.conn).await {
+            event!(
+                Level::WARN,
+                "fail to send verification to user [{}]: [{:?}]",
+                user.id,
+                e
+            );
+        }
+    }
     Ok(StatusCode::OK.into_response())
 }
 
-/// 删除用户自己
+/// 注销用户自己
+///
+/// 账号在保留期内可以通过登录恢复, 超过保留期后被清除
 #[cfg_attr(feature = "dev",
 utoipa::path(
     delete,
     path = "/user/profile",
-    responses((status = 204, description = "删除成功")),
+    responses((status = 204, description = "注销成功")),
     tag = "user"
 ))]
 #[instrument(skip(state))]
 pub async fn delete_user_handler(
-    State(state): State<AppState>,
+    State(mut state): State<AppState>,
     payload: JWTPayload,
 ) -> Result<Response, AppError> {
-    let res: DeleteResult = User::delete_by_id(payload.id).exec(&state.conn).await?;
-    if res.rows_affected == 0 {
-        return Err(AppError::NotFound(format!(
-            "cannot delete user [{}]",
-            payload.id
-        )));
-    }
+    let user = user::Model::from_uuid(payload.id, &state.conn).await?;
+    let mut user: user::ActiveModel = user.into();
+    user.deleted_at = ActiveValue::set(Some(chrono::Utc::now().naive_utc()));
+    User::update(user).exec(&state.conn).await?;
+    state.ws_pool.unregister(payload.id).await;
     event!(Level::INFO, "delete user [{}]", payload.id);
     Ok(StatusCode::NO_CONTENT.into_response())
 }
@@ -421,7 +607,9 @@ pub struct UserList {
 
 /// 各条件之间用与连接
 ///
-/// 没有提供字段的条件不参与查询
+/// 没有提供字段的条件不参与查询, 至少需要提供一个条件
+///
+/// 用户名与别名按部分匹配, 邮箱与电话需要完全匹配
 #[cfg_attr(feature = "dev", derive(IntoParams))]
 #[derive(Deserialize, Debug)]
 pub struct UserFindRequest {
@@ -436,23 +624,44 @@ pub struct UserFindRequest {
 }
 
 impl UserList {
+    /// 查找用户, 屏蔽了 `caller` 的用户与按隐私设置不可被搜索的用户不会出现在结果中
     pub async fn find(
         params: UserFindRequest,
+        caller: Uuid,
         conn: &DatabaseConnection,
     ) -> Result<Self, AppError> {
+        let mut cond = Condition::all();
+        if let Some(name) = params.name {
+            cond = cond
+                .add(user::Column::Name.like(format!("%{name}%")))
+                .add(PrivacySettings::searchable("name"));
+        }
+        if let Some(alias) = params.alias {
+            cond = cond
+                .add(user::Column::Alias.like(format!("%{alias}%")))
+                .add(PrivacySettings::searchable("name"));
+        }
+        if let Some(email) = params.email {
+            cond = cond
+                .add(user::Column::EmailNormalized.eq(normalize_email(&email)))
+                .add(PrivacySettings::searchable("email"));
+        }
+        if let Some(phone) = params.phone {
+            cond = cond
+                .add(user::Column::Phone.eq(phone))
+                .add(PrivacySettings::searchable("phone"));
+        }
+        if cond.is_empty() {
+            return Err(AppError::BadRequest(
+                ErrorCode::SearchConditionRequired,
+                "no search condition provided".to_string(),
+            ));
+        }
+        let blockers = Block::get_blockers(caller, conn).await?;
         let users = User::find()
             .filter(
-                Condition::all()
-                    .add(user::Column::Name.like(format!("%{}%", params.name.unwrap_or_default())))
-                    .add(
-                        user::Column::Alias.like(format!("%{}%", params.alias.unwrap_or_default())),
-                    )
-                    .add(
-                        user::Column::Email.like(format!("%{}%", params.email.unwrap_or_default())),
-                    )
-                    .add(
-                        user::Column::Phone.like(format!("%{}%", params.phone.unwrap_or_default())),
-                    ),
+                cond.add(user::Column::Id.is_not_in(blockers))
+                    .add(user::Column::DeletedAt.is_null()),
             )
             .all(conn)
             .await?;
@@ -474,13 +683,13 @@ utoipa::path(
     ),
     tag = "user"
 ))]
-#[instrument(skip(state, _payload))]
+#[instrument(skip(state, payload))]
 pub async fn find_user_handler(
     State(state): State<AppState>,
-    _payload: JWTPayload,
+    payload: JWTPayload,
     Query(params): Query<UserFindRequest>,
 ) -> Result<Json<UserList>, AppError> {
-    let users = UserList::find(params, &state.conn).await?;
+    let users = UserList::find(params, payload.id, &state.conn).await?;
     event!(Level::DEBUG, "conditional find users: [{:?}]", users);
     Ok(Json(users))
 }
@@ -1,13 +1,20 @@
 use super::*;
+use crate::metrics::{Push, METRICS};
 
+use axum::extract::ws::{close_code, CloseFrame};
 use futures::{sink::SinkExt, stream::StreamExt};
 use std::time::Duration;
 use tokio::time::timeout;
 
+/// 停机时关闭全部 websocket 的最长时间
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+type WebSocketSender = Arc<Mutex<SplitSink<WebSocket, WebSocketMessage>>>;
+
 #[doc(hidden)]
 #[derive(Clone, Debug, Default)]
 pub struct WebSocketPool {
-    senders: Arc<DashMap<Uuid, Arc<Mutex<SplitSink<WebSocket, WebSocketMessage>>>>>,
+    senders: Arc<DashMap<Uuid, WebSocketSender>>,
 }
 
 impl WebSocketPool {
@@ -26,13 +33,66 @@ impl WebSocketPool {
 
     #[instrument(skip(self))]
     pub async fn notify(&self, user: Uuid, message: WebSocketMessage) {
-        if let Some(ws) = self.senders.get_mut(&user) {
+        let result = match self.senders.get_mut(&user) {
+            Some(ws) => {
+                event!(
+                    Level::INFO,
+                    "websocket sent message [{message:?}] to user [{user}]",
+                );
+                match ws.lock().await.send(message).await {
+                    Ok(()) => Push::Sent,
+                    Err(_) => Push::Failed,
+                }
+            }
+            None => Push::Offline,
+        };
+        METRICS.push(result);
+    }
+
+    /// 已注册的 websocket 连接数
+    pub fn connections(&self) -> usize {
+        self.senders.len()
+    }
+
+    /// 停机时关闭所有 websocket
+    ///
+    /// 并发发送关闭码 `1012` (service restart), 客户端收到后应重新连接;
+    /// 超过 [`CLOSE_TIMEOUT`] 仍未完成的连接直接丢弃
+    #[instrument(skip(self))]
+    pub async fn close_all(&self) {
+        let senders: Vec<_> = self
+            .senders
+            .iter()
+            .map(|e| (*e.key(), e.value().clone()))
+            .collect();
+        self.senders.clear();
+        let closing = senders.into_iter().map(|(user, ws)| async move {
+            let mut ws = ws.lock().await;
+            let frame = CloseFrame {
+                code: close_code::RESTART,
+                reason: "server restarting, reconnect".into(),
+            };
+            if let Err(e) = ws.send(WebSocketMessage::Close(Some(frame))).await {
+                event!(
+                    Level::WARN,
+                    "cannot close websocket for user [{user}]: {e:?}"
+                );
+                return;
+            }
+            let _ = ws.close().await;
+        });
+        if timeout(CLOSE_TIMEOUT, futures::future::join_all(closing))
+            .await
+            .is_err()
+        {
             event!(
-                Level::INFO,
-                "websocket sent message [{message:?}] to user [{user}]",
+                Level::WARN,
+                "websockets not closed within {}s",
+                CLOSE_TIMEOUT.as_secs()
             );
-            ws.lock().await.send(message).await.ok();
+            return;
         }
+        event!(Level::INFO, "closed all websockets");
     }
 }
 
@@ -41,31 +101,23 @@ pub async fn ws_upgrade_handler(
     ws: WebSocketUpgrade,
     State(state): State<AppState>,
 ) -> Result<impl IntoResponse, AppError> {
-    Ok(ws.on_upgrade(move |mut socket| async {
+    Ok(ws.on_upgrade(move |mut socket| async move {
         let msg = timeout(Duration::from_millis(2000), socket.recv()).await;
         if let Ok(msg) = msg {
-            if let Some(msg) = msg {
-                if let Ok(msg) = msg {
-                    match msg {
-                        WebSocketMessage::Text(t) => {
-                            let token: Result<JWTPayload, AppError> = t.as_str().try_into();
-                            match token {
-                                Ok(payload) => {
-                                    let mut pool = state.ws_pool;
-                                    pool.register(payload.id, socket).await;
-                                }
-                                Err(e) => {
-                                    event!(Level::ERROR, "websocket received invalid jwt [{e:?}]",);
-                                    return;
-                                }
-                            }
-                        }
-                        _ => {
-                            return;
-                        }
+            if let Some(Ok(WebSocketMessage::Text(t))) = msg {
+                let token: Result<JWTPayload, AppError> = t.as_str().try_into();
+                let token = match token {
+                    Ok(payload) => payload.check_active(&state.conn).await.map(|_| payload),
+                    Err(e) => Err(e),
+                };
+                match token {
+                    Ok(payload) => {
+                        let mut pool = state.ws_pool;
+                        pool.register(payload.id, socket).await;
+                    }
+                    Err(e) => {
+                        event!(Level::ERROR, "websocket received invalid jwt [{e:?}]",);
                     }
-                } else {
-                    return;
                 }
             }
         } else {